@@ -39,6 +39,15 @@ macro_rules! group_bench_all_tables {
                 ),
                 $f,
             );
+            $group.bench_with_input(
+                BenchmarkId::new("treap", len),
+                &(
+                    treap::CumulFreqTable::<$t>::new(len),
+                    rand_pos.clone(),
+                    dist_pos,
+                ),
+                $f,
+            );
         }
     };
 }
@@ -138,6 +147,52 @@ fn inc_freq(c: &mut Criterion) {
     });
 }
 
+fn bench_find<T: CumulFreqTable>(
+    group: &mut criterion::BenchmarkGroup<criterion::measurement::WallTime>,
+    name: &str,
+    table: T,
+    rand_sum: &StdRng,
+) {
+    let len = table.len();
+    let dist_sum = Uniform::from(1..=table.total());
+    group.bench_with_input(
+        BenchmarkId::new(name, len),
+        &(table, rand_sum.clone(), dist_sum),
+        |b, (table, rand_sum, dist_sum)| {
+            let mut rand_sum = rand_sum.clone();
+            b.iter(|| table.find_by_sum(rand_sum.sample(dist_sum)))
+        },
+    );
+}
+
+fn find_by_sum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_by_sum_usize");
+    group.plot_config(PlotConfiguration::default().summary_scale(AxisScale::Logarithmic));
+    let mut rand_freq = StdRng::from_entropy();
+    let rand_sum = StdRng::from_entropy();
+    for i in (4..=20).step_by(4) {
+        // Half way between two powers of two, so the padding adds a quarter of the nodes.
+        let len = 3 << (i - 1);
+        group.throughput(criterion::Throughput::Elements(len as u64));
+        // Near-uniform, the worst case for the branches of the binary search.
+        let freqs: Vec<usize> = (0..len).map(|_| rand_freq.gen_range(1..=4)).collect();
+        let bit = binary_indexed_tree::CumulFreqTable::from_freqs(freqs.iter().copied());
+        bench_find(&mut group, "binary_indexed_tree", bit.clone(), &rand_sum);
+        bench_find(
+            &mut group,
+            "binary_indexed_tree_padded",
+            bit.padded(),
+            &rand_sum,
+        );
+        bench_find(
+            &mut group,
+            "treap",
+            treap::CumulFreqTable::from_freqs(freqs),
+            &rand_sum,
+        );
+    }
+}
+
 fn config() -> Criterion {
     use std::time::Duration;
     Criterion::default()
@@ -146,6 +201,6 @@ fn config() -> Criterion {
 }
 
 criterion_group!(name = benches; config = config();
-    targets = inc, inc_cumul, inc_total, inc_cumul_total, inc_freq,
+    targets = inc, inc_cumul, inc_total, inc_cumul_total, inc_freq, find_by_sum,
 );
 criterion_main!(benches);
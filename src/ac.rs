@@ -0,0 +1,267 @@
+//! Adaptive arithmetic coding driven by a [CumulFreqTable].
+//!
+//! Cumulative frequency tables were introduced by Fenwick for exactly this purpose: the coder
+//! needs the cumulative interval of every encoded symbol, and the decoder needs to find the symbol
+//! from a cumulative frequency.
+//!
+//! The [Encoder] and [Decoder] own the model. Every symbol is coded with the current frequencies,
+//! then its frequency is incremented. Both sides must start from identical tables.
+//!
+//! This is the classic 32 bits integer coder from Witten, Neal and Cleary (1987). The total of the
+//! table must stay below 2^30, and a coded symbol must have a non-zero frequency.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cumulfreqtable::{ac, CumulFreqTable, FreqTable};
+//!
+//! let message = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+//!
+//! let mut encoder = ac::Encoder::new(FreqTable::<u32>::with_freq(10, 1), Vec::new());
+//! for &symbol in &message {
+//!     encoder.encode(symbol).unwrap();
+//! }
+//! let (_, bytes) = encoder.finish().unwrap();
+//!
+//! let model = FreqTable::<u32>::with_freq(10, 1);
+//! let mut decoder = ac::Decoder::new(model, bytes.as_slice()).unwrap();
+//! for &symbol in &message {
+//!     assert_eq!(decoder.decode().unwrap(), symbol);
+//! }
+//! ```
+
+use crate::CumulFreqTable;
+use std::io::{self, Read, Write};
+
+const BITS: u32 = 32;
+const FULL: u64 = (1 << BITS) - 1;
+const HALF: u64 = 1 << (BITS - 1);
+const QUARTER: u64 = 1 << (BITS - 2);
+
+/// The largest total supported by the coder.
+pub const MAX_TOTAL: u64 = QUARTER;
+
+fn to_u64<F: TryInto<u64>>(freq: F) -> u64 {
+    match freq.try_into() {
+        Ok(freq) => freq,
+        Err(_) => panic!("frequency doesn't fit in u64"),
+    }
+}
+
+fn from_u64<F>(val: u64) -> F
+where
+    u64: TryInto<F>,
+{
+    match val.try_into() {
+        Ok(val) => val,
+        Err(_) => panic!("cumulative frequency doesn't fit in F"),
+    }
+}
+
+fn check_total(total: u64) {
+    assert!(total > 0, "total must be non-zero");
+    assert!(total <= MAX_TOTAL, "total must be <= 2^30");
+}
+
+/// Encode symbols with an adaptive model, writing the bits to W.
+#[derive(Debug)]
+pub struct Encoder<T, W: Write> {
+    model: T,
+    writer: W,
+    low: u64,
+    high: u64,
+    pending: u64,
+    byte: u8,
+    nbits: u32,
+}
+
+impl<T, W: Write> Encoder<T, W> {
+    /// Create an encoder with the initial model.
+    pub fn new(model: T, writer: W) -> Self {
+        Self {
+            model,
+            writer,
+            low: 0,
+            high: FULL,
+            pending: 0,
+            byte: 0,
+            nbits: 0,
+        }
+    }
+
+    /// The current model.
+    pub fn model(&self) -> &T {
+        &self.model
+    }
+
+    /// Encode the symbol at the given position, then increment its frequency.
+    /// Panics if the symbol has a zero frequency or if the total exceeds [MAX_TOTAL].
+    pub fn encode<F>(&mut self, pos: usize) -> io::Result<()>
+    where
+        T: CumulFreqTable<F>,
+        F: From<u8> + Copy + TryInto<u64>,
+    {
+        let total = to_u64(self.model.total());
+        check_total(total);
+        let (low, high) = self.model.bounds(pos);
+        let (low, high) = (to_u64(low), to_u64(high));
+        assert!(low < high, "symbol has a zero frequency");
+
+        let range = self.high - self.low + 1;
+        self.high = self.low + range * high / total - 1;
+        self.low += range * low / total;
+        loop {
+            if self.high < HALF {
+                self.write_bit_pending(false)?;
+            } else if self.low >= HALF {
+                self.write_bit_pending(true)?;
+                self.low -= HALF;
+                self.high -= HALF;
+            } else if self.low >= QUARTER && self.high < HALF + QUARTER {
+                self.pending += 1;
+                self.low -= QUARTER;
+                self.high -= QUARTER;
+            } else {
+                break;
+            }
+            self.low *= 2;
+            self.high = self.high * 2 + 1;
+        }
+
+        self.model.inc(pos);
+        Ok(())
+    }
+
+    /// Flush the remaining bits and return the model and the writer.
+    pub fn finish(mut self) -> io::Result<(T, W)> {
+        // Two more bits select a quarter within the final interval.
+        self.pending += 1;
+        self.write_bit_pending(self.low >= QUARTER)?;
+        if self.nbits > 0 {
+            self.byte <<= 8 - self.nbits;
+            self.writer.write_all(&[self.byte])?;
+        }
+        self.writer.flush()?;
+        Ok((self.model, self.writer))
+    }
+
+    fn write_bit_pending(&mut self, bit: bool) -> io::Result<()> {
+        self.write_bit(bit)?;
+        while self.pending > 0 {
+            self.write_bit(!bit)?;
+            self.pending -= 1;
+        }
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.byte = (self.byte << 1) | bit as u8;
+        self.nbits += 1;
+        if self.nbits == 8 {
+            self.writer.write_all(&[self.byte])?;
+            self.nbits = 0;
+        }
+        Ok(())
+    }
+}
+
+/// Decode symbols with an adaptive model, reading the bits from R.
+/// Reading past the end of R yields zero bits. Consider wrapping R in a [std::io::BufReader].
+#[derive(Debug)]
+pub struct Decoder<T, R: Read> {
+    model: T,
+    reader: R,
+    low: u64,
+    high: u64,
+    value: u64,
+    byte: u8,
+    nbits: u32,
+}
+
+impl<T, R: Read> Decoder<T, R> {
+    /// Create a decoder with the initial model, identical to the encoder's.
+    pub fn new(model: T, reader: R) -> io::Result<Self> {
+        let mut decoder = Self {
+            model,
+            reader,
+            low: 0,
+            high: FULL,
+            value: 0,
+            byte: 0,
+            nbits: 0,
+        };
+        for _ in 0..BITS {
+            decoder.value = decoder.value * 2 + decoder.read_bit()? as u64;
+        }
+        Ok(decoder)
+    }
+
+    /// The current model.
+    pub fn model(&self) -> &T {
+        &self.model
+    }
+
+    /// Return the model and the reader.
+    pub fn into_inner(self) -> (T, R) {
+        (self.model, self.reader)
+    }
+
+    /// Decode the next symbol, then increment its frequency.
+    /// Panics if the total exceeds [MAX_TOTAL].
+    pub fn decode<F>(&mut self) -> io::Result<usize>
+    where
+        T: CumulFreqTable<F>,
+        F: From<u8> + Copy + TryInto<u64>,
+        u64: TryInto<F>,
+    {
+        let total = to_u64(self.model.total());
+        check_total(total);
+        let range = self.high - self.low + 1;
+        let target = ((self.value - self.low + 1) * total - 1) / range;
+        // The symbol is the first with a cumulative frequency strictly greater than the target.
+        let (pos, low, high) = self.model.find_with_bounds(from_u64(target + 1));
+        let (low, high) = (to_u64(low), to_u64(high));
+
+        self.high = self.low + range * high / total - 1;
+        self.low += range * low / total;
+        loop {
+            // Mirror the encoder, where high < HALF emits a bit without shifting the interval.
+            if self.high >= HALF {
+                if self.low >= HALF {
+                    self.value -= HALF;
+                    self.low -= HALF;
+                    self.high -= HALF;
+                } else if self.low >= QUARTER && self.high < HALF + QUARTER {
+                    self.value -= QUARTER;
+                    self.low -= QUARTER;
+                    self.high -= QUARTER;
+                } else {
+                    break;
+                }
+            }
+            self.low *= 2;
+            self.high = self.high * 2 + 1;
+            self.value = self.value * 2 + self.read_bit()? as u64;
+        }
+
+        self.model.inc(pos);
+        Ok(pos)
+    }
+
+    fn read_bit(&mut self) -> io::Result<bool> {
+        if self.nbits == 0 {
+            let mut buf = [0];
+            self.byte = loop {
+                match self.reader.read(&mut buf) {
+                    Ok(0) => break 0,
+                    Ok(_) => break buf[0],
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            };
+            self.nbits = 8;
+        }
+        self.nbits -= 1;
+        Ok((self.byte >> self.nbits) & 1 == 1)
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{CumulFreqTable, Frequency};
+use std::ops::Div;
+
+/// How to rescale the frequencies when the total exceeds the ceiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rescale {
+    /// Halve rounding up: a seen position never drops to zero.
+    #[default]
+    HalveUp,
+    /// Halve rounding down: rare positions are forgotten.
+    HalveDown,
+}
+
+/// An adaptive model: a table where every observation increments the frequency of its position.
+/// When the total exceeds a ceiling, all the frequencies are halved, rounding up so that a seen
+/// position never drops to zero. Halving the counts also lets the model adapt faster to recent
+/// observations. See [AdaptiveModel::on_total_exceeds] to choose another [Rescale].
+///
+/// Halving cannot reduce frequencies of 1, so the ceiling should be well above the number of
+/// positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdaptiveModel<T, F = usize> {
+    table: T,
+    max_total: F,
+    rescale: Rescale,
+}
+
+impl<T, F> AdaptiveModel<T, F>
+where
+    T: CumulFreqTable<F>,
+    F: Frequency + Div<Output = F>,
+{
+    /// Wrap the table, with the given ceiling for the total. A typical ceiling for arithmetic
+    /// coding is 2^16.
+    pub fn new(table: T, max_total: F) -> Self {
+        Self {
+            table,
+            max_total,
+            rescale: Rescale::default(),
+        }
+    }
+
+    /// Set the ceiling of the total, and how to rescale when it is exceeded.
+    pub fn on_total_exceeds(mut self, max_total: F, rescale: Rescale) -> Self {
+        self.max_total = max_total;
+        self.rescale = rescale;
+        self
+    }
+
+    /// Increment the frequency of the position, then rescale all the frequencies if the total
+    /// exceeds the ceiling.
+    /// Panics if pos is out of bounds.
+    pub fn observe(&mut self, pos: usize) {
+        self.table.inc(pos);
+        if self.table.total() > self.max_total {
+            self.rescale();
+        }
+    }
+
+    /// Add to the frequency of the position. If the total would exceed the ceiling, the
+    /// frequencies are rescaled first, as long as it reduces the total.
+    /// Panics if pos is out of bounds.
+    pub fn add(&mut self, pos: usize, val: F) {
+        let mut total = self.table.total();
+        while total + val > self.max_total {
+            self.rescale();
+            let rescaled = self.table.total();
+            if rescaled >= total {
+                break;
+            }
+            total = rescaled;
+        }
+        self.table.add(pos, val);
+    }
+
+    fn rescale(&mut self) {
+        match self.rescale {
+            Rescale::HalveUp => self.table.halve_up(),
+            Rescale::HalveDown => self.table.halve_down(),
+        }
+    }
+
+    /// The ceiling of the total.
+    pub fn max_total(&self) -> F {
+        self.max_total
+    }
+
+    /// How the frequencies are rescaled.
+    pub fn rescale_strategy(&self) -> Rescale {
+        self.rescale
+    }
+
+    /// The memory used in bytes, inline and on the heap, see [CumulFreqTable::memory_bytes].
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<T>() + self.table.memory_bytes()
+    }
+
+    /// The underlying table.
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Return the underlying table.
+    pub fn into_inner(self) -> T {
+        self.table
+    }
+}
@@ -0,0 +1,463 @@
+//! A binary indexed tree for frequency types that are [Clone] but not [Copy], enabled by the
+//! `bigint` feature.
+//!
+//! The other implementations copy frequencies around freely, which rules out arbitrary precision
+//! integers like [num_bigint::BigUint]. This tree only borrows its nodes when adding them up, and
+//! clones a frequency when it has to return one. Every returned frequency is a fresh clone, so
+//! [crate::CumulFreqTable::sum] and friends allocate for big values.
+//!
+//! The methods of [crate::CumulFreqTable] that require [Copy], like
+//! [crate::CumulFreqTable::set] or [crate::CumulFreqTable::add_range], are not available.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cumulfreqtable::{BigTable, CumulFreqTable};
+//! use num_bigint::BigUint;
+//!
+//! let mut table = BigTable::new(4);
+//! table.add(1, BigUint::from(u128::MAX));
+//! table.add(3, BigUint::from(u128::MAX));
+//! assert_eq!(table.total(), BigUint::from(u128::MAX) * 2u8);
+//! assert_eq!(table.find_by_sum(BigUint::from(u128::MAX) + 1u8), 3);
+//! ```
+
+use crate::{Checked, OverflowError};
+use num_bigint::BigUint;
+use std::ops::{AddAssign, SubAssign};
+
+impl Checked for BigUint {
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        if self >= rhs {
+            Some(self - rhs)
+        } else {
+            None
+        }
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+}
+
+/// Store the cumulative frequencies with a binary indexed tree, without copying the frequencies.
+/// Position 0 is stored in the first element, outside of the tree, unlike the 1-based
+/// [crate::BinaryIndexedTree].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CumulFreqTable<F = BigUint> {
+    tree: Vec<F>,
+}
+
+impl<F> CumulFreqTable<F>
+where
+    F: From<u8> + Clone + for<'a> AddAssign<&'a F>,
+{
+    /// The largest power of two smaller than len, where the binary searches start.
+    fn search_start(&self) -> usize {
+        (((self.tree.len() - 1) / 2) + 1).next_power_of_two()
+    }
+
+    /// Replace the frequencies in the tree by the cumulative frequencies of the nodes. O(len).
+    fn build(&mut self) {
+        let len = self.tree.len();
+        for node in 1..len {
+            let parent = node + (1 << node.trailing_zeros());
+            if parent < len {
+                // The child is always below its parent.
+                let (children, parents) = self.tree.split_at_mut(parent);
+                parents[0] += &children[node];
+            }
+        }
+    }
+
+    /// The inverse of [Self::build]: replace the nodes by the frequencies. O(len).
+    fn unbuild(&mut self)
+    where
+        F: for<'a> SubAssign<&'a F>,
+    {
+        let len = self.tree.len();
+        for node in (1..len).rev() {
+            let parent = node + (1 << node.trailing_zeros());
+            if parent < len {
+                let (children, parents) = self.tree.split_at_mut(parent);
+                parents[0] -= &children[node];
+            }
+        }
+    }
+
+    /// Whether check succeeds on every node of the update path of pos.
+    fn check_path(&self, mut pos: usize, check: impl Fn(F) -> Option<F>) -> bool {
+        if pos == 0 {
+            return check(self.tree[0].clone()).is_some();
+        }
+        while pos < self.tree.len() {
+            if check(self.tree[pos].clone()).is_none() {
+                return false;
+            }
+            pos += 1 << pos.trailing_zeros();
+        }
+        true
+    }
+
+    /// Build the tree in place from the frequencies. O(len).
+    pub(crate) fn from_vec(freqs: Vec<F>) -> Self {
+        assert!(!freqs.is_empty(), "table must be non-empty");
+        let mut table = Self { tree: freqs };
+        table.build();
+        table
+    }
+}
+
+impl<F> super::CumulFreqTable<F> for CumulFreqTable<F>
+where
+    F: From<u8> + Clone + PartialOrd + for<'a> AddAssign<&'a F> + for<'a> SubAssign<&'a F>,
+{
+    /// Panics if len < 1.
+    fn new(len: usize) -> Self {
+        assert!(len > 0, "table must be non-empty");
+        Self {
+            tree: vec![0.into(); len],
+        }
+    }
+
+    /// Panics if len < 1.
+    /// O(len).
+    fn with_freq(len: usize, init: F) -> Self
+    where
+        usize: TryInto<F>,
+        <usize as TryInto<F>>::Error: std::fmt::Debug,
+    {
+        assert!(len > 0, "table must be non-empty");
+        Self::from_vec(vec![init; len])
+    }
+
+    /// Panics if freqs is empty.
+    /// O(len).
+    fn from_freqs<I: IntoIterator<Item = F>>(freqs: I) -> Self {
+        Self::from_vec(freqs.into_iter().collect())
+    }
+
+    /// O(1).
+    fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Counts the inline part of every F only, the digits of a BigUint are not included.
+    /// O(1).
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.tree.capacity() * std::mem::size_of::<F>()
+    }
+
+    /// Panics if new_len < 1.
+    /// Panics if the removed frequencies don't sum to zero.
+    /// O(㏒₂ len) when shrinking, O(new_len - len + ㏒₂ len) when growing.
+    fn resize(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        let len = self.tree.len();
+        if new_len < len {
+            assert!(
+                self.sum(new_len - 1) == self.total(),
+                "removed frequencies must sum to zero"
+            );
+            self.truncate(new_len);
+        } else {
+            self.extend_with(new_len - len, 0.into());
+        }
+    }
+
+    /// Panics if new_len < 1.
+    /// O(1).
+    fn truncate(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        self.tree.truncate(new_len);
+    }
+
+    /// O(extra + ㏒₂ len).
+    fn extend_with(&mut self, extra: usize, init: F) {
+        self.tree.reserve(extra);
+        for _ in 0..extra {
+            self.push(init.clone());
+        }
+    }
+
+    /// Amortized O(㏒₂ len).
+    fn push(&mut self, freq: F) {
+        let pos = self.tree.len();
+        let parent = pos - (1 << pos.trailing_zeros());
+        let mut node = freq;
+        let mut child = pos - 1;
+        while child != parent {
+            node += &self.tree[child];
+            child -= 1 << child.trailing_zeros();
+        }
+        self.tree.push(node);
+    }
+
+    /// Panics if len < 2.
+    /// O(㏒₂ len).
+    fn pop(&mut self) -> F {
+        assert!(self.tree.len() > 1, "table must be non-empty");
+        let freq = self.freq(self.tree.len() - 1);
+        self.tree.pop();
+        freq
+    }
+
+    /// O(len).
+    fn reserve(&mut self, additional: usize) {
+        self.tree.reserve(additional);
+    }
+
+    /// O(len).
+    fn shrink_to_fit(&mut self) {
+        self.tree.shrink_to_fit();
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len).
+    fn add(&mut self, mut pos: usize, val: F) {
+        assert!(pos < self.tree.len(), "pos out of bounds");
+        if pos == 0 {
+            self.tree[0] += &val;
+        } else {
+            while pos < self.tree.len() {
+                self.tree[pos] += &val;
+                pos += 1 << pos.trailing_zeros();
+            }
+        }
+    }
+
+    /// Panics if pos is out of bounds.
+    /// Panics on underflow.
+    /// O(㏒₂ len).
+    fn sub(&mut self, mut pos: usize, val: F) {
+        assert!(pos < self.tree.len(), "pos out of bounds");
+        if pos == 0 {
+            self.tree[0] -= &val;
+        } else {
+            while pos < self.tree.len() {
+                self.tree[pos] -= &val;
+                pos += 1 << pos.trailing_zeros();
+            }
+        }
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len).
+    fn try_add(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.tree.len(), "pos out of bounds");
+        if self.freq(pos).checked_add(val.clone()).is_none()
+            || self.total().checked_add(val.clone()).is_none()
+            || !self.check_path(pos, |node| node.checked_add(val.clone()))
+        {
+            return Err(OverflowError);
+        }
+        self.add(pos, val);
+        Ok(())
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len).
+    fn try_sub(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.tree.len(), "pos out of bounds");
+        if self.freq(pos).checked_sub(val.clone()).is_none()
+            || self.total().checked_sub(val.clone()).is_none()
+            || !self.check_path(pos, |node| node.checked_sub(val.clone()))
+        {
+            return Err(OverflowError);
+        }
+        self.sub(pos, val);
+        Ok(())
+    }
+
+    /// O(len).
+    fn clear(&mut self) {
+        self.tree.fill(0.into());
+    }
+
+    /// O(len).
+    fn fill(&mut self, init: F) {
+        self.tree.fill(init);
+        self.build();
+    }
+
+    /// O(len).
+    fn reverse(&mut self) {
+        self.unbuild();
+        self.tree.reverse();
+        self.build();
+    }
+
+    /// Panics if k > len.
+    /// O(len).
+    fn rotate_left(&mut self, k: usize) {
+        assert!(k <= self.tree.len(), "k out of bounds");
+        self.unbuild();
+        self.tree.rotate_left(k);
+        self.build();
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len).
+    fn sum(&self, mut pos: usize) -> F {
+        assert!(pos < self.tree.len(), "pos out of bounds");
+        let mut sum = self.tree[0].clone();
+        while pos > 0 {
+            sum += &self.tree[pos];
+            pos -= 1 << pos.trailing_zeros();
+        }
+        sum
+    }
+
+    /// O(㏒₂ len).
+    fn total(&self) -> F {
+        self.sum(self.len() - 1)
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len).
+    fn freq(&self, mut pos: usize) -> F {
+        assert!(pos < self.tree.len(), "pos out of bounds");
+        let mut freq = self.tree[pos].clone();
+        if pos > 0 {
+            let parent = pos - (1 << pos.trailing_zeros());
+            pos -= 1;
+            while parent != pos {
+                freq -= &self.tree[pos];
+                pos -= 1 << pos.trailing_zeros();
+            }
+        }
+        freq
+    }
+
+    /// O(㏒₂ len).
+    fn find_by_sum(&self, mut sum: F) -> usize {
+        if sum <= self.tree[0] {
+            return 0;
+        }
+        sum -= &self.tree[0];
+        let mut pos = 0;
+        let mut mid = self.search_start();
+        while mid != 0 {
+            let hi = pos + mid;
+            if hi < self.len() && self.tree[hi] < sum {
+                pos = hi;
+                sum -= &self.tree[pos];
+            }
+            mid /= 2;
+        }
+        (pos + 1).min(self.len() - 1)
+    }
+
+    /// O(㏒₂ len).
+    fn find_by_sum_strict(&self, mut sum: F) -> usize {
+        if sum < self.tree[0] {
+            return 0;
+        }
+        sum -= &self.tree[0];
+        let mut pos = 0;
+        let mut mid = self.search_start();
+        while mid != 0 {
+            let hi = pos + mid;
+            if hi < self.len() && self.tree[hi] <= sum {
+                pos = hi;
+                sum -= &self.tree[pos];
+            }
+            mid /= 2;
+        }
+        (pos + 1).min(self.len() - 1)
+    }
+
+    /// O(len).
+    /// scale_freq is called O(len) times (once per position).
+    fn scale<C: Fn(F) -> F>(&mut self, scale_freq: C) {
+        self.unbuild();
+        for freq in self.tree.iter_mut() {
+            *freq = scale_freq(std::mem::replace(freq, 0.into()));
+        }
+        self.build();
+    }
+
+    /// O(len).
+    /// scale_freq is called O(len) times (once per position).
+    fn scale_ret<C: Fn(F) -> F>(&mut self, scale_freq: C) -> F {
+        self.unbuild();
+        let mut total: F = 0.into();
+        for freq in self.tree.iter_mut() {
+            *freq = scale_freq(std::mem::replace(freq, 0.into()));
+            total += &*freq;
+        }
+        self.build();
+        total
+    }
+
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    fn merge_add<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(
+            self.tree.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        self.unbuild();
+        for (pos, freq) in self.tree.iter_mut().enumerate() {
+            *freq += &other.freq(pos);
+        }
+        self.build();
+    }
+
+    /// Panics if the tables have different lengths.
+    /// Panics on underflow.
+    /// O(len).
+    fn merge_sub<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(
+            self.tree.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        self.unbuild();
+        for (pos, freq) in self.tree.iter_mut().enumerate() {
+            *freq -= &other.freq(pos);
+        }
+        self.build();
+    }
+
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    /// combine is called O(len) times (once per position).
+    fn combine_with<T, C>(&mut self, other: &T, combine: C)
+    where
+        T: super::CumulFreqTable<F>,
+        C: Fn(F, F) -> F,
+    {
+        assert_eq!(
+            self.tree.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        self.unbuild();
+        for (pos, freq) in self.tree.iter_mut().enumerate() {
+            *freq = combine(std::mem::replace(freq, 0.into()), other.freq(pos));
+        }
+        self.build();
+    }
+}
+
+impl<F> Default for CumulFreqTable<F>
+where
+    F: From<u8> + Clone + PartialOrd + for<'a> AddAssign<&'a F> + for<'a> SubAssign<&'a F>,
+{
+    /// A single position with a zero frequency, tables are never empty.
+    fn default() -> Self {
+        <Self as super::CumulFreqTable<F>>::new(1)
+    }
+}
@@ -1,4 +1,6 @@
-use std::ops::{AddAssign, Shl, Sub, SubAssign};
+use crate::kahan::KahanSum;
+use crate::{Checked, Frequency, OverflowError};
+use std::fmt;
 
 /// store the cumulative frequencies with a binary indexed tree in an array.
 /// just as an integer is the sum of appropriate powers of two, so can a cumulative frequency be
@@ -10,26 +12,335 @@ use std::ops::{AddAssign, Shl, Sub, SubAssign};
 ///
 /// It is slightly slower than [crate::FreqTable] for small tables depending on the computer. See
 /// the [module][crate#benchmarks] documentation for more details.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// The alternate [Debug](fmt::Debug) format, `{:#?}`, shows the `(freq, sum)` of every position
+/// and the nodes of every level of the tree, with the positions they cover.
+///
+/// The total is maintained as a separate value, compensated like [crate::FreqTable], so
+/// [crate::CumulFreqTable::total] is O(1). So is the number of non-zero frequencies, for
+/// [crate::CumulFreqTable::count_nonzero].
+///
+/// With [Self::padded], the tree is rounded up to a power of two nodes, so the binary searches
+/// never go past the end and skip the bounds checks.
+#[derive(Clone)]
 pub struct CumulFreqTable<F = usize> {
-    tree: Box<[F]>,
+    // 1-based: the node of position pos is pos + 1, and node 0 is always zero. So no position is
+    // special on the update and query paths. The nodes after len cover zero frequencies only.
+    tree: Vec<F>,
+    len: usize,
+    total: KahanSum<F>,
+    nonzero: usize,
+    padded: bool,
+}
+
+impl<F> CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    /// Build the tree from the frequencies of every position, with the linear construction
+    /// instead of an O(㏒₂ len) add per position. Like [crate::CumulFreqTable::from_freqs], from a
+    /// slice.
+    /// Panics if counts is empty.
+    /// O(len).
+    pub fn from_counts(counts: &[F]) -> Self {
+        Self::from_vec(counts.to_vec())
+    }
+
+    /// Round the tree up to a power of two nodes, kept as the table grows. The binary searches
+    /// start at the last node, and every node they visit is in bounds, so they skip the bounds
+    /// checks. Up to twice the memory.
+    /// O(len).
+    pub fn padded(mut self) -> Self {
+        self.padded = true;
+        self.pad();
+        self
+    }
+
+    /// Whether the tree is rounded up to a power of two nodes, see [Self::padded].
+    pub fn is_padded(&self) -> bool {
+        self.padded
+    }
+
+    /// Push zero nodes up to a power of two nodes when padded. O(added nodes).
+    fn pad(&mut self) {
+        if self.padded {
+            let nodes = (self.tree.len() - 1).next_power_of_two();
+            self.tree.reserve(nodes + 1 - self.tree.len());
+            while self.tree.len() <= nodes {
+                self.push_node(0.into());
+            }
+        }
+    }
+
+    /// Append the node after the last one, for a frequency after the last node.
+    fn push_node(&mut self, freq: F) {
+        let new = self.tree.len();
+        // The new node covers the positions after its parent. Collect them from its children.
+        let parent = new - (1 << new.trailing_zeros());
+        let mut node = freq;
+        let mut child = new - 1;
+        while child != parent {
+            node += self.tree[child];
+            child -= 1 << child.trailing_zeros();
+        }
+        self.tree.push(node);
+    }
+
+    /// The largest power of two smaller or equal to the number of nodes, where the binary searches
+    /// start.
+    fn search_start(&self) -> usize {
+        // There is always at least one node.
+        let nodes = self.tree.len() - 1;
+        1 << (usize::BITS - 1 - nodes.leading_zeros())
+    }
+
+    /// A step of the binary searches: whether to take the node pos + mid, with its index and value.
+    /// Without branches, so they compile to conditional moves: unless CHECKED is false, the node
+    /// is clamped to the last one and only taken when in bounds, and the bounds are checked with a
+    /// non short-circuiting `&`. Near-uniform distributions make the branches unpredictable.
+    #[inline(always)]
+    fn descend<const CHECKED: bool>(
+        &self,
+        pos: usize,
+        mid: usize,
+        below: impl Fn(F) -> bool,
+    ) -> (bool, usize, F) {
+        let hi = pos + mid;
+        let len = self.tree.len();
+        if CHECKED {
+            // SAFETY: tables are non-empty, the index is clamped.
+            let node = unsafe { *self.tree.get_unchecked(hi.min(len - 1)) };
+            ((hi < len) & below(node), hi, node)
+        } else {
+            // SAFETY: the caller checks that the number of nodes is a power of two, and that the
+            // last node is not taken. So pos + mid stays below it.
+            let node = unsafe { *self.tree.get_unchecked(hi) };
+            (below(node), hi, node)
+        }
+    }
+
+    /// The binary search of the find methods: the number of positions whose cumulative frequency
+    /// is below sum, possibly past the end.
+    #[inline(always)]
+    fn search(&self, mut sum: F, below: impl Fn(F, F) -> bool) -> usize {
+        let mut pos = 0;
+        let mut mid = self.search_start();
+        if (self.tree.len() - 1).is_power_of_two() {
+            // The last node covers every position. Past the end if it is taken, otherwise the
+            // nodes after it are never visited.
+            if below(self.tree[mid], sum) {
+                return mid;
+            }
+            mid /= 2;
+            while mid != 0 {
+                let (take, hi, node) = self.descend::<false>(pos, mid, |node| below(node, sum));
+                pos = if take { hi } else { pos };
+                sum -= if take { node } else { 0.into() };
+                mid /= 2;
+            }
+        } else {
+            while mid != 0 {
+                let (take, hi, node) = self.descend::<true>(pos, mid, |node| below(node, sum));
+                pos = if take { hi } else { pos };
+                sum -= if take { node } else { 0.into() };
+                mid /= 2;
+            }
+        }
+        pos
+    }
+
+    /// Hint the CPU to load the node in the cache, ahead of its use on the update and query paths.
+    /// On large tables, the next node is loaded while the current one is, instead of after.
+    /// The node may be out of bounds: a prefetch never faults. Does nothing on other
+    /// architectures than x86_64.
+    #[inline(always)]
+    fn prefetch(&self, node: usize) {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: prefetching has no side effect, and the pointer is never dereferenced.
+        unsafe {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            _mm_prefetch::<_MM_HINT_T0>(self.tree.as_ptr().wrapping_add(node) as *const i8);
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = node;
+    }
+
+    /// Whether check succeeds on every node of the update path of pos.
+    fn check_path(&self, pos: usize, check: impl Fn(F) -> Option<F>) -> bool {
+        let mut node = pos + 1;
+        while node < self.tree.len() {
+            if check(self.tree[node]).is_none() {
+                return false;
+            }
+            node += 1 << node.trailing_zeros();
+        }
+        true
+    }
+
+    /// The value of the node i when every frequency is init.
+    fn uniform_node(i: usize, init: F) -> F {
+        if i == 0 {
+            return 0.into();
+        }
+        // A node covers 2^trailing_zeros positions. Doubling instead of shifting supports
+        // floating point frequencies. The trailing zeros sum to less than len over all the nodes.
+        let mut node = init;
+        for _ in 0..i.trailing_zeros() {
+            node += node;
+        }
+        node
+    }
+
+    /// Apply val to every position in [first, last] with op.
+    /// Like the linear construction, the change of every node is propagated to its parent. All the
+    /// nodes after last that cover positions in the range are on the update path of last.
+    /// O(last - first + ㏒₂ len): every node within the range changes, so a range covering the
+    /// whole table is O(len). The nodes hold plain cumulative frequencies, unlike a second tree of
+    /// range increments which would make every query pay for the range updates.
+    fn range_update(&mut self, first: usize, last: usize, val: F, op: fn(&mut F, F)) {
+        let len = self.tree.len();
+        let (first, last) = (first + 1, last + 1);
+        // Change of the nodes within the range, accumulated from their children.
+        let mut deltas: Vec<F> = vec![0.into(); last - first + 1];
+        // Changes for the nodes on the update path after last.
+        let mut path: Vec<(usize, F)> = Vec::new();
+        for node in first..=last {
+            let mut delta = deltas[node - first];
+            delta += val;
+            op(&mut self.tree[node], delta);
+            let parent = node + (1 << node.trailing_zeros());
+            if parent <= last {
+                deltas[parent - first] += delta;
+            } else if parent < len {
+                path.push((parent, delta));
+            }
+        }
+        path.sort_unstable_by_key(|&(node, _)| node);
+        let mut path = path.into_iter().peekable();
+        let mut delta: F = 0.into();
+        let mut node = last + (1 << last.trailing_zeros());
+        while node < len {
+            while let Some((_, child)) = path.next_if(|&(parent, _)| parent == node) {
+                delta += child;
+            }
+            op(&mut self.tree[node], delta);
+            node += 1 << node.trailing_zeros();
+        }
+    }
+
+    /// Replace the frequencies in the tree by the cumulative frequencies of the nodes, and count
+    /// the non-zero ones.
+    /// Every node is added to its parent, after its children were added to it. Node 0 stays zero.
+    /// O(len).
+    fn build(&mut self) {
+        self.nonzero = self.tree[1..=self.len]
+            .iter()
+            .filter(|&&freq| freq != 0.into())
+            .count();
+        let len = self.tree.len();
+        for node in 1..len {
+            let parent = node + (1 << node.trailing_zeros());
+            if parent < len {
+                let child = self.tree[node];
+                self.tree[parent] += child;
+            }
+        }
+    }
+
+    /// Compute the total from the tree, after changing many nodes. O(㏒₂ len).
+    fn recount(&mut self) {
+        let last = self.len - 1;
+        // SAFETY: tables are non-empty.
+        let total = unsafe { super::CumulFreqTable::sum_unchecked(self, last) };
+        self.total = KahanSum::new(total);
+    }
+
+    /// The number of non-zero frequencies in [first, last].
+    /// O(last - first + ㏒₂ len): the frequency queries walk the children of the nodes, and the
+    /// nodes of consecutive positions have a single child on average.
+    fn nonzero_in(&self, first: usize, last: usize) -> usize {
+        (first..=last)
+            .filter(|&pos| super::CumulFreqTable::freq(self, pos) != 0.into())
+            .count()
+    }
+
+    /// Account for a frequency changing from old to new.
+    fn track_nonzero(&mut self, old: F, new: F) {
+        self.nonzero += (new != 0.into()) as usize;
+        self.nonzero -= (old != 0.into()) as usize;
+    }
+
+    /// Count every position as non-zero or not, when every frequency is init.
+    fn fill_nonzero(&mut self, init: F) {
+        self.nonzero = if init != 0.into() { self.len } else { 0 };
+    }
+
+    /// Build the tree in place from the frequencies. O(len).
+    pub(crate) fn from_vec(mut freqs: Vec<F>) -> Self {
+        assert!(!freqs.is_empty(), "table must be non-empty");
+        freqs.insert(0, 0.into());
+        let mut table = Self {
+            len: freqs.len() - 1,
+            tree: freqs,
+            total: KahanSum::new(0.into()),
+            nonzero: 0,
+            padded: false,
+        };
+        table.build();
+        table.recount();
+        table
+    }
+
+    /// Copy the frequencies out of the tree. O(len).
+    pub(crate) fn to_vec(&self) -> Vec<F> {
+        self.clone().into_vec()
+    }
+
+    /// Turn the tree into the frequencies in place. O(len).
+    pub(crate) fn into_vec(mut self) -> Vec<F> {
+        self.unbuild();
+        self.tree.truncate(self.len + 1);
+        self.tree.remove(0);
+        self.tree
+    }
+
+    /// The inverse of [Self::build]: replace the nodes by the frequencies. O(len).
+    fn unbuild(&mut self) {
+        let len = self.tree.len();
+        for node in (1..len).rev() {
+            let parent = node + (1 << node.trailing_zeros());
+            if parent < len {
+                let child = self.tree[node];
+                self.tree[parent] -= child;
+            }
+        }
+    }
+
+    /// Replace every frequency by map(pos, freq), between an unbuild and a build of the tree.
+    /// O(len).
+    fn map_in_place(&mut self, mut map: impl FnMut(usize, F) -> F) {
+        self.unbuild();
+        for (pos, freq) in self.tree[1..=self.len].iter_mut().enumerate() {
+            *freq = map(pos, *freq);
+        }
+        self.build();
+        self.recount();
+    }
 }
 
 impl<F> super::CumulFreqTable<F> for CumulFreqTable<F>
 where
-    F: std::convert::From<u8>
-        + Copy
-        + AddAssign
-        + SubAssign
-        + Sub<Output = F>
-        + Shl<u32, Output = F>
-        + PartialOrd,
+    F: Frequency,
 {
     /// Panics if len < 1.
     fn new(len: usize) -> Self {
         assert!(len > 0, "table must be non-empty");
         Self {
-            tree: vec![0.into(); len].into_boxed_slice(),
+            tree: vec![0.into(); len + 1],
+            len,
+            total: KahanSum::new(0.into()),
+            nonzero: 0,
+            padded: false,
         }
     }
 
@@ -41,133 +352,678 @@ where
         <usize as TryInto<F>>::Error: std::fmt::Debug,
     {
         assert!(len > 0, "table must be non-empty");
-        Self {
-            tree: (0..len)
-                .map(|i| {
-                    if i == 0 {
-                        init
-                    } else {
-                        init << i.trailing_zeros()
-                    }
-                })
-                .collect::<Vec<F>>()
-                .into_boxed_slice(),
-        }
+        let mut table = Self {
+            tree: (0..=len).map(|i| Self::uniform_node(i, init)).collect(),
+            len,
+            total: KahanSum::new(0.into()),
+            nonzero: 0,
+            padded: false,
+        };
+        table.recount();
+        table.fill_nonzero(init);
+        table
+    }
+
+    /// Panics if freqs is empty.
+    /// O(len).
+    fn from_freqs<I: IntoIterator<Item = F>>(freqs: I) -> Self {
+        Self::from_vec(freqs.into_iter().collect())
+    }
+
+    /// O(len).
+    fn to_freq_vec(&self) -> Vec<F> {
+        self.to_vec()
     }
 
     /// O(1).
     fn len(&self) -> usize {
-        self.tree.len()
+        self.len
+    }
+
+    /// O(1).
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.tree.capacity() * std::mem::size_of::<F>()
+    }
+
+    /// Panics if new_len < 1.
+    /// Panics if the removed frequencies don't sum to zero.
+    /// O(len - new_len + ㏒₂ len) when shrinking, O(new_len - len + ㏒₂ len) when growing.
+    fn resize(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        let len = self.len();
+        if new_len < len {
+            // Not against the compensated total, which differs from the tree sums with floats.
+            assert!(
+                self.sum_range(new_len..) == 0.into(),
+                "removed frequencies must sum to zero"
+            );
+            self.truncate(new_len);
+        } else {
+            self.extend_with(new_len - len, 0.into());
+        }
+    }
+
+    /// Panics if new_len < 1.
+    /// O(len - new_len + ㏒₂ len), O(len) when padded.
+    fn truncate(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        // A node only covers positions at or below itself. The remaining nodes are left
+        // untouched.
+        if new_len < self.len() {
+            self.nonzero -= self.nonzero_in(new_len, self.len - 1);
+            self.tree.truncate(new_len + 1);
+            self.len = new_len;
+            self.pad();
+            self.recount();
+        }
+    }
+
+    /// Panics if mid < 1 or mid >= len.
+    /// O(len - mid + ㏒₂ len). Only the tail is rebuilt, self keeps its nodes like
+    /// [super::CumulFreqTable::truncate].
+    fn split_off(&mut self, mid: usize) -> Self {
+        assert!(mid > 0 && mid < self.len, "mid out of bounds");
+        // The frequencies of the tail, like unbuild on the nodes of the tail. The nodes before it
+        // that also cover tail positions are on the query path of mid.
+        let mut freqs = self.tree[mid + 1..=self.len].to_vec();
+        let parent_of = |node: usize| node + (1 << node.trailing_zeros());
+        for node in (mid + 1..=self.len).rev() {
+            let parent = parent_of(node);
+            if parent <= self.len {
+                let child = freqs[node - mid - 1];
+                freqs[parent - mid - 1] -= child;
+            }
+        }
+        let mut node = mid;
+        while node > 0 {
+            let parent = parent_of(node);
+            if parent <= self.len {
+                freqs[parent - mid - 1] -= self.tree[node];
+            }
+            node -= 1 << node.trailing_zeros();
+        }
+        let mut tail = Self::from_vec(freqs);
+        if self.padded {
+            tail = tail.padded();
+        }
+        self.truncate(mid);
+        tail
+    }
+
+    /// O(other len + ㏒₂ len), O(len) when padded.
+    fn concat(mut self, other: Self) -> Self {
+        let total = other.total();
+        self.nonzero += other.nonzero;
+        let freqs = other.into_vec();
+        // Without the padding, so the nodes of other are pushed right after the last position.
+        self.tree.truncate(self.len + 1);
+        self.tree.reserve(freqs.len());
+        for freq in freqs {
+            self.push_node(freq);
+        }
+        self.len = self.tree.len() - 1;
+        self.total.add(total);
+        self.pad();
+        self
+    }
+
+    /// O(extra + ㏒₂ len).
+    /// Every node has a single parent, so all the pushes visit O(extra + ㏒₂ len) children in
+    /// total.
+    fn extend_with(&mut self, extra: usize, init: F) {
+        self.tree.reserve(extra);
+        for _ in 0..extra {
+            self.push(init);
+        }
+    }
+
+    /// O(len). O(㏒₂ len) new nodes cover old positions, with an O(㏒₂ len) query each.
+    fn grow_double(&mut self) {
+        let new_len = 2 * self.len;
+        let old = self.tree.len();
+        if new_len >= old {
+            // The new nodes cover zero frequencies, but the ones covering the end of the old
+            // nodes. Their value is the sum of the old nodes after the ones they don't cover.
+            let prefix = |tree: &[F], mut node: usize| {
+                let mut sum: F = 0.into();
+                while node > 0 {
+                    sum += tree[node];
+                    node -= 1 << node.trailing_zeros();
+                }
+                sum
+            };
+            let all = prefix(&self.tree, old - 1);
+            self.tree.resize(new_len + 1, 0.into());
+            for node in old..=new_len {
+                let first = node - (1 << node.trailing_zeros());
+                if first < old - 1 {
+                    self.tree[node] = all - prefix(&self.tree, first);
+                }
+            }
+        }
+        self.len = new_len;
+        self.pad();
+    }
+
+    /// Amortized O(㏒₂ len).
+    fn push(&mut self, freq: F) {
+        if self.len + 1 < self.tree.len() {
+            // Into the padding.
+            self.len += 1;
+            self.add(self.len - 1, freq);
+        } else {
+            self.push_node(freq);
+            self.len += 1;
+            self.total.add(freq);
+            self.track_nonzero(0.into(), freq);
+            self.pad();
+        }
+    }
+
+    /// Panics if len < 2.
+    /// O(㏒₂ len).
+    fn pop(&mut self) -> F {
+        assert!(self.len() > 1, "table must be non-empty");
+        let freq = self.freq(self.len() - 1);
+        if self.padded {
+            // The padding is kept.
+            self.sub(self.len() - 1, freq);
+        } else {
+            self.tree.pop();
+            self.total.sub(freq);
+            self.track_nonzero(freq, 0.into());
+        }
+        self.len -= 1;
+        freq
+    }
+
+    /// O(len).
+    fn reserve(&mut self, additional: usize) {
+        self.tree.reserve(additional);
+    }
+
+    /// O(len).
+    fn shrink_to_fit(&mut self) {
+        self.tree.shrink_to_fit();
     }
 
     /// Panics if pos is out of bounds.
     /// Panics on overflow in debug.
     /// O(㏒₂ len).
-    fn add(&mut self, mut pos: usize, val: F) {
-        assert!(pos < self.tree.len(), "pos out of bounds");
-        if pos == 0 {
-            self.tree[0] += val;
+    fn add(&mut self, pos: usize, val: F) {
+        assert!(pos < self.len(), "pos out of bounds");
+        // SAFETY: pos is in bounds.
+        unsafe { self.add_unchecked(pos, val) }
+    }
+
+    /// O(㏒₂ len).
+    unsafe fn add_unchecked(&mut self, pos: usize, val: F) {
+        let old = self.freq_unchecked(pos);
+        self.track_nonzero(old, old + val);
+        self.total.add(val);
+        let mut node = pos + 1;
+        // Every node on the update path is in bounds.
+        while node < self.tree.len() {
+            // Add least significant bit.
+            // Equivalent to node += node & -node with two's complement.
+            let next = node + (1 << node.trailing_zeros());
+            self.prefetch(next);
+            *self.tree.get_unchecked_mut(node) += val;
+            node = next;
+        }
+    }
+
+    /// Panics if pos is out of bounds.
+    /// Panics on underflow in debug.
+    /// O(㏒₂ len).
+    fn sub(&mut self, pos: usize, val: F) {
+        assert!(pos < self.len(), "pos out of bounds");
+        let old = self.freq(pos);
+        self.track_nonzero(old, old - val);
+        self.total.sub(val);
+        let mut node = pos + 1;
+        while node < self.tree.len() {
+            // Add least significant bit.
+            // Equivalent to node += node & -node with two's complement.
+            let next = node + (1 << node.trailing_zeros());
+            self.prefetch(next);
+            self.tree[node] -= val;
+            node = next;
+        }
+    }
+
+    /// Panics if a position is out of bounds.
+    /// Panics on overflow in debug.
+    /// Like [super::CumulFreqTable::add_batch], duplicated positions are coalesced before touching
+    /// the tree.
+    fn inc_all<I: IntoIterator<Item = usize>>(&mut self, positions: I) {
+        let updates: Vec<(usize, F)> = positions.into_iter().map(|pos| (pos, 1.into())).collect();
+        self.add_batch(&updates);
+    }
+
+    /// Panics if a position is out of bounds.
+    /// Panics on overflow in debug.
+    /// O(len) for batches of len / ㏒₂ len updates or more, O(updates × ㏒₂ len) otherwise.
+    fn add_batch(&mut self, updates: &[(usize, F)]) {
+        let len = self.len();
+        let depth = (usize::BITS - len.leading_zeros()) as usize;
+        if updates.len() * depth >= len {
+            // Like the linear construction: the change of every node is propagated to its parent.
+            let mut deltas: Vec<F> = vec![0.into(); self.tree.len()];
+            for &(pos, val) in updates {
+                assert!(pos < len, "pos out of bounds");
+                deltas[pos + 1] += val;
+            }
+            for node in 1..self.tree.len() {
+                let delta = deltas[node];
+                self.tree[node] += delta;
+                let parent = node + (1 << node.trailing_zeros());
+                if parent < self.tree.len() {
+                    deltas[parent] += delta;
+                }
+            }
+            self.recount();
+            self.nonzero = self.nonzero_in(0, len - 1);
         } else {
-            while pos < self.tree.len() {
-                self.tree[pos] += val;
-                // Add least significant bit.
-                // Equivalent to pos += pos & -pos with two's complement.
-                pos += 1 << pos.trailing_zeros();
+            // In order of position, the update paths share their upper nodes, still in the cache.
+            let mut updates = updates.to_vec();
+            updates.sort_unstable_by_key(|&(pos, _)| pos);
+            let mut updates = updates.into_iter().peekable();
+            while let Some((pos, mut val)) = updates.next() {
+                while let Some((_, next)) = updates.next_if(|&(next, _)| next == pos) {
+                    val += next;
+                }
+                self.add(pos, val);
             }
         }
     }
 
     /// Panics if pos is out of bounds.
-    /// Panics on underflow in debug.
     /// O(㏒₂ len).
-    fn sub(&mut self, mut pos: usize, val: F) {
-        assert!(pos < self.tree.len(), "pos out of bounds");
-        if pos == 0 {
-            self.tree[0] -= val;
+    fn try_add(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.len(), "pos out of bounds");
+        // Check the whole update path before updating anything. The nodes only hold partial
+        // sums, so the frequency and the total are checked too.
+        if self.freq(pos).checked_add(val).is_none()
+            || self.total().checked_add(val).is_none()
+            || !self.check_path(pos, |node| node.checked_add(val))
+        {
+            return Err(OverflowError);
+        }
+        self.add(pos, val);
+        Ok(())
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len).
+    fn try_sub(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.len(), "pos out of bounds");
+        if self.freq(pos).checked_sub(val).is_none()
+            || self.total().checked_sub(val).is_none()
+            || !self.check_path(pos, |node| node.checked_sub(val))
+        {
+            return Err(OverflowError);
+        }
+        self.sub(pos, val);
+        Ok(())
+    }
+
+    /// Panics if a or b is out of bounds.
+    /// O(㏒₂ len).
+    fn swap(&mut self, a: usize, b: usize) {
+        let (fa, fb) = (self.freq(a), self.freq(b));
+        // The difference moves from the larger frequency to the smaller one, so nothing
+        // underflows with unsigned frequencies. The total doesn't change.
+        let (mut lo, mut hi, diff) = if fa < fb {
+            (a + 1, b + 1, fb - fa)
+        } else if fb < fa {
+            (b + 1, a + 1, fa - fb)
         } else {
-            while pos < self.tree.len() {
-                self.tree[pos] -= val;
-                // Add least significant bit.
-                // Equivalent to pos += pos & -pos with two's complement.
-                pos += 1 << pos.trailing_zeros();
+            return;
+        };
+        // Walk both update paths in node order. From the first node they share, the changes
+        // cancel out.
+        let len = self.tree.len();
+        while lo != hi && lo.min(hi) < len {
+            if lo < hi {
+                self.tree[lo] += diff;
+                lo += 1 << lo.trailing_zeros();
+            } else {
+                self.tree[hi] -= diff;
+                hi += 1 << hi.trailing_zeros();
             }
         }
     }
 
+    /// O(len).
+    fn clear(&mut self) {
+        self.tree.fill(0.into());
+        self.total = KahanSum::new(0.into());
+        self.nonzero = 0;
+    }
+
+    /// O(len).
+    fn fill(&mut self, init: F) {
+        if self.padded {
+            // The padding stays zero.
+            self.tree[1..=self.len].fill(init);
+            self.tree[self.len + 1..].fill(0.into());
+            self.build();
+        } else {
+            for (i, node) in self.tree.iter_mut().enumerate() {
+                *node = Self::uniform_node(i, init);
+            }
+            self.fill_nonzero(init);
+        }
+        self.recount();
+    }
+
+    /// O(len).
+    fn reverse(&mut self) {
+        self.unbuild();
+        self.tree[1..=self.len].reverse();
+        self.build();
+    }
+
+    /// Panics if k > len.
+    /// O(len).
+    fn rotate_left(&mut self, k: usize) {
+        assert!(k <= self.len(), "k out of bounds");
+        self.unbuild();
+        self.tree[1..=self.len].rotate_left(k);
+        self.build();
+    }
+
+    /// Panics if the range is out of bounds.
+    /// Panics on overflow in debug.
+    /// O(range length + ㏒₂ len), O(len) for the whole table.
+    fn add_range<R: std::ops::RangeBounds<usize>>(&mut self, range: R, val: F) {
+        if let Some((first, last)) = super::inclusive_range(range, self.len()) {
+            self.nonzero -= self.nonzero_in(first, last);
+            self.range_update(first, last, val, F::add_assign);
+            self.nonzero += self.nonzero_in(first, last);
+            self.recount();
+        }
+    }
+
+    /// Panics if the range is out of bounds.
+    /// Panics on underflow in debug.
+    /// O(range length + ㏒₂ len), O(len) for the whole table.
+    fn sub_range<R: std::ops::RangeBounds<usize>>(&mut self, range: R, val: F) {
+        if let Some((first, last)) = super::inclusive_range(range, self.len()) {
+            self.nonzero -= self.nonzero_in(first, last);
+            self.range_update(first, last, val, F::sub_assign);
+            self.nonzero += self.nonzero_in(first, last);
+            self.recount();
+        }
+    }
+
     /// Panics if pos is out of bounds.
     /// O(㏒₂ len).
-    fn sum(&self, mut pos: usize) -> F {
-        assert!(pos < self.tree.len(), "pos out of bounds");
-        let mut sum = self.tree[0];
-        while pos > 0 {
-            sum += self.tree[pos];
+    fn sum(&self, pos: usize) -> F {
+        assert!(pos < self.len(), "pos out of bounds");
+        // SAFETY: pos is in bounds.
+        unsafe { self.sum_unchecked(pos) }
+    }
+
+    /// O(㏒₂ len).
+    unsafe fn sum_unchecked(&self, pos: usize) -> F {
+        // Every node on the query path is at or below the node of pos.
+        let mut node = pos + 1;
+        let mut sum: F = 0.into();
+        while node > 0 {
             // Remove least significant bit.
-            // Equivalent to pos &= pos - 1;
-            pos -= 1 << pos.trailing_zeros();
+            // Equivalent to node &= node - 1;
+            let next = node - (1 << node.trailing_zeros());
+            self.prefetch(next);
+            sum += *self.tree.get_unchecked(node);
+            node = next;
         }
         sum
     }
 
+    /// Panics if pos is out of bounds.
     /// O(㏒₂ len).
+    fn bounds(&self, pos: usize) -> (F, F) {
+        assert!(pos < self.len(), "pos out of bounds");
+        // The paths of pos and pos - 1 join at the parent of the node of pos.
+        let child = pos + 1;
+        let parent = child - (1 << child.trailing_zeros());
+        let mut low: F = 0.into();
+        let mut node = parent;
+        while node > 0 {
+            low += self.tree[node];
+            node -= 1 << node.trailing_zeros();
+        }
+        let mut high = low;
+        high += self.tree[child];
+        node = child - 1;
+        while node != parent {
+            low += self.tree[node];
+            node -= 1 << node.trailing_zeros();
+        }
+        (low, high)
+    }
+
+    /// O(1).
     fn total(&self) -> F {
-        self.sum(self.len() - 1)
+        self.total.get()
+    }
+
+    /// O(1).
+    fn count_nonzero(&self) -> usize {
+        self.nonzero
     }
 
     /// O(㏒₂ len).
-    fn freq(&self, mut pos: usize) -> F {
-        assert!(pos < self.tree.len(), "pos out of bounds");
-        let mut freq = self.tree[pos];
-        if pos > 0 {
-            let parent = pos - (1 << pos.trailing_zeros());
-            pos -= 1;
-            while parent != pos {
-                freq -= self.tree[pos];
-                pos -= 1 << pos.trailing_zeros();
-            }
+    fn freq(&self, pos: usize) -> F {
+        assert!(pos < self.len(), "pos out of bounds");
+        // SAFETY: pos is in bounds.
+        unsafe { self.freq_unchecked(pos) }
+    }
+
+    /// O(㏒₂ len).
+    unsafe fn freq_unchecked(&self, pos: usize) -> F {
+        // The children of a node are below it.
+        let node = pos + 1;
+        let mut freq = *self.tree.get_unchecked(node);
+        let parent = node - (1 << node.trailing_zeros());
+        let mut child = node - 1;
+        while parent != child {
+            freq -= *self.tree.get_unchecked(child);
+            child -= 1 << child.trailing_zeros();
         }
         freq
     }
 
     /// O(㏒₂ len).
-    fn find_by_sum(&self, mut sum: F) -> usize {
-        // Modified binary search.
+    fn find_by_sum(&self, sum: F) -> usize {
+        // Binary search for the number of positions with a smaller cumulative frequency.
+        // Past the end if sum is greater than the total.
+        self.search(sum, |node, sum| node < sum).min(self.len() - 1)
+    }
+
+    /// O(㏒₂ len).
+    fn find_by_sum_strict(&self, sum: F) -> usize {
+        // Binary search for the number of positions with a smaller or equal cumulative
+        // frequency.
+        self.search(sum, |node, sum| node <= sum)
+            .min(self.len() - 1)
+    }
+
+    /// O(㏒₂ len).
+    fn find_with_bounds(&self, mut sum: F) -> (usize, F, F) {
+        let mut low: F = 0.into();
+        let mut high = low;
         let mut pos = 0;
-        if sum > self.tree[0] {
-            sum -= self.tree[0];
-            // .len() is always >= 1.
-            // The -1 and +1 dance is to avoid overflow.
-            let mut mid = (((self.len() - 1) / 2) + 1).next_power_of_two();
-            /* It is a more efficient version of this:
-            let mut mid = self
-                .len()
-                .checked_next_power_of_two()
-                .map(|x| x / 2)
-                .unwrap_or(1 << (usize::BITS-1));
-            */
-            while mid != 0 {
-                let hi = pos + mid;
-                if hi < self.len() && sum >= self.tree[hi] {
+        let mut mid = self.search_start();
+        while mid != 0 {
+            let hi = pos + mid;
+            if hi < self.tree.len() {
+                if self.tree[hi] < sum {
                     pos = hi;
                     sum -= self.tree[pos];
+                    low += self.tree[pos];
+                } else {
+                    // Every smaller node after this one is taken, unless another one is skipped.
+                    // So the last skipped node is the position found, and it covers all the
+                    // positions after low.
+                    high = low;
+                    high += self.tree[hi];
                 }
-                mid /= 2;
             }
+            mid /= 2;
+        }
+        if pos < self.len() {
+            (pos, low, high)
+        } else {
+            // sum is greater than the total.
+            let last = self.len() - 1;
+            let (low, high) = self.bounds(last);
+            (last, low, high)
         }
-        pos
     }
 
-    /// O(len ㏒₂ len).
+    /// O(len).
     /// scale_freq is called O(len) times (once per position).
     fn scale<C: Fn(F) -> F>(&mut self, scale_freq: C) {
-        for pos in (1..self.tree.len()).rev() {
-            let freq = self.freq(pos);
-            let sfreq = scale_freq(freq);
-            if sfreq < freq {
-                self.sub(pos, freq - sfreq);
-            } else if sfreq > freq {
-                self.add(pos, sfreq - freq);
-            }
+        self.map_in_place(|_, freq| scale_freq(freq));
+    }
+
+    /// O(len).
+    fn map_freqs<M: FnMut(usize, F) -> F>(&mut self, map: M) {
+        self.map_in_place(map);
+    }
+
+    /// Panics if the tables have different lengths.
+    /// Panics on overflow in debug.
+    /// O(len).
+    fn merge_add<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        self.unbuild();
+        for (pos, freq) in self.tree[1..=self.len].iter_mut().enumerate() {
+            *freq += other.freq(pos);
+        }
+        self.build();
+        self.recount();
+    }
+
+    /// Panics if the tables have different lengths.
+    /// Panics on underflow in debug.
+    /// O(len).
+    fn merge_sub<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        self.unbuild();
+        for (pos, freq) in self.tree[1..=self.len].iter_mut().enumerate() {
+            *freq -= other.freq(pos);
+        }
+        self.build();
+        self.recount();
+    }
+
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    /// combine is called O(len) times (once per position).
+    fn combine_with<T, C>(&mut self, other: &T, combine: C)
+    where
+        T: super::CumulFreqTable<F>,
+        C: Fn(F, F) -> F,
+    {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        self.unbuild();
+        for (pos, freq) in self.tree[1..=self.len].iter_mut().enumerate() {
+            *freq = combine(*freq, other.freq(pos));
+        }
+        self.build();
+        self.recount();
+    }
+}
+
+impl<F: PartialEq> PartialEq for CumulFreqTable<F> {
+    /// Compare the trees up to the last position. The nodes after it only depend on the ones
+    /// before, so neither the padding nor the compensation of the totals matter.
+    fn eq(&self, other: &Self) -> bool {
+        self.tree[..=self.len] == other.tree[..=other.len]
+    }
+}
+
+impl<F: Eq> Eq for CumulFreqTable<F> {}
+
+impl<F> Default for CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    /// A single position with a zero frequency, tables are never empty.
+    fn default() -> Self {
+        <Self as super::CumulFreqTable<F>>::new(1)
+    }
+}
+
+impl<F> fmt::Debug for CumulFreqTable<F>
+where
+    F: Frequency + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            // Without node 0, always zero.
+            return f
+                .debug_struct("CumulFreqTable")
+                .field("tree", &&self.tree[1..=self.len])
+                .finish();
+        }
+        let levels = usize::BITS - self.len.leading_zeros();
+        f.debug_struct("CumulFreqTable")
+            .field("positions", &Positions(self))
+            .field(
+                "levels",
+                &(0..levels)
+                    .map(|level| Level(&self.tree[..=self.len], level))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// The `pos: (freq, sum)` of every position.
+struct Positions<'a, F>(&'a CumulFreqTable<F>);
+
+impl<F> fmt::Debug for Positions<'_, F>
+where
+    F: Frequency + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use super::CumulFreqTable;
+        f.debug_map()
+            .entries((0..self.0.len()).map(|pos| (pos, FreqSum(self.0.freq(pos), self.0.sum(pos)))))
+            .finish()
+    }
+}
+
+/// A `(freq, sum)` pair, on a single line even in the alternate format.
+struct FreqSum<F>(F, F);
+
+impl<F: fmt::Debug> fmt::Debug for FreqSum<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?}, {:?})", self.0, self.1)
+    }
+}
+
+/// The `first..=last: value` of the nodes covering 2^level positions.
+struct Level<'a, F>(&'a [F], u32);
+
+impl<F: fmt::Debug> fmt::Debug for Level<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (tree, level) = (self.0, self.1);
+        let mut map = f.debug_map();
+        for node in (1..tree.len()).filter(|node| node.trailing_zeros() == level) {
+            map.entry(&((node - (1 << level))..=(node - 1)), &tree[node]);
         }
-        self.tree[0] = scale_freq(self.tree[0]);
+        map.finish()
     }
 }
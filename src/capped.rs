@@ -0,0 +1,69 @@
+use crate::CumulFreqTable;
+use std::ops::Sub;
+
+/// A table where every frequency is clamped to a maximum, like the 8 bits counters of a byte
+/// oriented coder. Adding to a position never raises its frequency past the cap, so the total
+/// stays consistent with the clamped frequencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CappedTable<T, F = usize> {
+    table: T,
+    max_freq: F,
+}
+
+impl<T, F> CappedTable<T, F>
+where
+    T: CumulFreqTable<F>,
+    F: From<u8> + Copy + Sub<Output = F> + PartialOrd,
+{
+    /// Create a table of len positions with zero frequencies, clamped to max_freq.
+    /// Panics if len < 1.
+    pub fn new(len: usize, max_freq: F) -> Self {
+        Self {
+            table: T::new(len),
+            max_freq,
+        }
+    }
+
+    /// Add to the frequency of the position, up to the cap. Returns the amount actually added.
+    /// Panics if pos is out of bounds.
+    pub fn add(&mut self, pos: usize, val: F) -> F {
+        let freq = self.table.freq(pos);
+        let room = if freq < self.max_freq {
+            self.max_freq - freq
+        } else {
+            0.into()
+        };
+        let val = if val < room { val } else { room };
+        if val > 0.into() {
+            self.table.add(pos, val);
+        }
+        val
+    }
+
+    /// Increment the frequency of the position, unless it is at the cap.
+    /// Returns whether the frequency was incremented.
+    /// Panics if pos is out of bounds.
+    pub fn inc(&mut self, pos: usize) -> bool {
+        self.add(pos, 1.into()) > 0.into()
+    }
+
+    /// The maximum frequency of every position.
+    pub fn max_freq(&self) -> F {
+        self.max_freq
+    }
+
+    /// The memory used in bytes, inline and on the heap, see [CumulFreqTable::memory_bytes].
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<T>() + self.table.memory_bytes()
+    }
+
+    /// The underlying table.
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Return the underlying table.
+    pub fn into_inner(self) -> T {
+        self.table
+    }
+}
@@ -0,0 +1,91 @@
+//! Arithmetic detecting overflow, for [crate::CumulFreqTable::try_add],
+//! [crate::CumulFreqTable::try_sub] and [crate::CumulFreqTable::try_with_freq], and the errors of
+//! the fallible operations.
+//!
+//! Integers report the overflow of their range. Floats report a non-finite result.
+
+use std::fmt;
+
+/// A frequency type with checked addition, substraction and multiplication.
+pub trait Checked: Sized {
+    /// `self + rhs`, or None on overflow.
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    /// `self - rhs`, or None on overflow.
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+
+    /// `self * rhs`, or None on overflow.
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_int {
+    ($($t:ty),*) => {$(
+        impl Checked for $t {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        }
+    )*};
+}
+
+impl_checked_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+macro_rules! impl_checked_float {
+    ($($t:ty),*) => {$(
+        impl Checked for $t {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                Some(self + rhs).filter(|r| r.is_finite())
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                Some(self - rhs).filter(|r| r.is_finite())
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                Some(self * rhs).filter(|r| r.is_finite())
+            }
+        }
+    )*};
+}
+
+impl_checked_float!(f32, f64);
+
+/// A frequency or cumulative frequency would overflow. The table is left unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverflowError;
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("frequency overflow")
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+/// A table cannot be created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableError {
+    /// Tables must have at least one position.
+    Empty,
+    /// The total frequency would overflow.
+    Overflow,
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::Empty => f.write_str("table must be non-empty"),
+            TableError::Overflow => f.write_str("frequency overflow"),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
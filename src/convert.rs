@@ -0,0 +1,202 @@
+//! O(len) conversions between the implementations, through the frequencies, from observations,
+//! and into the frequencies. Also the equality between the implementations, and a hash
+//! consistent with it.
+
+use crate::{binary_indexed_tree, cumulfreq_array, freq_array, treap, CumulFreqTable, Frequency};
+use std::hash::{Hash, Hasher};
+
+macro_rules! impl_from {
+    ($from:ty => $($to:ty),+) => {
+        $(
+            impl<F> From<&$from> for $to
+            where
+                F: Frequency,
+            {
+                /// O(len).
+                fn from(table: &$from) -> Self {
+                    Self::from_vec(table.to_vec())
+                }
+            }
+        )+
+    };
+}
+
+impl_from!(freq_array::FreqTable<F> =>
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>);
+impl_from!(cumulfreq_array::CumulFreqTable<F> =>
+    freq_array::FreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>);
+impl_from!(binary_indexed_tree::CumulFreqTable<F> =>
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>);
+impl_from!(treap::CumulFreqTable<F> =>
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>);
+
+macro_rules! impl_eq {
+    ($lhs:ty => $($rhs:ty),+) => {
+        $(
+            impl<F> PartialEq<$rhs> for $lhs
+            where
+                F: Frequency,
+            {
+                /// Same length and same frequency at every position.
+                /// O(len).
+                fn eq(&self, other: &$rhs) -> bool {
+                    self.len() == other.len()
+                        && self.iter().zip(other.iter()).all(|(a, b)| a.1 == b.1)
+                }
+            }
+        )+
+    };
+}
+
+impl_eq!(freq_array::FreqTable<F> =>
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>);
+impl_eq!(cumulfreq_array::CumulFreqTable<F> =>
+    freq_array::FreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>);
+impl_eq!(binary_indexed_tree::CumulFreqTable<F> =>
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>);
+impl_eq!(treap::CumulFreqTable<F> =>
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>);
+
+macro_rules! impl_hash {
+    ($($table:ty),+) => {
+        $(
+            impl<F> Hash for $table
+            where
+                F: Frequency + Hash,
+            {
+                /// Hash the frequencies in position order, like a `Vec<F>` of the frequencies. Equal
+                /// tables hash identically, whatever their implementation.
+                /// O(len).
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    state.write_usize(self.len());
+                    for (_, freq) in self.iter() {
+                        freq.hash(state);
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_hash!(
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>
+);
+
+macro_rules! impl_from_iter {
+    ($($to:ty),+) => {
+        $(
+            impl<F> FromIterator<usize> for $to
+            where
+                F: Frequency,
+            {
+                /// Count the observed positions. The length is one past the largest position. See
+                /// [crate::CumulFreqTable::count_from] to set the length.
+                /// Panics if there are no observations.
+                /// O(observations + len).
+                fn from_iter<I: IntoIterator<Item = usize>>(observations: I) -> Self {
+                    Self::from_vec(crate::tally(observations))
+                }
+            }
+        )+
+    };
+}
+
+impl_from_iter!(
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>
+);
+
+macro_rules! impl_extend {
+    ($($to:ty),+) => {
+        $(
+            impl<F> Extend<usize> for $to
+            where
+                F: Frequency,
+            {
+                /// Increment the frequency of every observed position.
+                /// Panics if a position is out of bounds.
+                fn extend<I: IntoIterator<Item = usize>>(&mut self, observations: I) {
+                    self.inc_all(observations);
+                }
+            }
+
+            impl<F> Extend<(usize, F)> for $to
+            where
+                F: Frequency,
+            {
+                /// Add the frequency to every position.
+                /// Panics if a position is out of bounds.
+                fn extend<I: IntoIterator<Item = (usize, F)>>(&mut self, freqs: I) {
+                    for (pos, val) in freqs {
+                        self.add(pos, val);
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_extend!(
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>
+);
+
+macro_rules! impl_into_vec {
+    ($($from:ty),+) => {
+        $(
+            impl<F> From<$from> for Vec<F>
+            where
+                F: Frequency,
+            {
+                /// The frequencies in position order, reusing the storage if possible.
+                /// O(len).
+                fn from(table: $from) -> Self {
+                    table.into_vec()
+                }
+            }
+
+            impl<F> IntoIterator for $from
+            where
+                F: Frequency,
+            {
+                type Item = F;
+                type IntoIter = std::vec::IntoIter<F>;
+
+                /// Consume the table, yielding the frequencies in position order.
+                fn into_iter(self) -> Self::IntoIter {
+                    self.into_vec().into_iter()
+                }
+            }
+        )+
+    };
+}
+
+impl_into_vec!(
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>
+);
@@ -1,39 +1,124 @@
-use std::{
-    iter::Sum,
-    ops::{AddAssign, Sub, SubAssign},
-};
+use crate::{Checked, Frequency, OverflowError};
 
 /// Store the cumulative frequencies of each position in a array.
 /// The cumulative frequency is computed on update. In practice this is slightly slower than
 /// freq_array::FreqTable because of the extra memory writes. It exbibits identical big-O runtime
 /// complexity. And is only useful for validating benchmarks.
+/// The number of non-zero frequencies is maintained as a separate value.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CumulFreqTable<F = usize> {
-    sums: Box<[F]>,
+    sums: Vec<F>,
+    nonzero: usize,
+}
+
+/// The number of non-zero frequencies, from the cumulative frequencies. psum is the cumulative
+/// frequency before the first one.
+fn count_nonzero<F: Frequency>(sums: &[F], mut psum: F) -> usize {
+    sums.iter()
+        .filter(|&&sum| sum - std::mem::replace(&mut psum, sum) != 0.into())
+        .count()
+}
+
+impl<F> CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    // Accumulate the frequencies in place. O(len).
+    pub(crate) fn from_vec(mut freqs: Vec<F>) -> Self {
+        assert!(!freqs.is_empty(), "table must be non-empty");
+        let mut sum: F = 0.into();
+        let mut nonzero = 0;
+        for freq in freqs.iter_mut() {
+            nonzero += (*freq != 0.into()) as usize;
+            sum += *freq;
+            *freq = sum;
+        }
+        Self {
+            sums: freqs,
+            nonzero,
+        }
+    }
+
+    /// Recount the non-zero frequencies, after updating every position. O(len).
+    fn recount_nonzero(&mut self) {
+        self.nonzero = count_nonzero(&self.sums, 0.into());
+    }
+
+    /// Account for a frequency changing from old to new.
+    fn track_nonzero(&mut self, old: F, new: F) {
+        self.nonzero += (new != 0.into()) as usize;
+        self.nonzero -= (old != 0.into()) as usize;
+    }
+
+    /// Account for the positions from first being removed. O(len - first).
+    fn untrack_from(&mut self, first: usize) {
+        if first < self.sums.len() {
+            self.nonzero -= count_nonzero(&self.sums[first..], self.sums[first - 1]);
+        }
+    }
+
+    // Difference of the cumulative frequencies. O(len).
+    pub(crate) fn to_vec(&self) -> Vec<F> {
+        let mut psum: F = 0.into();
+        self.sums
+            .iter()
+            .map(|&sum| sum - std::mem::replace(&mut psum, sum))
+            .collect()
+    }
+
+    // Difference of the cumulative frequencies in place. O(len).
+    pub(crate) fn into_vec(mut self) -> Vec<F> {
+        let mut psum: F = 0.into();
+        for sum in self.sums.iter_mut() {
+            let freq = *sum - psum;
+            psum = std::mem::replace(sum, freq);
+        }
+        self.sums
+    }
 }
 
 impl<F> super::CumulFreqTable<F> for CumulFreqTable<F>
 where
-    F: std::convert::From<u8> + Copy + AddAssign + SubAssign + Sub<Output = F> + Sum + PartialOrd,
+    F: Frequency,
 {
     /// Panics if len < 1.
     fn new(len: usize) -> Self {
         assert!(len > 0, "table must be non-empty");
         Self {
-            sums: vec![0.into(); len].into_boxed_slice(),
+            sums: vec![0.into(); len],
+            nonzero: 0,
         }
     }
 
     /// Panics if len < 1.
     fn with_freq(len: usize, init: F) -> Self {
         assert!(len > 0, "table must be non-empty");
-        let mut sums = vec![0.into(); len].into_boxed_slice();
-        let mut total = init;
+        let mut sums = vec![0.into(); len];
+        let mut total: F = 0.into();
         for sum in sums.iter_mut() {
-            *sum = total;
             total += init;
+            *sum = total;
         }
-        Self { sums }
+        Self {
+            sums,
+            nonzero: if init != 0.into() { len } else { 0 },
+        }
+    }
+
+    // Panics if freqs is empty.
+    // O(len).
+    fn from_freqs<I: IntoIterator<Item = F>>(freqs: I) -> Self {
+        Self::from_vec(freqs.into_iter().collect())
+    }
+
+    // O(len).
+    fn to_freq_vec(&self) -> Vec<F> {
+        self.to_vec()
+    }
+
+    // O(len).
+    fn to_sum_vec(&self) -> Vec<F> {
+        self.sums.clone()
     }
 
     // O(1).
@@ -41,14 +126,108 @@ where
         self.sums.len()
     }
 
+    // O(1).
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.sums.capacity() * std::mem::size_of::<F>()
+    }
+
+    // Panics if new_len < 1.
+    // Panics if the removed frequencies don't sum to zero.
+    // O(new_len).
+    fn resize(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        let total = self.total();
+        if new_len < self.sums.len() {
+            assert!(
+                self.sums[new_len - 1] == total,
+                "removed frequencies must sum to zero"
+            );
+            self.untrack_from(new_len);
+        }
+        self.sums.resize(new_len, total);
+    }
+
+    // Panics if new_len < 1.
+    // O(len - new_len).
+    fn truncate(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        self.untrack_from(new_len);
+        self.sums.truncate(new_len);
+    }
+
+    // Panics if mid < 1 or mid >= len.
+    // O(len - mid).
+    fn split_off(&mut self, mid: usize) -> Self {
+        assert!(mid > 0 && mid < self.sums.len(), "mid out of bounds");
+        let mut sums = self.sums.split_off(mid);
+        // Re-based on the cumulative frequency before mid.
+        let base = self.sums[mid - 1];
+        for sum in sums.iter_mut() {
+            *sum -= base;
+        }
+        let nonzero = count_nonzero(&sums, 0.into());
+        self.nonzero -= nonzero;
+        Self { sums, nonzero }
+    }
+
+    // Amortized O(other len).
+    fn concat(mut self, mut other: Self) -> Self {
+        // Re-based on the total of self.
+        let total = self.total();
+        for sum in other.sums.iter_mut() {
+            *sum += total;
+        }
+        self.sums.append(&mut other.sums);
+        self.nonzero += other.nonzero;
+        self
+    }
+
+    // O(extra).
+    fn extend_with(&mut self, extra: usize, init: F) {
+        self.sums.reserve(extra);
+        for _ in 0..extra {
+            self.push(init);
+        }
+    }
+
+    // Amortized O(1).
+    fn push(&mut self, freq: F) {
+        let mut sum = self.total();
+        sum += freq;
+        self.sums.push(sum);
+        self.track_nonzero(0.into(), freq);
+    }
+
+    // Panics if len < 2.
+    // O(1).
+    fn pop(&mut self) -> F {
+        assert!(self.sums.len() > 1, "table must be non-empty");
+        let freq = self.freq(self.sums.len() - 1);
+        self.sums.pop();
+        self.track_nonzero(freq, 0.into());
+        freq
+    }
+
+    // O(len).
+    fn reserve(&mut self, additional: usize) {
+        self.sums.reserve(additional);
+    }
+
+    // O(len).
+    fn shrink_to_fit(&mut self) {
+        self.sums.shrink_to_fit();
+    }
+
     // Panics if pos is out of bounds.
     // Panics on overflow in debug.
     // O(len).
     fn add(&mut self, pos: usize, val: F) {
         assert!(pos < self.sums.len(), "pos out of bounds");
+        let old = self.freq(pos);
         for sum in self.sums[pos..].iter_mut() {
             *sum += val;
         }
+        self.track_nonzero(old, self.freq(pos));
     }
     //
     // Panics if pos is out of bounds.
@@ -56,9 +235,146 @@ where
     // O(len).
     fn sub(&mut self, pos: usize, val: F) {
         assert!(pos < self.sums.len(), "pos out of bounds");
+        let old = self.freq(pos);
         for sum in self.sums[pos..].iter_mut() {
             *sum -= val;
         }
+        self.track_nonzero(old, self.freq(pos));
+    }
+
+    // Panics if pos is out of bounds.
+    // O(len).
+    fn try_add(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.sums.len(), "pos out of bounds");
+        // Check everything before updating anything.
+        if self.sums[pos..]
+            .iter()
+            .any(|&sum| sum.checked_add(val).is_none())
+            || self.freq(pos).checked_add(val).is_none()
+        {
+            return Err(OverflowError);
+        }
+        self.add(pos, val);
+        Ok(())
+    }
+
+    // Panics if pos is out of bounds.
+    // O(len).
+    fn try_sub(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.sums.len(), "pos out of bounds");
+        if self.sums[pos..]
+            .iter()
+            .any(|&sum| sum.checked_sub(val).is_none())
+            || self.freq(pos).checked_sub(val).is_none()
+        {
+            return Err(OverflowError);
+        }
+        self.sub(pos, val);
+        Ok(())
+    }
+
+    // O(len).
+    fn clear(&mut self) {
+        self.sums.fill(0.into());
+        self.nonzero = 0;
+    }
+
+    // O(len).
+    fn fill(&mut self, init: F) {
+        let mut total: F = 0.into();
+        for sum in self.sums.iter_mut() {
+            total += init;
+            *sum = total;
+        }
+        self.nonzero = if init != 0.into() { self.sums.len() } else { 0 };
+    }
+
+    // O(len).
+    fn reverse(&mut self) {
+        // The cumulative frequencies from the other end are the total minus the cumulative
+        // frequencies strictly below.
+        let total = self.total();
+        let len = self.sums.len();
+        self.sums.copy_within(..len - 1, 1);
+        self.sums[0] = 0.into();
+        self.sums.reverse();
+        for sum in self.sums.iter_mut() {
+            *sum = total - *sum;
+        }
+        // Rounding might turn a zero frequency into a non-zero one, with floats.
+        self.recount_nonzero();
+    }
+
+    // Panics if k > len.
+    // O(len).
+    fn rotate_left(&mut self, k: usize) {
+        assert!(k <= self.sums.len(), "k out of bounds");
+        let mut freqs = self.to_vec();
+        freqs.rotate_left(k);
+        *self = Self::from_vec(freqs);
+    }
+
+    // Panics if a position is out of bounds.
+    // Panics on overflow in debug.
+    // O(len + positions), instead of O(len) per position.
+    fn inc_all<I: IntoIterator<Item = usize>>(&mut self, positions: I) {
+        let updates: Vec<(usize, F)> = positions.into_iter().map(|pos| (pos, 1.into())).collect();
+        self.add_batch(&updates);
+    }
+
+    // Panics if a position is out of bounds.
+    // Panics on overflow in debug.
+    // O(len + updates), instead of O(len) per update.
+    fn add_batch(&mut self, updates: &[(usize, F)]) {
+        let mut deltas: Vec<F> = vec![0.into(); self.sums.len()];
+        for &(pos, val) in updates {
+            assert!(pos < self.sums.len(), "pos out of bounds");
+            deltas[pos] += val;
+        }
+        let mut acc: F = 0.into();
+        for (sum, delta) in self.sums.iter_mut().zip(deltas) {
+            acc += delta;
+            *sum += acc;
+        }
+        self.recount_nonzero();
+    }
+
+    // Panics if the range is out of bounds.
+    // Panics on overflow in debug.
+    // O(len).
+    fn add_range<R: std::ops::RangeBounds<usize>>(&mut self, range: R, val: F) {
+        if let Some((first, last)) = super::inclusive_range(range, self.sums.len()) {
+            let mut acc: F = 0.into();
+            for (pos, sum) in self.sums[first..].iter_mut().enumerate() {
+                if pos <= last - first {
+                    acc += val;
+                }
+                *sum += acc;
+            }
+            self.recount_nonzero();
+        }
+    }
+
+    // Panics if the range is out of bounds.
+    // Panics on underflow in debug.
+    // O(len).
+    fn sub_range<R: std::ops::RangeBounds<usize>>(&mut self, range: R, val: F) {
+        if let Some((first, last)) = super::inclusive_range(range, self.sums.len()) {
+            let mut acc: F = 0.into();
+            for (pos, sum) in self.sums[first..].iter_mut().enumerate() {
+                if pos <= last - first {
+                    acc += val;
+                }
+                *sum -= acc;
+            }
+            self.recount_nonzero();
+        }
     }
 
     // Panics if pos is out of bounds.
@@ -68,6 +384,11 @@ where
         self.sums[pos]
     }
 
+    // O(1).
+    unsafe fn sum_unchecked(&self, pos: usize) -> F {
+        *self.sums.get_unchecked(pos)
+    }
+
     // O(1).
     fn total(&self) -> F {
         let r = self.sums.last().copied();
@@ -75,6 +396,11 @@ where
         unsafe { r.unwrap_unchecked() }
     }
 
+    // O(1).
+    fn count_nonzero(&self) -> usize {
+        self.nonzero
+    }
+
     // Panics if pos is out of bounds.
     // O(1).
     fn freq(&self, pos: usize) -> F {
@@ -86,11 +412,25 @@ where
         }
     }
 
+    // O(1).
+    unsafe fn freq_unchecked(&self, pos: usize) -> F {
+        if pos == 0 {
+            *self.sums.get_unchecked(0)
+        } else {
+            *self.sums.get_unchecked(pos) - *self.sums.get_unchecked(pos - 1)
+        }
+    }
+
     // O(len).
     fn find_by_sum(&self, sum: F) -> usize {
         let r = self.sums.iter().position(|&i_sum| i_sum >= sum);
-        // SAFETY: self.sums is non-empty, so r is always Some.
-        unsafe { r.unwrap_unchecked() }
+        r.unwrap_or(self.sums.len() - 1)
+    }
+
+    // O(len).
+    fn find_by_sum_strict(&self, sum: F) -> usize {
+        let r = self.sums.iter().position(|&i_sum| i_sum > sum);
+        r.unwrap_or(self.sums.len() - 1)
     }
 
     // O(len).
@@ -101,5 +441,84 @@ where
             spsum += scale_freq(*sum - psum);
             psum = std::mem::replace(sum, spsum);
         }
+        self.recount_nonzero();
+    }
+
+    // O(len).
+    fn map_freqs<M: FnMut(usize, F) -> F>(&mut self, mut map: M) {
+        let mut psum: F = 0.into();
+        let mut mpsum: F = 0.into();
+        for (pos, sum) in self.sums.iter_mut().enumerate() {
+            mpsum += map(pos, *sum - psum);
+            psum = std::mem::replace(sum, mpsum);
+        }
+        self.recount_nonzero();
+    }
+
+    // Panics if the tables have different lengths.
+    // Panics on overflow in debug.
+    // O(len).
+    fn merge_add<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(
+            self.sums.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        // The cumulative frequencies add up like the frequencies.
+        let mut osum: F = 0.into();
+        for (pos, sum) in self.sums.iter_mut().enumerate() {
+            osum += other.freq(pos);
+            *sum += osum;
+        }
+        self.recount_nonzero();
+    }
+
+    // Panics if the tables have different lengths.
+    // Panics on underflow in debug.
+    // O(len).
+    fn merge_sub<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(
+            self.sums.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        let mut osum: F = 0.into();
+        for (pos, sum) in self.sums.iter_mut().enumerate() {
+            osum += other.freq(pos);
+            *sum -= osum;
+        }
+        self.recount_nonzero();
+    }
+
+    // Panics if the tables have different lengths.
+    // O(len).
+    // combine is called O(len) times (once per position).
+    fn combine_with<T, C>(&mut self, other: &T, combine: C)
+    where
+        T: super::CumulFreqTable<F>,
+        C: Fn(F, F) -> F,
+    {
+        assert_eq!(
+            self.sums.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        let mut psum: F = 0.into();
+        let mut csum: F = 0.into();
+        for (pos, sum) in self.sums.iter_mut().enumerate() {
+            csum += combine(*sum - psum, other.freq(pos));
+            psum = std::mem::replace(sum, csum);
+        }
+        self.recount_nonzero();
+    }
+}
+
+impl<F> Default for CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    /// A single position with a zero frequency, tables are never empty.
+    fn default() -> Self {
+        <Self as super::CumulFreqTable<F>>::new(1)
     }
 }
@@ -0,0 +1,72 @@
+use crate::{CumulFreqTable, Frequency};
+use std::ops::Shr;
+
+/// A table aging its counts: every period observations, all the frequencies are right-shifted.
+/// Long running counters adapt to drifting distributions without an external maintenance task.
+///
+/// Right-shifting rounds down, so rarely seen positions eventually drop back to zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecayingTable<T, F = usize> {
+    table: T,
+    period: usize,
+    shift: u32,
+    countdown: usize,
+    _freq: std::marker::PhantomData<F>,
+}
+
+impl<T, F> DecayingTable<T, F>
+where
+    T: CumulFreqTable<F>,
+    F: Frequency + Shr<u32, Output = F>,
+{
+    /// Create a table of len positions, right-shifting the frequencies by shift bits every period
+    /// observations. A shift of 1 halves the frequencies.
+    /// Panics if len < 1 or period < 1.
+    pub fn new(len: usize, period: usize, shift: u32) -> Self {
+        assert!(period > 0, "period must be non-zero");
+        Self {
+            table: T::new(len),
+            period,
+            shift,
+            countdown: period,
+            _freq: std::marker::PhantomData,
+        }
+    }
+
+    /// Increment the frequency of the position, then age all the frequencies if the period is
+    /// over.
+    /// Panics if pos is out of bounds.
+    pub fn observe(&mut self, pos: usize) {
+        self.table.inc(pos);
+        self.countdown -= 1;
+        if self.countdown == 0 {
+            self.table.decay_shift(self.shift);
+            self.countdown = self.period;
+        }
+    }
+
+    /// The number of observations between two agings.
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    /// The number of bits the frequencies are right-shifted by.
+    pub fn shift(&self) -> u32 {
+        self.shift
+    }
+
+    /// The memory used in bytes, inline and on the heap, see [CumulFreqTable::memory_bytes].
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<T>() + self.table.memory_bytes()
+    }
+
+    /// The underlying table.
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Return the underlying table.
+    pub fn into_inner(self) -> T {
+        self.table
+    }
+}
@@ -0,0 +1,110 @@
+//! Compact binary dump of a table, independent of the implementation.
+//!
+//! The format is:
+//! - 4 bytes magic: `CFT\0`.
+//! - 1 byte format version (currently 1).
+//! - 1 byte element width in bytes.
+//! - 8 bytes length (little-endian u64).
+//! - length × element width bytes of frequencies (little-endian), in position order.
+//!
+//! Only the absolute frequencies are stored. This makes a dump readable by any implementation.
+//! `usize` and `isize` are always stored on 8 bytes so a dump is portable across platforms.
+
+use std::io::{self, Read, Write};
+
+const MAGIC: [u8; 4] = *b"CFT\0";
+const VERSION: u8 = 1;
+
+/// A frequency type that can be stored in a binary dump.
+pub trait Element: Sized {
+    /// Width in bytes of the stored element.
+    const WIDTH: u8;
+
+    /// Write the little-endian representation.
+    fn write_le<W: Write>(self, w: &mut W) -> io::Result<()>;
+
+    /// Read the little-endian representation.
+    fn read_le<R: Read>(r: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_element {
+    ($($t:ty),*) => {$(
+        impl Element for $t {
+            const WIDTH: u8 = std::mem::size_of::<$t>() as u8;
+
+            fn write_le<W: Write>(self, w: &mut W) -> io::Result<()> {
+                w.write_all(&self.to_le_bytes())
+            }
+
+            fn read_le<R: Read>(r: &mut R) -> io::Result<Self> {
+                let mut buf = [0; std::mem::size_of::<$t>()];
+                r.read_exact(&mut buf)?;
+                Ok(Self::from_le_bytes(buf))
+            }
+        }
+    )*};
+}
+
+impl_element!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+macro_rules! impl_element_as {
+    ($($t:ty as $s:ty),*) => {$(
+        impl Element for $t {
+            const WIDTH: u8 = <$s as Element>::WIDTH;
+
+            fn write_le<W: Write>(self, w: &mut W) -> io::Result<()> {
+                (self as $s).write_le(w)
+            }
+
+            fn read_le<R: Read>(r: &mut R) -> io::Result<Self> {
+                <$s>::read_le(r)?.try_into().map_err(|_| invalid_data("element out of range"))
+            }
+        }
+    )*};
+}
+
+impl_element_as!(usize as u64, isize as i64);
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Write the header for a table of `len` elements of type F.
+pub fn write_header<F: Element, W: Write>(w: &mut W, len: usize) -> io::Result<()> {
+    w.write_all(&MAGIC)?;
+    w.write_all(&[VERSION, F::WIDTH])?;
+    (len as u64).write_le(w)
+}
+
+/// Read and validate the header for a table of elements of type F.
+/// Returns the length of the table.
+pub fn read_header<F: Element, R: Read>(r: &mut R) -> io::Result<usize> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(invalid_data("bad magic"));
+    }
+    let mut version_width = [0; 2];
+    r.read_exact(&mut version_width)?;
+    if version_width[0] != VERSION {
+        return Err(invalid_data("unsupported version"));
+    }
+    if version_width[1] != F::WIDTH {
+        return Err(invalid_data("element width mismatch"));
+    }
+    let len = usize::read_le(r)?;
+    if len == 0 {
+        return Err(invalid_data("table must be non-empty"));
+    }
+    Ok(len)
+}
+
+/// Read the frequencies following the header.
+/// The vector grows as elements are read, so a corrupted length cannot trigger a huge allocation.
+pub fn read_freqs<F: Element, R: Read>(r: &mut R, len: usize) -> io::Result<Vec<F>> {
+    let mut freqs = Vec::new();
+    for _ in 0..len {
+        freqs.push(F::read_le(r)?);
+    }
+    Ok(freqs)
+}
@@ -0,0 +1,84 @@
+use crate::CumulFreqTable;
+
+/// Below this scale, the stored frequencies are renormalized.
+const MIN_SCALE: f64 = 1e-32;
+
+/// An online exponentially weighted distribution with cumulative queries.
+///
+/// Every observation decays all the mass by `1 - alpha` and adds `alpha` at the observed position.
+/// Instead of scaling every position on every observation, the decay is accumulated in a global
+/// scale and the added mass is divided by it. Observations are O(㏒₂ len) with
+/// [crate::BinaryIndexedTree], and the table is rebuilt once the scale gets too small, which is
+/// rare enough to be amortized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmaTable<T> {
+    table: T,
+    scale: f64,
+}
+
+impl<T: CumulFreqTable<f64>> EmaTable<T> {
+    /// Create a table of len positions with zero mass.
+    /// Panics if len < 1.
+    pub fn new(len: usize) -> Self {
+        Self {
+            table: T::new(len),
+            scale: 1.0,
+        }
+    }
+
+    /// Decay all the mass by `1 - alpha` and add alpha at the position.
+    /// Panics if alpha is not in (0, 1].
+    /// Panics if pos is out of bounds.
+    pub fn observe_ema(&mut self, pos: usize, alpha: f64) {
+        assert!(alpha > 0.0 && alpha <= 1.0, "alpha must be in (0, 1]");
+        let scale = self.scale * (1.0 - alpha);
+        if scale < MIN_SCALE {
+            self.renormalize(scale);
+            self.scale = 1.0;
+        } else {
+            self.scale = scale;
+        }
+        self.table.add(pos, alpha / self.scale);
+    }
+
+    /// Get the weight of the given position.
+    pub fn freq(&self, pos: usize) -> f64 {
+        self.table.freq(pos) * self.scale
+    }
+
+    /// Get the cumulative weight of the given position.
+    pub fn sum(&self, pos: usize) -> f64 {
+        self.table.sum(pos) * self.scale
+    }
+
+    /// The total weight. It converges to 1 as observations accumulate.
+    pub fn total(&self) -> f64 {
+        self.table.total() * self.scale
+    }
+
+    /// Find the first position with an equal or greater cumulative weight.
+    pub fn find_by_sum(&self, sum: f64) -> usize {
+        self.table.find_by_sum(sum / self.scale)
+    }
+
+    /// The memory used in bytes, inline and on the heap, see [CumulFreqTable::memory_bytes].
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<T>() + self.table.memory_bytes()
+    }
+
+    /// Return the underlying table, renormalized to the actual weights.
+    pub fn into_inner(mut self) -> T {
+        self.renormalize(self.scale);
+        self.table
+    }
+
+    /// Rebuild the table rather than scaling it in place: with large stored weights, updating the
+    /// cumulative weights would cancel catastrophically.
+    fn renormalize(&mut self, scale: f64) {
+        let mut table = T::new(self.table.len());
+        for pos in 0..table.len() {
+            table.add(pos, self.table.freq(pos) * scale);
+        }
+        self.table = table;
+    }
+}
@@ -0,0 +1,171 @@
+//! PPM style escape estimation over a table of counts.
+//!
+//! In Prediction by Partial Matching, a context only codes the symbols it has already seen. Any
+//! other symbol is coded as an escape to a shorter context. The methods differ by the frequency
+//! they give to the escape, with `n` the total count and `q` the number of distinct symbols seen:
+//! - [EscapeMethod::A]: escape 1/(n+1), symbol c/(n+1).
+//! - [EscapeMethod::B]: escape q/n, symbol (c-1)/n. A symbol seen once is still coded as an escape.
+//! - [EscapeMethod::C]: escape q/(n+q), symbol c/(n+q).
+//! - [EscapeMethod::D]: escape q/(2n), symbol (2c-1)/(2n).
+//!
+//! The escape interval is placed after all the symbols. An empty context always escapes, with
+//! the interval `[0, 1)` of a total of 1.
+
+use crate::CumulFreqTable;
+use std::ops::{Add, Sub};
+
+/// The escape estimation method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeMethod {
+    A,
+    B,
+    C,
+    D,
+}
+
+/// A cumulative interval `[low, high)` out of total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval<F> {
+    pub low: F,
+    pub high: F,
+    pub total: F,
+}
+
+/// How to code a symbol in the context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prob<F> {
+    /// The symbol is coded with this interval.
+    Symbol(Interval<F>),
+    /// The escape is coded with this interval, then the symbol in a shorter context.
+    Escape(Interval<F>),
+}
+
+/// Counts of a PPM context with escape estimation.
+/// Maintains a second table flagging the seen positions, so the methods that depend on the
+/// number of distinct symbols stay O(㏒₂ len) with [crate::BinaryIndexedTree].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeModel<T, F = usize> {
+    counts: T,
+    seen: T,
+    method: EscapeMethod,
+    _freq: std::marker::PhantomData<F>,
+}
+
+impl<T, F> EscapeModel<T, F>
+where
+    T: CumulFreqTable<F>,
+    F: From<u8> + Copy + Add<Output = F> + Sub<Output = F> + PartialOrd,
+{
+    /// Create an empty context for len symbols.
+    /// Panics if len < 1.
+    pub fn new(len: usize, method: EscapeMethod) -> Self {
+        Self {
+            counts: T::new(len),
+            seen: T::new(len),
+            method,
+            _freq: std::marker::PhantomData,
+        }
+    }
+
+    /// Create a context from existing counts.
+    /// O(len) calls to `freq`.
+    pub fn from_counts(counts: T, method: EscapeMethod) -> Self {
+        let mut seen = T::new(counts.len());
+        for pos in 0..counts.len() {
+            if counts.freq(pos) > 0.into() {
+                seen.inc(pos);
+            }
+        }
+        Self {
+            counts,
+            seen,
+            method,
+            _freq: std::marker::PhantomData,
+        }
+    }
+
+    /// Count an occurrence of the symbol.
+    /// Panics if pos is out of bounds.
+    pub fn observe(&mut self, pos: usize) {
+        if self.counts.freq(pos) == 0.into() {
+            self.seen.inc(pos);
+        }
+        self.counts.inc(pos);
+    }
+
+    /// The number of distinct symbols seen.
+    pub fn distinct(&self) -> F {
+        self.seen.total()
+    }
+
+    /// The escape method.
+    pub fn method(&self) -> EscapeMethod {
+        self.method
+    }
+
+    /// The counts.
+    pub fn counts(&self) -> &T {
+        &self.counts
+    }
+
+    /// The memory used in bytes, inline and on the heap, see [CumulFreqTable::memory_bytes].
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - 2 * std::mem::size_of::<T>()
+            + self.counts.memory_bytes()
+            + self.seen.memory_bytes()
+    }
+
+    /// The interval coding the symbol in this context, or the escape interval if the context
+    /// cannot code it.
+    /// Panics if pos is out of bounds.
+    pub fn encode_prob(&self, pos: usize) -> Prob<F> {
+        let zero: F = 0.into();
+        let one: F = 1.into();
+        let n = self.counts.total();
+        if n == zero {
+            return Prob::Escape(Interval {
+                low: zero,
+                high: one,
+                total: one,
+            });
+        }
+        let q = self.seen.total();
+        let (low, high) = self.counts.bounds(pos);
+        let count = high - low;
+        // (symbols total, escape width).
+        let (symbols, escape) = match self.method {
+            EscapeMethod::A => (n, one),
+            EscapeMethod::B => (n - q, q),
+            EscapeMethod::C => (n, q),
+            EscapeMethod::D => (n + n - q, q),
+        };
+        let codable = match self.method {
+            EscapeMethod::B => count > one,
+            _ => count > zero,
+        };
+        if !codable {
+            return Prob::Escape(Interval {
+                low: symbols,
+                high: symbols + escape,
+                total: symbols + escape,
+            });
+        }
+        let (low, high) = match self.method {
+            EscapeMethod::A | EscapeMethod::C => (low, high),
+            EscapeMethod::B => {
+                // Every seen symbol before pos loses one.
+                let low = low - self.seen.sum_exclusive(pos);
+                (low, low + count - one)
+            }
+            EscapeMethod::D => {
+                let low = low + low - self.seen.sum_exclusive(pos);
+                (low, low + count + count - one)
+            }
+        };
+        Prob::Symbol(Interval {
+            low,
+            high,
+            total: symbols + escape,
+        })
+    }
+}
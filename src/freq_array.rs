@@ -1,30 +1,90 @@
-use std::{
-    iter::Sum,
-    ops::{AddAssign, Mul, SubAssign},
-};
+use crate::kahan::{self, KahanSum};
+use crate::{Checked, Frequency, OverflowError};
+use std::ops::Index;
 
 /// Store the frequency of each position in a array.
 /// Compute the cumulative frequency on demande by summing over the array.
-/// The total is maintained as a separate value.
+/// The total and the number of non-zero frequencies are maintained as separate values.
+/// The sums are compensated (Kahan summation), so floating point frequencies don't drift.
 ///
 /// It is slightly faster than [crate::BinaryIndexedTree] for small tables depending on the
 /// computer. See the [module][crate#benchmarks] documentation for more details.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct FreqTable<F = usize> {
-    freqs: Box<[F]>,
-    total: F,
+    freqs: Vec<F>,
+    total: KahanSum<F>,
+    nonzero: usize,
+}
+
+/// The number of non-zero frequencies.
+fn count_nonzero<F: Frequency>(freqs: &[F]) -> usize {
+    freqs.iter().filter(|&&freq| freq != 0.into()).count()
+}
+
+impl<F> FreqTable<F>
+where
+    F: Frequency,
+{
+    /// Take the frequencies as is. O(len).
+    pub(crate) fn from_vec(freqs: Vec<F>) -> Self {
+        assert!(!freqs.is_empty(), "table must be non-empty");
+        let total = KahanSum::new(kahan::sum(freqs.iter().copied()));
+        let nonzero = count_nonzero(&freqs);
+        Self {
+            freqs,
+            total,
+            nonzero,
+        }
+    }
+
+    /// Account for a frequency changing from old to new.
+    fn track_nonzero(&mut self, old: F, new: F) {
+        self.nonzero += (new != 0.into()) as usize;
+        self.nonzero -= (old != 0.into()) as usize;
+    }
+
+    /// Copy the frequencies. O(len).
+    pub(crate) fn to_vec(&self) -> Vec<F> {
+        self.freqs.clone()
+    }
+
+    /// Return the frequencies. O(1).
+    pub(crate) fn into_vec(self) -> Vec<F> {
+        self.freqs
+    }
+
+    /// The frequencies, in position order.
+    /// O(1).
+    pub fn as_freq_slice(&self) -> &[F] {
+        &self.freqs
+    }
+
+    /// Create a table owning the frequencies, without copying them. The total and the number of
+    /// non-zero frequencies are computed.
+    /// Panics if freqs is empty.
+    /// O(len).
+    pub fn from_raw_parts(freqs: Vec<F>) -> Self {
+        Self::from_vec(freqs)
+    }
+
+    /// Return the frequencies, without copying them.
+    /// O(1).
+    pub fn into_raw_parts(self) -> Vec<F> {
+        self.into_vec()
+    }
 }
 
 impl<F> super::CumulFreqTable<F> for FreqTable<F>
 where
-    F: From<u8> + Copy + AddAssign + SubAssign + Sum + Mul<Output = F> + PartialOrd,
+    F: Frequency,
 {
     /// Panics if len < 1.
     fn new(len: usize) -> Self {
         assert!(len > 0, "table must be non-empty");
         Self {
-            freqs: vec![0.into(); len].into_boxed_slice(),
-            total: 0.into(),
+            freqs: vec![0.into(); len],
+            total: KahanSum::new(0.into()),
+            nonzero: 0,
         }
     }
 
@@ -37,23 +97,143 @@ where
     {
         assert!(len > 0, "table must be non-empty");
         Self {
-            freqs: vec![init; len].into_boxed_slice(),
-            total: init * len.try_into().unwrap(),
+            freqs: vec![init; len],
+            total: KahanSum::new(init * len.try_into().unwrap()),
+            nonzero: if init != 0.into() { len } else { 0 },
         }
     }
 
+    /// Panics if freqs is empty.
+    /// O(len).
+    fn from_freqs<I: IntoIterator<Item = F>>(freqs: I) -> Self {
+        Self::from_vec(freqs.into_iter().collect())
+    }
+
     /// O(1).
     fn len(&self) -> usize {
         self.freqs.len()
     }
 
+    /// O(1).
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.freqs.capacity() * std::mem::size_of::<F>()
+    }
+
+    /// Panics if new_len < 1.
+    /// Panics if the removed frequencies don't sum to zero.
+    /// O(new_len).
+    fn resize(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        if new_len < self.freqs.len() {
+            let removed = kahan::sum(self.freqs[new_len..].iter().copied());
+            assert!(removed == 0.into(), "removed frequencies must sum to zero");
+            self.nonzero -= count_nonzero(&self.freqs[new_len..]);
+        }
+        self.freqs.resize(new_len, 0.into());
+    }
+
+    /// Panics if new_len < 1.
+    /// O(len - new_len).
+    fn truncate(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        if new_len < self.freqs.len() {
+            for &freq in &self.freqs[new_len..] {
+                self.total.sub(freq);
+            }
+            self.nonzero -= count_nonzero(&self.freqs[new_len..]);
+            self.freqs.truncate(new_len);
+        }
+    }
+
+    /// Panics if mid < 1 or mid >= len.
+    /// O(len - mid).
+    fn split_off(&mut self, mid: usize) -> Self {
+        assert!(mid > 0 && mid < self.freqs.len(), "mid out of bounds");
+        let tail = Self::from_vec(self.freqs.split_off(mid));
+        for &freq in &tail.freqs {
+            self.total.sub(freq);
+        }
+        self.nonzero -= tail.nonzero;
+        tail
+    }
+
+    /// Amortized O(other len).
+    fn concat(mut self, mut other: Self) -> Self {
+        self.freqs.append(&mut other.freqs);
+        self.total.add(other.total.get());
+        self.nonzero += other.nonzero;
+        self
+    }
+
+    /// O(extra).
+    fn extend_with(&mut self, extra: usize, init: F) {
+        self.freqs.reserve(extra);
+        for _ in 0..extra {
+            self.push(init);
+        }
+    }
+
+    /// Amortized O(1).
+    fn push(&mut self, freq: F) {
+        self.freqs.push(freq);
+        self.total.add(freq);
+        self.track_nonzero(0.into(), freq);
+    }
+
+    /// Panics if len < 2.
+    /// O(1).
+    fn pop(&mut self) -> F {
+        assert!(self.freqs.len() > 1, "table must be non-empty");
+        // SAFETY: self.freqs has at least two elements.
+        let freq = unsafe { self.freqs.pop().unwrap_unchecked() };
+        self.total.sub(freq);
+        self.track_nonzero(freq, 0.into());
+        freq
+    }
+
+    /// O(len).
+    fn reserve(&mut self, additional: usize) {
+        self.freqs.reserve(additional);
+    }
+
+    /// O(len).
+    fn shrink_to_fit(&mut self) {
+        self.freqs.shrink_to_fit();
+    }
+
     /// Panics if pos is out of bounds.
     /// Panics on overflow in debug.
     /// O(1).
     fn add(&mut self, pos: usize, val: F) {
         assert!(pos < self.freqs.len(), "pos out of bounds");
-        self.freqs[pos] += val;
-        self.total += val;
+        // SAFETY: pos is in bounds.
+        unsafe { self.add_unchecked(pos, val) }
+    }
+
+    /// O(1).
+    unsafe fn add_unchecked(&mut self, pos: usize, val: F) {
+        let freq = self.freqs.get_unchecked_mut(pos);
+        let old = *freq;
+        *freq += val;
+        let new = *freq;
+        self.total.add(val);
+        self.track_nonzero(old, new);
+    }
+
+    /// Panics if a position is out of bounds.
+    /// Panics on overflow in debug.
+    /// O(positions).
+    fn inc_all<I: IntoIterator<Item = usize>>(&mut self, positions: I) {
+        // The total is updated once, with the number of positions.
+        let mut count: F = 0.into();
+        for pos in positions {
+            assert!(pos < self.freqs.len(), "pos out of bounds");
+            let old = self.freqs[pos];
+            self.freqs[pos] += 1.into();
+            self.track_nonzero(old, self.freqs[pos]);
+            count += 1.into();
+        }
+        self.total.add(count);
     }
 
     /// Panics if pos is out of bounds.
@@ -61,20 +241,163 @@ where
     /// O(1).
     fn sub(&mut self, pos: usize, val: F) {
         assert!(pos < self.freqs.len(), "pos out of bounds");
+        let old = self.freqs[pos];
         self.freqs[pos] -= val;
-        self.total -= val;
+        self.total.sub(val);
+        self.track_nonzero(old, self.freqs[pos]);
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(1).
+    fn try_add(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.freqs.len(), "pos out of bounds");
+        let freq = self.freqs[pos].checked_add(val).ok_or(OverflowError)?;
+        self.total.get().checked_add(val).ok_or(OverflowError)?;
+        self.track_nonzero(self.freqs[pos], freq);
+        self.freqs[pos] = freq;
+        self.total.add(val);
+        Ok(())
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(1).
+    fn try_sub(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.freqs.len(), "pos out of bounds");
+        let freq = self.freqs[pos].checked_sub(val).ok_or(OverflowError)?;
+        self.total.get().checked_sub(val).ok_or(OverflowError)?;
+        self.track_nonzero(self.freqs[pos], freq);
+        self.freqs[pos] = freq;
+        self.total.sub(val);
+        Ok(())
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(1).
+    fn set(&mut self, pos: usize, val: F) {
+        assert!(pos < self.freqs.len(), "pos out of bounds");
+        self.total.sub(self.freqs[pos]);
+        self.total.add(val);
+        self.track_nonzero(self.freqs[pos], val);
+        self.freqs[pos] = val;
+    }
+
+    /// Panics if a or b is out of bounds.
+    /// O(1).
+    fn swap(&mut self, a: usize, b: usize) {
+        assert!(
+            a < self.freqs.len() && b < self.freqs.len(),
+            "pos out of bounds"
+        );
+        self.freqs.swap(a, b);
+    }
+
+    /// O(len).
+    fn clear(&mut self) {
+        self.freqs.fill(0.into());
+        self.total = KahanSum::new(0.into());
+        self.nonzero = 0;
+    }
+
+    /// O(len).
+    fn fill(&mut self, init: F) {
+        self.freqs.fill(init);
+        self.total = KahanSum::new(kahan::sum(self.freqs.iter().copied()));
+        self.nonzero = if init != 0.into() {
+            self.freqs.len()
+        } else {
+            0
+        };
+    }
+
+    /// O(len).
+    fn reverse(&mut self) {
+        self.freqs.reverse();
+    }
+
+    /// Panics if k > len.
+    /// O(len).
+    fn rotate_left(&mut self, k: usize) {
+        assert!(k <= self.freqs.len(), "k out of bounds");
+        self.freqs.rotate_left(k);
+    }
+
+    /// Panics if the range is out of bounds.
+    /// Panics on overflow in debug.
+    /// O(range length).
+    fn add_range<R: std::ops::RangeBounds<usize>>(&mut self, range: R, val: F) {
+        if let Some((first, last)) = super::inclusive_range(range, self.freqs.len()) {
+            for pos in first..=last {
+                let old = self.freqs[pos];
+                self.freqs[pos] += val;
+                self.total.add(val);
+                self.track_nonzero(old, self.freqs[pos]);
+            }
+        }
+    }
+
+    /// Panics if the range is out of bounds.
+    /// Panics on underflow in debug.
+    /// O(range length).
+    fn sub_range<R: std::ops::RangeBounds<usize>>(&mut self, range: R, val: F) {
+        if let Some((first, last)) = super::inclusive_range(range, self.freqs.len()) {
+            for pos in first..=last {
+                let old = self.freqs[pos];
+                self.freqs[pos] -= val;
+                self.total.sub(val);
+                self.track_nonzero(old, self.freqs[pos]);
+            }
+        }
     }
 
     /// Panics if pos is out of bounds.
     /// O(len).
     fn sum(&self, pos: usize) -> F {
         assert!(pos < self.freqs.len(), "pos out of bounds");
-        self.freqs[..=pos].iter().copied().sum()
+        // SAFETY: pos is in bounds.
+        unsafe { self.sum_unchecked(pos) }
+    }
+
+    /// O(len).
+    unsafe fn sum_unchecked(&self, pos: usize) -> F {
+        kahan::sum(self.freqs.get_unchecked(..=pos).iter().copied())
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(pos).
+    fn bounds(&self, pos: usize) -> (F, F) {
+        assert!(pos < self.freqs.len(), "pos out of bounds");
+        let mut sum = KahanSum::new(0.into());
+        for &freq in &self.freqs[..pos] {
+            sum.add(freq);
+        }
+        let low = sum.get();
+        sum.add(self.freqs[pos]);
+        (low, sum.get())
+    }
+
+    /// Panics if the range is out of bounds.
+    /// O(range length).
+    fn sum_range<R: std::ops::RangeBounds<usize>>(&self, range: R) -> F {
+        match super::inclusive_range(range, self.freqs.len()) {
+            None => 0.into(),
+            Some((first, last)) => kahan::sum(self.freqs[first..=last].iter().copied()),
+        }
     }
 
     /// O(1).
     fn total(&self) -> F {
-        self.total
+        self.total.get()
+    }
+
+    /// O(1).
+    fn count_nonzero(&self) -> usize {
+        self.nonzero
     }
 
     /// Panics if pos is out of bounds.
@@ -84,24 +407,175 @@ where
         self.freqs[pos]
     }
 
+    /// O(1).
+    unsafe fn freq_unchecked(&self, pos: usize) -> F {
+        *self.freqs.get_unchecked(pos)
+    }
+
+    /// O(len).
+    fn iter(&self) -> impl Iterator<Item = (usize, F)> + '_ {
+        self.freqs.iter().copied().enumerate()
+    }
+
+    /// O(len).
+    fn to_freq_vec(&self) -> Vec<F> {
+        self.to_vec()
+    }
+
+    /// Compensated like [super::CumulFreqTable::sum].
+    /// O(len).
+    fn to_sum_vec(&self) -> Vec<F> {
+        let mut sum = KahanSum::new(0.into());
+        self.freqs
+            .iter()
+            .map(|&freq| {
+                sum.add(freq);
+                sum.get()
+            })
+            .collect()
+    }
+
     /// O(len).
     fn find_by_sum(&self, sum: F) -> usize {
-        let mut r_sum: F = 0.into();
+        let mut r_sum = KahanSum::new(0.into());
+        let r = self.freqs.iter().position(|&freq| {
+            r_sum.add(freq);
+            r_sum.get() >= sum
+        });
+        r.unwrap_or(self.freqs.len() - 1)
+    }
+
+    /// O(len).
+    fn find_by_sum_strict(&self, sum: F) -> usize {
+        let mut r_sum = KahanSum::new(0.into());
         let r = self.freqs.iter().position(|&freq| {
-            r_sum += freq;
-            r_sum >= sum
+            r_sum.add(freq);
+            r_sum.get() > sum
         });
-        // SAFETY: self.freqs is non-empty, so r is always Some.
-        unsafe { r.unwrap_unchecked() }
+        r.unwrap_or(self.freqs.len() - 1)
+    }
+
+    /// O(len).
+    fn find_with_bounds(&self, sum: F) -> (usize, F, F) {
+        let mut r_sum = KahanSum::new(0.into());
+        for (pos, &freq) in self.freqs.iter().enumerate() {
+            let low = r_sum.get();
+            r_sum.add(freq);
+            let high = r_sum.get();
+            if high >= sum || pos == self.freqs.len() - 1 {
+                return (pos, low, high);
+            }
+        }
+        unreachable!("table must be non-empty")
     }
 
     /// O(len).
     fn scale<C: Fn(F) -> F>(&mut self, scale_freq: C) {
-        let mut sum: F = 0.into();
+        let mut sum = KahanSum::new(0.into());
         for freq in self.freqs.iter_mut() {
             *freq = scale_freq(*freq);
-            sum += *freq;
+            sum.add(*freq);
+        }
+        self.total = sum;
+        self.nonzero = count_nonzero(&self.freqs);
+    }
+
+    /// O(len).
+    fn map_freqs<M: FnMut(usize, F) -> F>(&mut self, mut map: M) {
+        let mut sum = KahanSum::new(0.into());
+        for (pos, freq) in self.freqs.iter_mut().enumerate() {
+            *freq = map(pos, *freq);
+            sum.add(*freq);
+        }
+        self.total = sum;
+        self.nonzero = count_nonzero(&self.freqs);
+    }
+
+    /// Panics if the tables have different lengths.
+    /// Panics on overflow in debug.
+    /// O(len).
+    fn merge_add<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(
+            self.freqs.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        for (pos, freq) in self.freqs.iter_mut().enumerate() {
+            *freq += other.freq(pos);
+        }
+        self.total.add(other.total());
+        self.nonzero = count_nonzero(&self.freqs);
+    }
+
+    /// Panics if the tables have different lengths.
+    /// Panics on underflow in debug.
+    /// O(len).
+    fn merge_sub<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(
+            self.freqs.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        for (pos, freq) in self.freqs.iter_mut().enumerate() {
+            *freq -= other.freq(pos);
+        }
+        self.total.sub(other.total());
+        self.nonzero = count_nonzero(&self.freqs);
+    }
+
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    /// combine is called O(len) times (once per position).
+    fn combine_with<T, C>(&mut self, other: &T, combine: C)
+    where
+        T: super::CumulFreqTable<F>,
+        C: Fn(F, F) -> F,
+    {
+        assert_eq!(
+            self.freqs.len(),
+            other.len(),
+            "tables must have the same length"
+        );
+        let mut sum = KahanSum::new(0.into());
+        for (pos, freq) in self.freqs.iter_mut().enumerate() {
+            *freq = combine(*freq, other.freq(pos));
+            sum.add(*freq);
         }
         self.total = sum;
+        self.nonzero = count_nonzero(&self.freqs);
+    }
+}
+
+impl<F: PartialEq> PartialEq for FreqTable<F> {
+    /// Compare the frequencies only. The compensation of the total depends on the order of the
+    /// updates.
+    fn eq(&self, other: &Self) -> bool {
+        self.freqs == other.freqs
+    }
+}
+
+impl<F: Eq> Eq for FreqTable<F> {}
+
+impl<F> Default for FreqTable<F>
+where
+    F: Frequency,
+{
+    /// A single position with a zero frequency, tables are never empty.
+    fn default() -> Self {
+        <Self as super::CumulFreqTable<F>>::new(1)
+    }
+}
+
+/// Read the frequency of a position, like [crate::CumulFreqTable::freq].
+/// The other implementations, except [crate::Treap], don't store the frequencies and cannot return
+/// a reference.
+impl<F> Index<usize> for FreqTable<F> {
+    type Output = F;
+
+    /// Panics if pos is out of bounds.
+    /// O(1).
+    fn index(&self, pos: usize) -> &F {
+        assert!(pos < self.freqs.len(), "pos out of bounds");
+        &self.freqs[pos]
     }
 }
@@ -0,0 +1,69 @@
+//! Text histogram of the frequencies, for debugging.
+
+use crate::{binary_indexed_tree, cumulfreq_array, freq_array, treap, CumulFreqTable};
+use crate::{Frequency, ToF64};
+use std::fmt::{self, Display, Write};
+
+/// Write a row per position: the position, a bar of up to width `#` proportional to the
+/// frequency, the frequency and the cumulative percentage of the total. Negative frequencies have
+/// an empty bar. The percentage is 0 when the total is zero.
+pub(crate) fn write<F, T, W>(table: &T, w: &mut W, width: usize) -> fmt::Result
+where
+    F: Frequency + ToF64 + Display,
+    T: CumulFreqTable<F> + ?Sized,
+    W: Write + ?Sized,
+{
+    let labels: Vec<String> = table.iter().map(|(_, freq)| freq.to_string()).collect();
+    let pos_width = (table.len() - 1).to_string().len();
+    let freq_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let max = table
+        .iter()
+        .fold(0.0, |max: f64, (_, freq)| max.max(freq.to_f64()));
+    let total = table.total().to_f64();
+
+    let mut cumul = 0.0;
+    for ((pos, freq), label) in table.iter().zip(&labels) {
+        let freq = freq.to_f64();
+        cumul += freq;
+        let bar = if max > 0.0 {
+            (freq.max(0.0) / max * width as f64).round() as usize
+        } else {
+            0
+        };
+        let percent = if total != 0.0 {
+            cumul / total * 100.0
+        } else {
+            0.0
+        };
+        writeln!(
+            w,
+            "{pos:>pos_width$} |{:<width$}| {label:>freq_width$} {percent:5.1}%",
+            "#".repeat(bar)
+        )?;
+    }
+    Ok(())
+}
+
+macro_rules! impl_display {
+    ($($table:ty),+) => {
+        $(
+            impl<F> Display for $table
+            where
+                F: Frequency + ToF64 + Display,
+            {
+                /// A histogram of the frequencies, see [CumulFreqTable::histogram]. The width of
+                /// the bars is the width of the formatter, 40 by default: `{:60}`.
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write(self, f, f.width().unwrap_or(40))
+                }
+            }
+        )+
+    };
+}
+
+impl_display!(
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>
+);
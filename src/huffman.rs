@@ -0,0 +1,98 @@
+//! Huffman codes from the frequencies of a table.
+//!
+//! [CumulFreqTable::huffman_lengths] computes the length of the code of every position, and
+//! [canonical_codes] assigns the codes themselves, so a decoder only needs the lengths.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cumulfreqtable::{huffman, CumulFreqTable, FreqTable};
+//!
+//! let table = FreqTable::<u32>::from_freqs([5, 9, 12, 13, 16, 45, 0]);
+//! let lengths = table.huffman_lengths();
+//! assert_eq!(lengths, [4, 4, 3, 3, 3, 1, 0]);
+//! assert_eq!(huffman::canonical_codes(&lengths), [0b1110, 0b1111, 0b100, 0b101, 0b110, 0b0, 0]);
+//! ```
+
+use crate::{CumulFreqTable, Frequency};
+
+/// The length of the Huffman code of every position. Built with the two queues method over the
+/// frequencies sorted by increasing frequency, then position. On ties, leaves are merged before
+/// internal nodes, minimizing the longest code.
+/// Panics if two frequencies are not comparable, or if a code is longer than 255 bits.
+pub(crate) fn lengths<F, T>(table: &T) -> Vec<u8>
+where
+    F: Frequency,
+    T: CumulFreqTable<F> + ?Sized,
+{
+    let mut leaves: Vec<(usize, F)> = table.iter().filter(|(_, freq)| *freq > 0.into()).collect();
+    let mut lengths = vec![0; table.len()];
+    if leaves.len() <= 1 {
+        // A single symbol still needs one bit.
+        for &(pos, _) in &leaves {
+            lengths[pos] = 1;
+        }
+        return lengths;
+    }
+    leaves.sort_by(|a, b| {
+        a.1.partial_cmp(&b.1)
+            .expect("frequencies must be comparable")
+    });
+
+    // Nodes 0..n are the leaves in sorted order, n.. the internal nodes in creation order.
+    let n = leaves.len();
+    let mut weights: Vec<F> = leaves.iter().map(|&(_, freq)| freq).collect();
+    let mut parents = vec![0; 2 * n - 1];
+    let (mut leaf, mut internal) = (0, n);
+    let mut pop_min = |weights: &Vec<F>| {
+        if leaf < n && (internal == weights.len() || weights[leaf] <= weights[internal]) {
+            leaf += 1;
+            leaf - 1
+        } else {
+            internal += 1;
+            internal - 1
+        }
+    };
+    for parent in n..2 * n - 1 {
+        let a = pop_min(&weights);
+        let b = pop_min(&weights);
+        parents[a] = parent;
+        parents[b] = parent;
+        weights.push(weights[a] + weights[b]);
+    }
+
+    // The root is the last node. Parents are created after their children.
+    let mut depths = vec![0_usize; 2 * n - 1];
+    for node in (0..2 * n - 2).rev() {
+        depths[node] = depths[parents[node]] + 1;
+    }
+    for (node, &(pos, _)) in leaves.iter().enumerate() {
+        lengths[pos] = u8::try_from(depths[node]).expect("code longer than 255 bits");
+    }
+    lengths
+}
+
+/// The canonical Huffman code of every position given the length of its code, as returned by
+/// [CumulFreqTable::huffman_lengths]. Codes are assigned by increasing length, then position.
+/// The code of a position is in the lowest bits, most significant bit first. Positions with a
+/// length of 0 have no code and get 0.
+/// Panics if a length is greater than 64, or if the lengths don't form a prefix code.
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u64> {
+    assert!(
+        lengths.iter().all(|&len| len <= 64),
+        "lengths must be <= 64"
+    );
+    let mut order: Vec<usize> = (0..lengths.len()).filter(|&pos| lengths[pos] > 0).collect();
+    order.sort_by_key(|&pos| lengths[pos]);
+
+    let mut codes = vec![0; lengths.len()];
+    // The next code, left aligned on 64 bits. u128 to detect the overflow of the last code.
+    let mut next: u128 = 0;
+    for pos in order {
+        let shift = 64 - lengths[pos] as u32;
+        assert!(next < 1 << 64, "lengths must form a prefix code");
+        codes[pos] = (next >> shift) as u64;
+        next += 1 << shift;
+    }
+    codes
+}
@@ -0,0 +1,40 @@
+//! JSON export of the distribution, enabled by the `json` feature.
+//!
+//! The document is an object with the length, the total and the frequency of every positions:
+//! ```json
+//! {"len":3,"total":5,"freqs":[1,0,4]}
+//! ```
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Distribution<F> {
+    len: usize,
+    total: F,
+    freqs: Vec<F>,
+}
+
+/// Serialize the frequencies and the total into a JSON document.
+pub fn to_string<F: Serialize>(total: F, freqs: Vec<F>) -> String {
+    let dist = Distribution {
+        len: freqs.len(),
+        total,
+        freqs,
+    };
+    // Serializing numbers into a string cannot fail.
+    serde_json::to_string(&dist).unwrap()
+}
+
+/// Deserialize the frequencies from a JSON document.
+/// The length must match the number of frequencies and be non-zero.
+pub fn from_str<F: DeserializeOwned>(s: &str) -> serde_json::Result<Vec<F>> {
+    use serde::de::Error;
+    let dist: Distribution<F> = serde_json::from_str(s)?;
+    if dist.len != dist.freqs.len() {
+        return Err(serde_json::Error::custom("len doesn't match freqs"));
+    }
+    if dist.len == 0 {
+        return Err(serde_json::Error::custom("table must be non-empty"));
+    }
+    Ok(dist.freqs)
+}
@@ -0,0 +1,55 @@
+//! Compensated summation, so that long sums of small floating point frequencies don't drift.
+
+use std::ops::{Add, Sub};
+
+/// A running sum with Kahan compensation: the low-order bits lost by every addition are carried
+/// into the next one. With exact types like integers, the compensation stays zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KahanSum<F> {
+    sum: F,
+    compensation: F,
+}
+
+impl<F> KahanSum<F>
+where
+    F: From<u8> + Copy + Add<Output = F> + Sub<Output = F>,
+{
+    pub(crate) fn new(sum: F) -> Self {
+        Self {
+            sum,
+            compensation: 0.into(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, val: F) {
+        let val = val - self.compensation;
+        let sum = self.sum + val;
+        self.compensation = (sum - self.sum) - val;
+        self.sum = sum;
+    }
+
+    pub(crate) fn sub(&mut self, val: F) {
+        // Like adding -val, without negating unsigned types.
+        let val = val + self.compensation;
+        let sum = self.sum - val;
+        self.compensation = val - (self.sum - sum);
+        self.sum = sum;
+    }
+
+    pub(crate) fn get(&self) -> F {
+        self.sum
+    }
+}
+
+/// Sum with Kahan compensation.
+pub(crate) fn sum<F, I>(vals: I) -> F
+where
+    F: From<u8> + Copy + Add<Output = F> + Sub<Output = F>,
+    I: IntoIterator<Item = F>,
+{
+    let mut sum = KahanSum::new(0.into());
+    for val in vals {
+        sum.add(val);
+    }
+    sum.get()
+}
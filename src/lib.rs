@@ -38,6 +38,9 @@
 //! - [BinaryIndexedTree]: stores the cumulative frequency of every positions in
 //! a binary indexed tree. The runtime complexity is O(㏒₂ len) for all operations.
 //!
+//! When positions must be inserted or removed in the middle of the table, [Treap] stores the
+//! frequencies in an implicit treap, with O(㏒₂ len) expected runtime for all operations.
+//!
 //! There is also [cumulfreq_array::CumulFreqTable] that computes and stores the cumulative
 //! frequency of every positions on update. It's only purpose is to validate the benchmark results.
 //!
@@ -59,12 +62,19 @@
 //! On a 2021 AMD Ryzen 7 PRO 5850U with dual-channel DDR4 3200 Mhz, the binary_indexed_tree becomes
 //! faster somewhere above 512×64 bits elements.
 #![doc = include_str!("bench_amd_2021.md")]
+//!
+//! #### Binary indexed tree search
+//! The padded [BinaryIndexedTree] against the unpadded one and the [Treap].
+#![doc = include_str!("bench_find_by_sum.md")]
 
 /// A cumulative frequency table maintains the cumulative frequency for every positions in the
 /// table. Different implementations offer different performance characteristics.
 ///
 /// By default, the type used to store the frequency and cumulative frequency is `usize`. Consider
-/// the risk of overflow before using a smaller type.
+/// the risk of overflow before using a smaller type. Every primitive integer type up to `u128`
+/// and `i128` is supported, as well as `f32` and `f64`. With the `bigint` feature, [BigTable]
+/// supports `num_bigint::BigUint`. With the `num-traits` feature, [num::NumFreq] wraps any
+/// `num_traits::Num` type.
 pub trait CumulFreqTable<F: From<u8> = usize> {
     /// Create a new table with the given length and zero frequency for every positions.
     /// Might be more efficient than `with_freq(len, 0)`.
@@ -77,15 +87,160 @@ pub trait CumulFreqTable<F: From<u8> = usize> {
         <usize as TryInto<F>>::Error: std::fmt::Debug,
         F: std::fmt::Debug;
 
+    /// Create a new table like [CumulFreqTable::new], or fail if len < 1.
+    fn try_new(len: usize) -> Result<Self, TableError>
+    where
+        Self: Sized,
+    {
+        if len == 0 {
+            return Err(TableError::Empty);
+        }
+        Ok(Self::new(len))
+    }
+
+    /// Create a new table like [CumulFreqTable::with_freq], or fail if len < 1 or if the total
+    /// frequency would overflow.
+    fn try_with_freq(len: usize, init: F) -> Result<Self, TableError>
+    where
+        Self: Sized,
+        usize: TryInto<F>,
+        <usize as TryInto<F>>::Error: std::fmt::Debug,
+        F: std::fmt::Debug + Frequency + Checked,
+    {
+        if len == 0 {
+            return Err(TableError::Empty);
+        }
+        // Every cumulative frequency is between zero and the total.
+        let len_f: F = len.try_into().map_err(|_| TableError::Overflow)?;
+        init.checked_mul(len_f).ok_or(TableError::Overflow)?;
+        Ok(Self::with_freq(len, init))
+    }
+
+    /// Create a new table with the given frequencies.
+    /// Faster than `new` followed by `add` for every position depending on the implementation.
+    /// Panics if freqs is empty.
+    fn from_freqs<I: IntoIterator<Item = F>>(freqs: I) -> Self;
+
+    /// Create a new table of the given length, counting the observed positions.
+    /// Panics if len < 1 or if an observation is out of bounds.
+    /// Panics on overflow in debug.
+    fn count_from<I: IntoIterator<Item = usize>>(observations: I, len: usize) -> Self
+    where
+        Self: Sized,
+        F: Frequency,
+    {
+        let mut freqs: Vec<F> = vec![0.into(); len];
+        for pos in observations {
+            assert!(pos < len, "pos out of bounds");
+            freqs[pos] += 1.into();
+        }
+        Self::from_freqs(freqs)
+    }
+
     /// Get the length of the table.
     fn len(&self) -> usize;
 
+    /// Whether the table has no positions. Always false: tables have at least one position, and
+    /// [Default] creates a table with a single position.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The memory used by the table in bytes, inline and on the heap, including the spare
+    /// capacity. The default assumes the table stores one F per position on the heap.
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of_val(self) + self.len() * std::mem::size_of::<F>()
+    }
+
+    /// Change the length of the table, preserving the frequency of the remaining positions.
+    /// New positions have a zero frequency.
+    /// Panics if new_len < 1.
+    /// Panics if the frequencies of the removed positions don't sum to zero.
+    fn resize(&mut self, new_len: usize);
+
+    /// Shorten the table to new_len positions, discarding the frequencies of the removed
+    /// positions. Does nothing if new_len is greater or equal to the current length.
+    /// Panics if new_len < 1.
+    fn truncate(&mut self, new_len: usize);
+
+    /// Append extra positions with the given frequency at the end of the table.
+    fn extend_with(&mut self, extra: usize, init: F);
+
+    /// Split the table in two at mid: self keeps the positions before mid, and the positions from
+    /// mid are returned, re-based to start at 0.
+    /// The default copies the frequencies of the tail. The implementations of this crate move
+    /// them instead: [Treap] splits the tree and moves the nodes of the smaller side, the others
+    /// rebuild the tail only.
+    /// Panics if mid < 1 or mid >= len, tables are never empty.
+    fn split_off(&mut self, mid: usize) -> Self
+    where
+        Self: Sized,
+        F: Frequency,
+    {
+        assert!(mid > 0 && mid < self.len(), "mid out of bounds");
+        let tail: Vec<F> = self.iter().skip(mid).map(|(_, freq)| freq).collect();
+        self.truncate(mid);
+        Self::from_freqs(tail)
+    }
+
+    /// Append the positions of other after the positions of self, the inverse of
+    /// [CumulFreqTable::split_off]. The length is the sum of the lengths.
+    /// The default pushes the positions of other one by one. The implementations of this crate
+    /// append them at once: [Treap] merges the trees, moving the nodes of the smaller one.
+    fn concat(mut self, other: Self) -> Self
+    where
+        Self: Sized,
+        F: Frequency,
+    {
+        self.reserve(other.len());
+        for (_, freq) in other.iter() {
+            self.push(freq);
+        }
+        self
+    }
+
+    /// Double the length of the table, the new positions have a zero frequency. For alphabets
+    /// growing by powers of two: `extend_with(len, 0)`, but [BinaryIndexedTree] keeps its nodes
+    /// and only computes the few new ones covering old positions.
+    fn grow_double(&mut self) {
+        self.extend_with(self.len(), 0.into());
+    }
+
+    /// Append a position with the given frequency at the end of the table.
+    fn push(&mut self, freq: F);
+
+    /// Remove the last position of the table and return its frequency.
+    /// Panics if the table has a single position.
+    fn pop(&mut self) -> F;
+
+    /// Reserve memory for at least additional more positions, to grow without reallocating.
+    /// The default does nothing.
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Release the memory that isn't needed for the current length, after shrinking.
+    /// The default does nothing.
+    fn shrink_to_fit(&mut self) {}
+
     /// Add to the frequency of the given position.
     fn add(&mut self, pos: usize, val: F);
 
     /// Substract to the frequency of the given position.
     fn sub(&mut self, pos: usize, val: F);
 
+    /// Add to the frequency of the given position, unless a frequency or a cumulative frequency
+    /// would overflow. On error, the table is left unchanged.
+    /// Panics if pos is out of bounds.
+    fn try_add(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked;
+
+    /// Substract to the frequency of the given position, unless a frequency or a cumulative
+    /// frequency would underflow. On error, the table is left unchanged.
+    /// Panics if pos is out of bounds.
+    fn try_sub(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked;
+
     /// Add one to the frequency of the given position.
     /// A shortcut for `add(pos, 1)`.
     fn inc(&mut self, pos: usize) {
@@ -98,248 +253,4327 @@ pub trait CumulFreqTable<F: From<u8> = usize> {
         self.sub(pos, 1.into());
     }
 
-    /// Get the cumulative frequency of the given position.
-    fn sum(&self, pos: usize) -> F;
+    /// Increment the frequency of every observed position, in any order. Positions may repeat.
+    /// [FreqTable] counts in a tight loop, the tree based implementations aggregate the
+    /// observations with [CumulFreqTable::add_batch] before touching the tree.
+    /// Panics if a position is out of bounds.
+    fn inc_all<I: IntoIterator<Item = usize>>(&mut self, positions: I)
+    where
+        F: Frequency,
+    {
+        for pos in positions {
+            self.inc(pos);
+        }
+    }
 
-    /// The total cumulative frequency.
-    /// This is the same as the cumulative frequency of the last position, but more efficient
-    /// depending on the implementation.
-    fn total(&self) -> F;
+    /// Add every `(pos, val)` of updates, in any order. Positions may repeat.
+    /// The tree based implementations coalesce the updates of the same position, and touch the
+    /// tree in order of position, or once in a linear pass for large batches.
+    /// Panics if a position is out of bounds.
+    fn add_batch(&mut self, updates: &[(usize, F)])
+    where
+        F: Frequency,
+    {
+        for &(pos, val) in updates {
+            self.add(pos, val);
+        }
+    }
 
-    /// Get the frequency of the given position.
-    fn freq(&self, pos: usize) -> F;
+    /// Draw a position with a probability proportional to its frequency, then increment it, like
+    /// a Pólya urn or a Chinese restaurant process. Returns the position drawn.
+    /// [Treap] draws and increments in a single descent.
+    /// Panics if the total is zero. Assumes non-negative frequencies.
+    #[cfg(feature = "rand")]
+    fn sample_and_inc<R: rand::Rng + ?Sized>(&mut self, rng: &mut R) -> usize
+    where
+        Self: Sized,
+        F: Frequency + rand::distributions::uniform::SampleUniform,
+    {
+        let pos = sample::draw(self, rng);
+        self.inc(pos);
+        pos
+    }
 
-    /// Find the first position with an equal or greater cumulative frequency.
-    fn find_by_sum(&self, sum: F) -> usize;
+    /// Draw a position with a probability proportional to its frequency, then decrement it, like
+    /// drawing a ball from an urn without replacement. Returns the position drawn.
+    /// [Treap] draws and decrements in a single descent.
+    /// Panics if the total is zero. Assumes non-negative integer frequencies.
+    #[cfg(feature = "rand")]
+    fn sample_and_dec<R: rand::Rng + ?Sized>(&mut self, rng: &mut R) -> usize
+    where
+        Self: Sized,
+        F: Frequency + rand::distributions::uniform::SampleUniform,
+    {
+        let pos = sample::draw(self, rng);
+        self.dec(pos);
+        pos
+    }
 
-    /// Scale the frequency of every positions by the given factor.
-    /// scale_freq is given the frequency to scale (not the cumulative frequency).
-    /// Examples:
-    ///     - `scale(|f| f / 2)` halves rounding down.
-    ///     - `scale(|f| (f + 1) / 2)` halves rounding up.
-    fn scale<C: Fn(F) -> F>(&mut self, scale_freq: C);
-}
+    /// Draw every position with [CumulFreqTable::sample_and_dec] until the total is zero: a
+    /// random permutation of the urn. Every position is yielded as many times as its frequency.
+    /// Assumes non-negative integer frequencies.
+    #[cfg(feature = "rand")]
+    fn drain_sampled<'a, R: rand::Rng + ?Sized>(
+        &'a mut self,
+        rng: &'a mut R,
+    ) -> impl Iterator<Item = usize> + 'a
+    where
+        Self: Sized,
+        F: Frequency + rand::distributions::uniform::SampleUniform + 'a,
+    {
+        std::iter::from_fn(move || (self.total() > 0.into()).then(|| self.sample_and_dec(rng)))
+    }
 
-pub mod binary_indexed_tree;
-pub mod cumulfreq_array;
-pub mod freq_array;
+    /// Take an immutable snapshot of the frequencies, drawing a position in O(1) instead of
+    /// O(㏒₂ len). Worth it when many positions are drawn between updates.
+    /// Panics if the total is zero. Assumes non-negative frequencies.
+    /// O(len).
+    #[cfg(feature = "rand")]
+    fn freeze_sampler(&self) -> AliasSampler<F>
+    where
+        F: Frequency + ToF64,
+    {
+        AliasSampler::from_freqs(self.iter().map(|(_, freq)| freq))
+    }
 
-use std::convert::From;
+    /// Set the frequency of the given position.
+    /// A single adjustment by the difference with the current frequency, depending on the
+    /// implementation.
+    /// Panics if pos is out of bounds.
+    fn set(&mut self, pos: usize, val: F)
+    where
+        F: Frequency,
+    {
+        let freq = self.freq(pos);
+        if val > freq {
+            self.add(pos, val - freq);
+        } else if val < freq {
+            self.sub(pos, freq - val);
+        }
+    }
 
-pub use binary_indexed_tree::CumulFreqTable as BinaryIndexedTree;
-pub use freq_array::FreqTable;
+    /// Exchange the frequencies of two positions.
+    /// The difference moves from one position to the other: two updates, depending on the
+    /// implementation.
+    /// Panics if a or b is out of bounds.
+    fn swap(&mut self, a: usize, b: usize)
+    where
+        F: Frequency,
+    {
+        let (fa, fb) = (self.freq(a), self.freq(b));
+        if fa > fb {
+            self.sub(a, fa - fb);
+            self.add(b, fa - fb);
+        } else if fa < fb {
+            self.sub(b, fb - fa);
+            self.add(a, fb - fa);
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::convert::From;
-    use std::fmt::Debug;
-    use std::ops::{Add, Div, Mul, Sub};
+    /// Set the frequency of every positions to zero, keeping the length and the allocation.
+    fn clear(&mut self);
 
-    #[test]
-    fn long_test_usize() {
-        long_test::<usize>();
-    }
+    /// Set the frequency of every positions to init, keeping the length and the allocation.
+    fn fill(&mut self, init: F);
 
-    #[test]
-    fn long_test_i16() {
-        long_test::<i16>();
-    }
+    /// Reverse the order of the positions.
+    fn reverse(&mut self);
 
-    #[test]
-    fn long_test_u16() {
-        long_test::<u16>();
+    /// Rotate the positions k times to the left: the frequency of position k moves to position 0,
+    /// and the first k frequencies move to the end.
+    /// Panics if k > len.
+    fn rotate_left(&mut self, k: usize);
+
+    /// Rotate the positions k times to the right: the frequency of position 0 moves to position k,
+    /// and the last k frequencies move to the front.
+    /// Panics if k > len.
+    fn rotate_right(&mut self, k: usize) {
+        assert!(k <= self.len(), "k out of bounds");
+        self.rotate_left(self.len() - k);
     }
 
-    fn long_test<F>()
+    /// Add to the frequency of every positions in the given range.
+    /// Panics if the range is out of bounds.
+    fn add_range<R: RangeBounds<usize>>(&mut self, range: R, val: F)
     where
-        F: Copy
-            + Debug
-            + 'static
-            + From<u8>
-            + Add<Output = F>
-            + Sub<Output = F>
-            + Mul<Output = F>
-            + Div<Output = F>
-            + PartialEq,
-        usize: TryInto<F>,
-        <usize as TryInto<F>>::Error: std::fmt::Debug,
-        freq_array::FreqTable<F>: CumulFreqTable<F>,
-        cumulfreq_array::CumulFreqTable<F>: CumulFreqTable<F>,
-        binary_indexed_tree::CumulFreqTable<F>: CumulFreqTable<F>,
+        F: Frequency,
     {
-        for len in 1..=32 {
-            //dbg!("freq_array", len);
-            long_test_impl::<F, freq_array::FreqTable<F>>(len);
-            //dbg!("cumulfreq_array", len);
-            long_test_impl::<F, cumulfreq_array::CumulFreqTable<F>>(len);
-            //dbg!("binary_indexed_tree", len);
-            long_test_impl::<F, binary_indexed_tree::CumulFreqTable<F>>(len);
+        if let Some((first, last)) = inclusive_range(range, self.len()) {
+            for pos in first..=last {
+                self.add(pos, val);
+            }
         }
     }
 
-    fn long_test_impl<F, T>(len: usize)
+    /// Substract to the frequency of every positions in the given range.
+    /// Panics if the range is out of bounds.
+    fn sub_range<R: RangeBounds<usize>>(&mut self, range: R, val: F)
     where
-        F: Copy
-            + Debug
-            + From<u8>
-            + Add<Output = F>
-            + Sub<Output = F>
-            + Mul<Output = F>
-            + Div<Output = F>
-            + PartialEq,
-        usize: TryInto<F>,
-        <usize as TryInto<F>>::Error: std::fmt::Debug,
-        T: CumulFreqTable<F> + Debug + 'static,
+        F: Frequency,
     {
-        let flen: F = (len as u8).into();
-        let f0: F = 0_u8.into();
-        let f1: F = 1_u8.into();
-        let f2: F = 2_u8.into();
-        let f3: F = 3_u8.into();
-        let f42: F = 42_u8.into();
+        if let Some((first, last)) = inclusive_range(range, self.len()) {
+            for pos in first..=last {
+                self.sub(pos, val);
+            }
+        }
+    }
 
-        // Table with all freqs to 1.
-        let mut table: T = T::with_freq(len, f1);
-        //dbg!(&table);
+    /// Get the cumulative frequency of the given position.
+    fn sum(&self, pos: usize) -> F;
 
-        assert_eq!(table.len(), len);
-        assert_eq!(table.total(), flen);
-        for i in 0..len {
-            assert_eq!(table.freq(i), f1);
-            assert_eq!(table.sum(i), F::from(i as u8) + f1);
+    /// Get the cumulative frequency strictly below the given position: zero for position 0.
+    /// A single cumulative frequency query, unlike `sum(pos) - freq(pos)`.
+    /// Panics if pos is out of bounds.
+    fn sum_exclusive(&self, pos: usize) -> F {
+        assert!(pos < self.len(), "pos out of bounds");
+        if pos == 0 {
+            0.into()
+        } else {
+            self.sum(pos - 1)
         }
+    }
 
-        // All freqs to zero.
-        for i in 0..len {
-            table.dec(i);
-        }
-        assert_eq!(table.total(), f0);
+    /// Get the cumulative frequency interval of the given position: `(sum_exclusive(pos),
+    /// sum(pos))`. This is the symbol interval used by arithmetic coders.
+    /// Computed in a single traversal depending on the implementation.
+    /// Panics if pos is out of bounds.
+    fn bounds(&self, pos: usize) -> (F, F) {
+        (self.sum_exclusive(pos), self.sum(pos))
+    }
 
-        // Adding zeroes, should not change anything.
-        for i in 0..len {
-            assert_eq!(table.freq(i), f0);
-            assert_eq!(table.sum(i), f0);
+    /// Get the total frequency of the positions in the given range.
+    /// Computed as the difference of two cumulative frequencies, depending on the implementation.
+    /// Panics if the range is out of bounds.
+    fn sum_range<R: RangeBounds<usize>>(&self, range: R) -> F
+    where
+        F: Sub<Output = F>,
+    {
+        match inclusive_range(range, self.len()) {
+            None => 0.into(),
+            Some((0, last)) => self.sum(last),
+            Some((first, last)) => self.sum(last) - self.sum(first - 1),
         }
-        assert_eq!(table.total(), f0);
+    }
 
-        // All freqs to 1.
-        for i in 0..len {
-            table.inc(i);
-            assert_eq!(table.freq(i), f1);
-            assert_eq!(table.sum(i), F::from(i as u8) + f1);
-        }
-        assert_eq!(table.total(), flen);
+    /// The number of observations strictly below pos, when the table counts observations of the
+    /// positions. The order statistics name of [CumulFreqTable::sum_exclusive].
+    /// Panics if pos is out of bounds.
+    fn rank(&self, pos: usize) -> F {
+        self.sum_exclusive(pos)
+    }
 
-        // All freqs to 3.
-        for i in 0..len {
-            table.add(i, 2_u8.into());
-            assert_eq!(table.freq(i), f3);
-            assert_eq!(table.sum(i), (F::from(i as u8) + f1) * f3);
-        }
-        assert_eq!(table.total(), flen * f3);
+    /// The number of observations within the range, when the table counts observations of the
+    /// positions. The order statistics name of [CumulFreqTable::sum_range].
+    /// Panics if the range is out of bounds.
+    fn count_between<R: RangeBounds<usize>>(&self, range: R) -> F
+    where
+        F: Sub<Output = F>,
+    {
+        self.sum_range(range)
+    }
 
-        // All freqs should still be 3.
-        for i in 0..len {
-            assert_eq!(table.freq(i), f3);
-            assert_eq!(table.sum(i), (F::from(i as u8) + f1) * f3);
-        }
+    /// The position holding the k-th observation, counting from 0, when the table counts
+    /// observations of the positions: the first position with a cumulative frequency greater
+    /// than k. This is [CumulFreqTable::find_by_sum_strict], or `find_by_sum(k + 1)`.
+    /// The inverse of [CumulFreqTable::rank]: `rank(select(k)) <= k < rank(select(k)) +
+    /// freq(select(k))`.
+    /// Panics if k is not below the total.
+    fn select(&self, k: F) -> usize
+    where
+        F: PartialOrd,
+    {
+        assert!(k < self.total(), "k out of bounds");
+        self.find_by_sum_strict(k)
+    }
 
-        // Find every element by sum.
-        for i in 0..len {
-            //dbg!(i, (i + 1) * 3, table.sum(i));
-            assert_eq!(table.find_by_sum((F::from(i as u8) + f1) * f3), i);
-        }
+    /// The total cumulative frequency.
+    /// This is the same as the cumulative frequency of the last position, but more efficient
+    /// depending on the implementation.
+    fn total(&self) -> F;
 
-        // Divide by two, flooring. So all freqs become 1.
-        table.scale(|x| x / f2);
-        assert_eq!(table.total(), flen);
-        for i in 0..len {
-            assert_eq!(table.freq(i), f3 / f2);
-            assert_eq!(table.sum(i), F::from(i as u8) + f1);
-        }
+    /// Get the frequency of the given position.
+    fn freq(&self, pos: usize) -> F;
 
-        // Bring every other positions to 42.
-        for i in (0..len).step_by(2) {
-            table.add(i, f42 - f1);
-        }
-        assert_eq!(
-            table.total(),
-            (F::from(len as u8) - F::from(len as u8) / f2) * f42 + (F::from(len as u8) / f2)
-        );
-        for i in 0..len {
-            if i % 2 == 0 {
-                assert_eq!(table.freq(i), f42);
-            } else {
-                assert_eq!(table.freq(i), f1);
+    /// Get the frequency of the given position, without checking the bounds.
+    ///
+    /// # Safety
+    ///
+    /// pos must be less than len.
+    unsafe fn freq_unchecked(&self, pos: usize) -> F {
+        self.freq(pos)
+    }
+
+    /// Get the cumulative frequency of the given position, without checking the bounds.
+    ///
+    /// # Safety
+    ///
+    /// pos must be less than len.
+    unsafe fn sum_unchecked(&self, pos: usize) -> F {
+        self.sum(pos)
+    }
+
+    /// Add to the frequency of the given position, without checking the bounds.
+    ///
+    /// # Safety
+    ///
+    /// pos must be less than len.
+    unsafe fn add_unchecked(&mut self, pos: usize, val: F) {
+        self.add(pos, val)
+    }
+
+    /// Get the frequency of the given position, or None if pos is out of bounds.
+    fn get_freq(&self, pos: usize) -> Option<F> {
+        (pos < self.len()).then(|| self.freq(pos))
+    }
+
+    /// The number of positions with a non-zero frequency, like the number of distinct symbols
+    /// seen.
+    /// O(1) with [FreqTable], [cumulfreq_array::CumulFreqTable], [BinaryIndexedTree] and [Treap],
+    /// which maintain it on every update. [EscapeModel::distinct] maintains it in O(㏒₂ len) with a
+    /// second table.
+    fn count_nonzero(&self) -> usize
+    where
+        F: PartialEq,
+    {
+        self.iter().filter(|(_, freq)| *freq != 0.into()).count()
+    }
+
+    /// The first position with the largest frequency (the mode), and its frequency.
+    /// Panics if the table is empty.
+    /// O(len).
+    fn max_freq_pos(&self) -> (usize, F)
+    where
+        F: PartialOrd,
+    {
+        let mut iter = self.iter();
+        let mut max = iter.next().expect("table must be non-empty");
+        for (pos, freq) in iter {
+            if freq > max.1 {
+                max = (pos, freq);
             }
-            assert_eq!(
-                table.sum(i),
-                ((F::from(i as u8) + f2) / f2 * f42) + ((F::from(i as u8) + f1) / f2)
-            );
         }
+        max
     }
 
-    #[test]
-    fn scale_test() {
-        type F = usize;
-        for len in 1..=32 {
-            //dbg!("scale_test freq_array", len);
-            scale_test_impl::<F, freq_array::FreqTable<F>>(len);
-            //dbg!("scale_test cumulfreq_array", len);
-            scale_test_impl::<F, cumulfreq_array::CumulFreqTable<F>>(len);
-            //dbg!("scale_test binary_indexed_tree", len);
-            scale_test_impl::<F, binary_indexed_tree::CumulFreqTable<F>>(len);
+    /// The first position with the smallest frequency, and its frequency.
+    /// Panics if the table is empty.
+    /// O(len).
+    fn min_freq_pos(&self) -> (usize, F)
+    where
+        F: PartialOrd,
+    {
+        let mut iter = self.iter();
+        let mut min = iter.next().expect("table must be non-empty");
+        for (pos, freq) in iter {
+            if freq < min.1 {
+                min = (pos, freq);
+            }
         }
+        min
     }
 
-    fn scale_test_impl<F, T>(len: usize)
+    /// The k positions with the largest frequencies, with their frequencies, by decreasing
+    /// frequency. Ties go to the lowest position. All the positions if k >= len.
+    /// Panics if two frequencies are not comparable, like a NaN.
+    /// O(len + k ㏒₂ k).
+    fn top_k(&self, k: usize) -> Vec<(usize, F)>
     where
-        F: Copy
-            + Debug
-            + From<u8>
-            + Add<Output = F>
-            + Sub<Output = F>
-            + Mul<Output = F>
-            + Div<Output = F>
-            + PartialEq,
-        usize: TryInto<F>,
-        <usize as TryInto<F>>::Error: std::fmt::Debug,
-        T: CumulFreqTable<F> + Debug + 'static + Clone,
+        F: PartialOrd,
     {
-        let mut table = T::with_freq(len, 1.into());
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut freqs: Vec<(usize, F)> = self.iter().collect();
+        let order = |a: &(usize, F), b: &(usize, F)| {
+            b.1.partial_cmp(&a.1)
+                .expect("frequencies must be comparable")
+                .then(a.0.cmp(&b.0))
+        };
+        if k < freqs.len() {
+            freqs.select_nth_unstable_by(k - 1, order);
+            freqs.truncate(k);
+        }
+        freqs.sort_unstable_by(order);
+        freqs
+    }
+
+    /// The length in bits of the Huffman code of every position, 0 for a zero frequency. A single
+    /// non-zero frequency gets a length of 1. See [huffman::canonical_codes] for the codes.
+    /// Panics if two frequencies are not comparable, or if a code is longer than 255 bits.
+    /// O(len ㏒₂ len).
+    fn huffman_lengths(&self) -> Vec<u8>
+    where
+        F: Frequency,
+    {
+        huffman::lengths(self)
+    }
+
+    /// A text histogram of the frequencies, a row per position with a bar of up to width `#`
+    /// and the cumulative percentage of the total. The tables also implement
+    /// [Display](std::fmt::Display) with it, `println!("{table:60}")` sets the width.
+    ///
+    /// ```text
+    /// 0 |########  | 4  40.0%
+    /// 1 |          | 0  40.0%
+    /// 2 |##########| 5  90.0%
+    /// 3 |##        | 1 100.0%
+    /// ```
+    /// O(len).
+    fn histogram(&self, width: usize) -> String
+    where
+        F: Frequency + ToF64 + std::fmt::Display,
+    {
+        let mut s = String::new();
+        // Writing to a String never fails.
+        let _ = histogram::write(self, &mut s, width);
+        s
+    }
+
+    /// Get the cumulative frequency of the given position, or None if pos is out of bounds.
+    fn get_sum(&self, pos: usize) -> Option<F> {
+        (pos < self.len()).then(|| self.sum(pos))
+    }
+
+    /// Iterate over the `(position, frequency)` pairs in position order.
+    /// O(len) for every implementation. With [BinaryIndexedTree], the frequency of a position
+    /// walks the children of its node, and every node is the child of a single node.
+    fn iter(&self) -> impl Iterator<Item = (usize, F)> + '_ {
+        (0..self.len()).map(|pos| (pos, self.freq(pos)))
+    }
+
+    /// Copy the frequencies, in position order.
+    /// O(len). With [BinaryIndexedTree], the tree is copied then turned into the frequencies in a
+    /// single sweep.
+    fn to_freq_vec(&self) -> Vec<F> {
+        self.iter().map(|(_, freq)| freq).collect()
+    }
+
+    /// Copy the cumulative frequencies, in position order.
+    /// O(len). They are accumulated from the frequencies, except with
+    /// [cumulfreq_array::CumulFreqTable] which stores them.
+    fn to_sum_vec(&self) -> Vec<F>
+    where
+        F: Frequency,
+    {
+        let mut sums = self.to_freq_vec();
+        let mut sum: F = 0.into();
+        for freq in sums.iter_mut() {
+            sum += *freq;
+            *freq = sum;
+        }
+        sums
+    }
+
+    /// A read-only view of the positions in range, re-based to start at position 0 with a zero
+    /// cumulative frequency. See [TableView].
+    /// Panics if the range is empty or out of bounds.
+    fn view<R: RangeBounds<usize>>(&self, range: R) -> TableView<'_, Self, F>
+    where
+        Self: Sized,
+        F: Frequency,
+    {
+        TableView::new(self, range)
+    }
+
+    /// A view of the positions in range, like [CumulFreqTable::view], that can also update them.
+    /// See [TableViewMut].
+    /// Panics if the range is empty or out of bounds.
+    fn view_mut<R: RangeBounds<usize>>(&mut self, range: R) -> TableViewMut<'_, Self, F>
+    where
+        Self: Sized,
+        F: Frequency,
+    {
+        TableViewMut::new(self, range)
+    }
+
+    /// Find the first position with an equal or greater cumulative frequency.
+    /// Returns the last position if sum is greater than the total.
+    ///
+    /// With signed frequencies, negative frequencies make the cumulative frequencies unsorted.
+    /// The position returned is then one where the cumulative frequencies cross sum:
+    /// `sum(pos - 1) < sum <= sum(pos)`, or `sum <= sum(0)` for position 0. Such a position exists
+    /// whenever a cumulative frequency reaches sum. The array based implementations return the
+    /// first one, or the last position if no cumulative frequency reaches sum. The tree based
+    /// implementations return any of them, or the last position if the total is smaller than sum.
+    fn find_by_sum(&self, sum: F) -> usize;
+
+    /// Find the first position with a strictly greater cumulative frequency.
+    /// Returns the last position if sum is greater than or equal to the total.
+    /// The other convention for decoders. Unlike `find_by_sum(sum + 1)`, it also holds for non
+    /// integer frequencies.
+    /// With signed frequencies, the crossings are `sum(pos - 1) <= sum < sum(pos)`, like
+    /// [CumulFreqTable::find_by_sum].
+    /// O(㏒₂ len) queries of [CumulFreqTable::sum], unless the implementation does better.
+    fn find_by_sum_strict(&self, sum: F) -> usize
+    where
+        F: PartialOrd,
+    {
+        // Binary search for the first cumulative frequency greater than sum.
+        let (mut low, mut high) = (0, self.len() - 1);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.sum(mid) > sum {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        low
+    }
+
+    /// Find the first position with a non-zero frequency and an equal or greater cumulative
+    /// frequency, like [CumulFreqTable::find_by_sum] but never on a zero frequency.
+    /// Returns the first non-zero position if sum is zero or less, and the last non-zero position
+    /// if sum is greater than the total.
+    /// Assumes non-negative frequencies.
+    /// Panics if the total is zero.
+    fn find_by_sum_nonzero(&self, sum: F) -> usize
+    where
+        F: PartialOrd,
+    {
+        let zero: F = 0.into();
+        let total = self.total();
+        assert!(total > zero, "total must be non-zero");
+        // Between two positions with non-zero frequencies, the cumulative frequency of the first
+        // is strictly below the one of the second.
+        if sum <= zero {
+            self.find_by_sum_strict(zero)
+        } else if sum > total {
+            self.find_by_sum(total)
+        } else {
+            self.find_by_sum(sum)
+        }
+    }
+
+    /// Find the first position with an equal or greater cumulative frequency, like
+    /// [CumulFreqTable::find_by_sum], and return it with its bounds like
+    /// [CumulFreqTable::bounds]: `(pos, low, high)`.
+    /// The search and the bounds are computed in a single traversal depending on the
+    /// implementation.
+    fn find_with_bounds(&self, sum: F) -> (usize, F, F) {
+        let pos = self.find_by_sum(sum);
+        let (low, high) = self.bounds(pos);
+        (pos, low, high)
+    }
+
+    /// Find the first position with an equal or greater cumulative frequency, like
+    /// [CumulFreqTable::find_by_sum], and return it with the offset of sum within the position:
+    /// `(pos, sum - sum_exclusive(pos))`. This is what range decoders need.
+    /// The offset comes from the traversal of [CumulFreqTable::find_with_bounds].
+    fn find_by_sum_rem(&self, sum: F) -> (usize, F)
+    where
+        F: Copy + Sub<Output = F>,
+    {
+        let (pos, low, _) = self.find_with_bounds(sum);
+        (pos, sum - low)
+    }
+
+    /// Scale the frequency of every positions by the given factor.
+    /// scale_freq is given the frequency to scale (not the cumulative frequency).
+    /// Examples:
+    ///     - `scale(|f| f / 2)` halves rounding down.
+    ///     - `scale(|f| (f + 1) / 2)` halves rounding up.
+    fn scale<C: Fn(F) -> F>(&mut self, scale_freq: C);
+
+    /// Replace the frequency of every positions by `map(pos, freq)`, in order of position. Unlike
+    /// [CumulFreqTable::scale], the position is given, for position dependent changes.
+    /// O(len) for the implementations of this crate, instead of an update per position.
+    fn map_freqs<M: FnMut(usize, F) -> F>(&mut self, mut map: M)
+    where
+        F: Frequency,
+    {
+        for pos in 0..self.len() {
+            let freq = self.freq(pos);
+            self.set(pos, map(pos, freq));
+        }
+    }
+
+    /// Set to zero the frequency of every positions for which `keep(pos, freq)` is false, in
+    /// order of position. The length doesn't change.
+    /// O(len) for the implementations of this crate, like [CumulFreqTable::map_freqs].
+    fn retain<K: FnMut(usize, F) -> bool>(&mut self, mut keep: K)
+    where
+        Self: Sized,
+        F: Frequency,
+    {
+        self.map_freqs(|pos, freq| if keep(pos, freq) { freq } else { 0.into() });
+    }
+
+    /// Right-shift the frequency of every positions by the given number of bits: the aging step
+    /// of adaptive models, `scale(|f| f >> bits)`. A shift of 1 halves rounding down.
+    /// The nodes of the trees cannot be shifted in place, a shifted sum isn't the sum of the
+    /// shifted frequencies, so they are rebuilt in O(len) like [CumulFreqTable::scale].
+    /// Shifting by the width of F or more clears the table, instead of overflowing the shift.
+    fn decay_shift(&mut self, bits: u32)
+    where
+        F: Frequency + Shr<u32, Output = F>,
+    {
+        if bits as usize >= std::mem::size_of::<F>() * 8 {
+            self.clear();
+        } else {
+            self.scale(|f| f >> bits);
+        }
+    }
+
+    /// Like [CumulFreqTable::scale], returning the new total to decide whether to scale again.
+    /// The total is O(1) for every implementation but [BigTable], where it is summed while
+    /// scaling.
+    fn scale_ret<C: Fn(F) -> F>(&mut self, scale_freq: C) -> F
+    where
+        Self: Sized,
+    {
+        self.scale(scale_freq);
+        self.total()
+    }
+
+    /// Like [CumulFreqTable::scale], but a positive frequency never drops below one, so a seen
+    /// position keeps a non-zero probability. A zero frequency stays zero.
+    /// Examples:
+    ///     - `scale_min1(|f| f / 4)` quarters rounding down, except to zero.
+    fn scale_min1<C: Fn(F) -> F>(&mut self, scale_freq: C)
+    where
+        F: Frequency,
+    {
+        self.scale(|f| {
+            let scaled = scale_freq(f);
+            if f > 0.into() && scaled < 1.into() {
+                1.into()
+            } else {
+                scaled
+            }
+        });
+    }
+
+    /// Halve the frequency of every positions, rounding down: `scale(|f| f / 2)`.
+    fn halve_down(&mut self)
+    where
+        F: Frequency + Div<Output = F>,
+    {
+        self.scale(|f| f / 2.into());
+    }
+
+    /// Halve the frequency of every positions, rounding up so a non-zero frequency stays
+    /// non-zero: `scale(|f| (f + 1) / 2)`.
+    fn halve_up(&mut self)
+    where
+        F: Frequency + Div<Output = F>,
+    {
+        self.scale(|f| (f + 1.into()) / 2.into());
+    }
+
+    /// Add the frequency of every position of other to the same position of this table.
+    /// The frequencies of other are queried once per position, the table is rebuilt in O(len).
+    /// Panics if the tables have different lengths.
+    /// Panics on overflow in debug.
+    fn merge_add<T: CumulFreqTable<F>>(&mut self, other: &T);
+
+    /// Substract the frequency of every position of other to the same position of this table.
+    /// The frequencies of other are queried once per position, the table is rebuilt in O(len).
+    /// Panics if the tables have different lengths.
+    /// Panics on underflow in debug.
+    fn merge_sub<T: CumulFreqTable<F>>(&mut self, other: &T);
+
+    /// Replace the frequency of every position by `combine(freq, other_freq)`, with the
+    /// frequency of the same position in other.
+    /// The frequencies of other are queried once per position, the table is rebuilt in O(len).
+    /// Examples:
+    ///     - `combine_with(&other, |a, b| a.max(b))` takes the elementwise maximum.
+    ///     - `combine_with(&other, |a, b| a * 3 + b)` blends with weights 3 and 1.
+    /// Panics if the tables have different lengths.
+    fn combine_with<T, C>(&mut self, other: &T, combine: C)
+    where
+        T: CumulFreqTable<F>,
+        C: Fn(F, F) -> F;
+
+    /// Create a table blending two tables with the weights `w_num / w_den` and
+    /// `(w_den - w_num) / w_den`: the frequency of every position is
+    /// `(a * w_num + b * (w_den - w_num)) / w_den`, rounded down with integers. For annealing
+    /// between a prior and a learned model. The tables can be different implementations.
+    /// Panics if the tables have different lengths, if w_den is zero, or if w_num > w_den.
+    /// Panics on overflow of `freq * w_den` in debug.
+    /// O(len).
+    fn blend<A, B>(a: &A, b: &B, w_num: F, w_den: F) -> Self
+    where
+        Self: Sized,
+        F: Frequency + Div<Output = F>,
+        A: CumulFreqTable<F>,
+        B: CumulFreqTable<F>,
+    {
+        assert_eq!(a.len(), b.len(), "tables must have the same length");
+        assert!(w_den != 0.into(), "w_den must be non-zero");
+        assert!(w_num <= w_den, "w_num must be <= w_den");
+        let w_rest = w_den - w_num;
+        Self::from_freqs(
+            a.iter()
+                .zip(b.iter())
+                .map(|((_, fa), (_, fb))| (fa * w_num + fb * w_rest) / w_den),
+        )
+    }
+
+    /// The largest absolute difference between the frequencies of the same position in both
+    /// tables. The tables can be different implementations.
+    /// Panics if the tables have different lengths.
+    fn max_abs_diff<T: CumulFreqTable<F>>(&self, other: &T) -> F
+    where
+        F: Sub<Output = F> + PartialOrd,
+    {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        let mut max = 0.into();
+        for pos in 0..self.len() {
+            let diff = abs_diff(self.freq(pos), other.freq(pos));
+            if diff > max {
+                max = diff;
+            }
+        }
+        max
+    }
+
+    /// Iterate over the positions where the frequencies of both tables differ by more than
+    /// threshold, in increasing order. The tables can be different implementations.
+    /// Panics if the tables have different lengths.
+    fn disagreements<'a, T: CumulFreqTable<F>>(
+        &'a self,
+        other: &'a T,
+        threshold: F,
+    ) -> impl Iterator<Item = usize> + 'a
+    where
+        F: Frequency + 'a,
+    {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        (0..self.len()).filter(move |&pos| abs_diff(self.freq(pos), other.freq(pos)) > threshold)
+    }
+
+    /// The probability of every position: its frequency divided by the total.
+    /// NaN everywhere if the total is zero.
+    /// O(len).
+    fn probabilities(&self) -> Vec<f64>
+    where
+        F: ToF64,
+    {
+        let total = self.total().to_f64();
+        self.iter().map(|(_, freq)| freq.to_f64() / total).collect()
+    }
+
+    /// The cumulative probability of every position: its cumulative frequency divided by the
+    /// total. The last one is 1.
+    /// NaN everywhere if the total is zero.
+    /// O(len).
+    fn cdf(&self) -> Vec<f64>
+    where
+        F: ToF64,
+    {
+        let total = self.total().to_f64();
+        let mut sum = 0.0;
+        self.iter()
+            .map(|(_, freq)| {
+                sum += freq.to_f64();
+                sum / total
+            })
+            .collect()
+    }
+
+    /// The inverse of [CumulFreqTable::cdf]: the first position whose cumulative probability is
+    /// strictly greater than p, so a position with a zero frequency is never returned for p < 1.
+    /// With p uniform in [0, 1), this is inverse transform sampling.
+    /// Returns the last position if p is 1.
+    /// Panics if p is not within [0, 1], or if the total is zero.
+    /// O(㏒₂ len) cumulative frequency queries.
+    fn inverse_cdf(&self, p: f64) -> usize
+    where
+        F: ToF64,
+    {
+        assert!((0.0..=1.0).contains(&p), "p must be within [0, 1]");
+        let total = self.total().to_f64();
+        assert!(total != 0.0, "total must be non-zero");
+        let target = p * total;
+        let (mut lo, mut hi) = (0, self.len() - 1);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.sum(mid).to_f64() > target {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+
+    /// Like [CumulFreqTable::inverse_cdf], with a linear interpolation within the position
+    /// found: position pos spans [pos, pos + 1), and p is located in it by its share of the
+    /// frequency of pos. For fractional quantiles of an empirical distribution.
+    /// Returns len if p is 1.
+    /// Panics if p is not within [0, 1], or if the total is zero.
+    /// O(㏒₂ len) cumulative frequency queries.
+    fn inverse_cdf_interpolated(&self, p: f64) -> f64
+    where
+        F: ToF64,
+    {
+        let pos = self.inverse_cdf(p);
+        let (low, high) = self.bounds(pos);
+        let (low, high) = (low.to_f64(), high.to_f64());
+        let target = p * self.total().to_f64();
+        if target >= high {
+            // Only past the last non-zero frequency, when p is 1.
+            return self.len() as f64;
+        }
+        pos as f64 + (target - low) / (high - low)
+    }
+
+    /// Iterate over the cumulative frequency of every position divided by the total,
+    /// `sum(pos) / total` in position order, as a fraction: the last one is 1.
+    /// Unlike [CumulFreqTable::cdf], the cumulative frequencies are accumulated as F, so they are
+    /// exact with integers, and nothing is allocated.
+    /// NaN everywhere if the total is zero.
+    /// O(len).
+    fn percentages<'a>(&'a self) -> impl Iterator<Item = f64> + 'a
+    where
+        F: Frequency + ToF64 + 'a,
+    {
+        let total = self.total().to_f64();
+        let mut sum: F = 0.into();
+        self.iter().map(move |(_, freq)| {
+            sum += freq;
+            sum.to_f64() / total
+        })
+    }
+
+    /// The mean of the positions, weighted by their frequencies.
+    /// NaN if the total is zero.
+    /// O(len).
+    fn mean_pos(&self) -> f64
+    where
+        F: ToF64,
+    {
+        let mut total = 0.0;
+        let mut weighted = 0.0;
+        for (pos, freq) in self.iter() {
+            let freq = freq.to_f64();
+            total += freq;
+            weighted += pos as f64 * freq;
+        }
+        weighted / total
+    }
+
+    /// The population variance of the positions, weighted by their frequencies.
+    /// NaN if the total is zero.
+    /// O(len), in two passes for accuracy.
+    fn variance_pos(&self) -> f64
+    where
+        F: ToF64,
+    {
+        let mean = self.mean_pos();
+        let mut total = 0.0;
+        let mut squares = 0.0;
+        for (pos, freq) in self.iter() {
+            let freq = freq.to_f64();
+            let delta = pos as f64 - mean;
+            total += freq;
+            squares += delta * delta * freq;
+        }
+        squares / total
+    }
+
+    /// The population standard deviation of the positions, weighted by their frequencies.
+    /// NaN if the total is zero.
+    /// O(len).
+    fn stddev_pos(&self) -> f64
+    where
+        F: ToF64,
+    {
+        self.variance_pos().sqrt()
+    }
+
+    /// The cross-entropy in bits of the distribution of other relative to the distribution of
+    /// self: `-Σ p(pos) log₂ q(pos)`, where p and q are the frequencies divided by the totals.
+    /// This is the average code length when coding symbols distributed like self with a model
+    /// distributed like other. The tables can be different implementations.
+    /// Positions with a zero frequency in self contribute nothing. Infinite if other has a zero
+    /// frequency where self doesn't. NaN if a total is zero.
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    fn cross_entropy<T: CumulFreqTable<F>>(&self, other: &T) -> f64
+    where
+        F: ToF64,
+    {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        let (p_total, q_total) = (self.total().to_f64(), other.total().to_f64());
+        let mut bits = 0.0;
+        for (pos, freq) in self.iter() {
+            let p = freq.to_f64() / p_total;
+            if p > 0.0 {
+                bits -= p * (other.freq(pos).to_f64() / q_total).log2();
+            }
+        }
+        bits
+    }
+
+    /// The Kullback-Leibler divergence in bits of the distribution of other from the distribution
+    /// of self: `Σ p(pos) log₂ (p(pos) / q(pos))`. This is the average number of bits wasted by
+    /// coding symbols distributed like self with a model distributed like other.
+    /// Zero frequencies are handled like [CumulFreqTable::cross_entropy].
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    fn kl_divergence<T: CumulFreqTable<F>>(&self, other: &T) -> f64
+    where
+        F: ToF64,
+    {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        let (p_total, q_total) = (self.total().to_f64(), other.total().to_f64());
+        let mut bits = 0.0;
+        for (pos, freq) in self.iter() {
+            let p = freq.to_f64() / p_total;
+            if p > 0.0 {
+                bits += p * (p * q_total / other.freq(pos).to_f64()).log2();
+            }
+        }
+        bits
+    }
+
+    /// Pearson's chi-square statistic of the observed frequencies of self against the expected
+    /// frequencies: `Σ (O - E)² / E`. The expected frequencies are scaled to the total of self,
+    /// so only their proportions matter. The tables can be different implementations.
+    /// Positions expected with a zero frequency contribute nothing if none are observed, and make
+    /// the statistic infinite otherwise.
+    /// Zero if the total of self is zero and not the expected one, NaN if the expected total is
+    /// zero.
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    fn chi_square<T: CumulFreqTable<F>>(&self, expected: &T) -> f64
+    where
+        F: ToF64,
+    {
+        assert_eq!(
+            self.len(),
+            expected.len(),
+            "tables must have the same length"
+        );
+        let scale = self.total().to_f64() / expected.total().to_f64();
+        let mut chi2 = 0.0;
+        for (pos, freq) in self.iter() {
+            let (o, e) = (freq.to_f64(), expected.freq(pos).to_f64() * scale);
+            if o != 0.0 || e != 0.0 {
+                chi2 += (o - e) * (o - e) / e;
+            }
+        }
+        chi2
+    }
+
+    /// Pearson's chi-square statistic of the observed frequencies of self against a uniform
+    /// distribution: `Σ (O - E)² / E` with `E = total / len`.
+    /// NaN if the total is zero.
+    /// O(len).
+    fn chi_square_uniform(&self) -> f64
+    where
+        F: ToF64,
+    {
+        let e = self.total().to_f64() / self.len() as f64;
+        self.iter()
+            .map(|(_, freq)| {
+                let o = freq.to_f64();
+                (o - e) * (o - e) / e
+            })
+            .sum()
+    }
+
+    /// The Gini coefficient of the frequencies: 0 when every position has the same frequency, up
+    /// to `1 - 1 / len` when a single position holds the whole total.
+    /// The Lorenz curve accumulates the frequencies in increasing order, not in position order, so
+    /// the frequencies are sorted first.
+    /// NaN if the total is zero. Assumes non-negative frequencies.
+    /// O(len ㏒₂ len).
+    fn gini(&self) -> f64
+    where
+        F: ToF64,
+    {
+        let mut freqs: Vec<f64> = self.iter().map(|(_, freq)| freq.to_f64()).collect();
+        freqs.sort_unstable_by(f64::total_cmp);
+        // The area between the Lorenz curve and the diagonal, from the ranks of the frequencies.
+        let n = freqs.len() as f64;
+        let mut total = 0.0;
+        let mut weighted = 0.0;
+        for (rank, freq) in freqs.iter().enumerate() {
+            total += freq;
+            weighted += (rank + 1) as f64 * freq;
+        }
+        2.0 * weighted / (n * total) - (n + 1.0) / n
+    }
+
+    /// Rescale the frequencies so they sum to exactly `2^total_bits`, while every non-zero
+    /// frequency stays at least 1. This is the normalization required by range coders and rANS.
+    /// The remainder of the rounding goes to the positions with the largest fractional parts.
+    /// Panics if the total is zero or if there are more non-zero positions than `2^total_bits`.
+    /// Panics if total_bits > 63, if a frequency doesn't fit in u64 or if `2^total_bits` doesn't
+    /// fit in F.
+    fn quantize_to(&self, total_bits: u32) -> Vec<F>
+    where
+        F: TryInto<u64>,
+        u64: TryInto<F>,
+    {
+        let freqs: Vec<u64> = (0..self.len())
+            .map(|pos| match self.freq(pos).try_into() {
+                Ok(freq) => freq,
+                Err(_) => panic!("frequency doesn't fit in u64"),
+            })
+            .collect();
+        quantize::quantize(&freqs, total_bits)
+            .into_iter()
+            .map(|freq| match freq.try_into() {
+                Ok(freq) => freq,
+                Err(_) => panic!("quantized frequency doesn't fit in F"),
+            })
+            .collect()
+    }
+
+    /// Write the table in a compact binary format. See [dump] for the format.
+    /// The dump only contains the frequencies, so it can be read back by any implementation.
+    fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()>
+    where
+        F: dump::Element,
+    {
+        dump::write_header::<F, W>(w, self.len())?;
+        for pos in 0..self.len() {
+            self.freq(pos).write_le(w)?;
+        }
+        Ok(())
+    }
+
+    /// Read a table written by [CumulFreqTable::write_to].
+    /// Fails with [std::io::ErrorKind::InvalidData] if the header doesn't match the frequency type.
+    fn read_from<R: std::io::Read>(r: &mut R) -> std::io::Result<Self>
+    where
+        Self: Sized,
+        F: dump::Element,
+    {
+        let len = dump::read_header::<F, R>(r)?;
+        let freqs = dump::read_freqs::<F, R>(r, len)?;
+        Ok(Self::from_freqs(freqs))
+    }
+
+    /// Export the length, total and frequencies as a JSON document. See [json] for the format.
+    #[cfg(feature = "json")]
+    fn to_json(&self) -> String
+    where
+        F: serde::Serialize,
+    {
+        json::to_string(
+            self.total(),
+            (0..self.len()).map(|pos| self.freq(pos)).collect(),
+        )
+    }
+
+    /// Create a table from a JSON document written by [CumulFreqTable::to_json].
+    /// The total is not trusted and recomputed from the frequencies.
+    #[cfg(feature = "json")]
+    fn from_json(s: &str) -> serde_json::Result<Self>
+    where
+        Self: Sized,
+        F: serde::de::DeserializeOwned,
+    {
+        json::from_str::<F>(s).map(Self::from_freqs)
+    }
+}
+
+pub mod ac;
+pub mod adaptive;
+#[cfg(feature = "bigint")]
+pub mod bigint;
+pub mod binary_indexed_tree;
+pub mod capped;
+pub mod checked;
+mod convert;
+pub mod cumulfreq_array;
+pub mod decay;
+pub mod dump;
+pub mod ema;
+pub mod escape;
+pub mod freq_array;
+mod histogram;
+pub mod huffman;
+#[cfg(feature = "json")]
+pub mod json;
+mod kahan;
+pub mod mixed;
+pub mod multiset;
+#[cfg(feature = "num-traits")]
+pub mod num;
+mod quantize;
+#[cfg(feature = "rans")]
+pub mod rans;
+#[cfg(feature = "rand")]
+pub mod sample;
+pub mod smoothed;
+pub mod stats;
+pub mod treap;
+pub mod view;
+pub mod window;
+
+use std::convert::From;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Bound, Div, Mul, RangeBounds, Shr, Sub, SubAssign};
+
+pub use adaptive::AdaptiveModel;
+#[cfg(feature = "bigint")]
+pub use bigint::CumulFreqTable as BigTable;
+pub use binary_indexed_tree::CumulFreqTable as BinaryIndexedTree;
+pub use capped::CappedTable;
+pub use checked::{Checked, OverflowError, TableError};
+pub use decay::DecayingTable;
+pub use ema::EmaTable;
+pub use escape::EscapeModel;
+pub use freq_array::FreqTable;
+pub use mixed::MixedView;
+pub use multiset::CountingMultiset;
+#[cfg(feature = "rand")]
+pub use sample::AliasSampler;
+pub use smoothed::SmoothedView;
+pub use stats::ToF64;
+pub use treap::CumulFreqTable as Treap;
+pub use view::{TableView, TableViewMut};
+pub use window::SlidingWindowTable;
+
+/// The bounds shared by the frequency types of all the implementations, so generic code over
+/// [CumulFreqTable] needs a single bound whatever the implementation. Implemented for every type
+/// satisfying them, which includes every primitive integer and float type.
+pub trait Frequency:
+    From<u8>
+    + Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Sum
+{
+}
+
+impl<F> Frequency for F where
+    F: From<u8>
+        + Copy
+        + PartialOrd
+        + Add<Output = F>
+        + Sub<Output = F>
+        + Mul<Output = F>
+        + AddAssign
+        + SubAssign
+        + Sum
+{
+}
+
+/// Convert a range into its first and last positions. Returns None for an empty range.
+/// Panics if the range is out of bounds.
+fn inclusive_range<R: RangeBounds<usize>>(range: R, len: usize) -> Option<(usize, usize)> {
+    let first = match range.start_bound() {
+        Bound::Included(&first) => first,
+        Bound::Excluded(&first) => first.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&last) => last.checked_add(1).expect("range out of bounds"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(end <= len, "range out of bounds");
+    if first < end {
+        Some((first, end - 1))
+    } else {
+        None
+    }
+}
+
+/// Count the observed positions. The length is one past the largest position.
+fn tally<F, I>(observations: I) -> Vec<F>
+where
+    F: From<u8> + Copy + AddAssign,
+    I: IntoIterator<Item = usize>,
+{
+    let mut freqs: Vec<F> = Vec::new();
+    for pos in observations {
+        if pos >= freqs.len() {
+            freqs.resize(pos + 1, 0.into());
+        }
+        freqs[pos] += 1.into();
+    }
+    freqs
+}
+
+/// `|a - b|`, without going negative for unsigned types.
+fn abs_diff<F: Sub<Output = F> + PartialOrd>(a: F, b: F) -> F {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::From;
+    use std::fmt::Debug;
+    use std::ops::Div;
+
+    #[test]
+    fn long_test_usize() {
+        long_test::<usize>();
+    }
+
+    #[test]
+    fn long_test_i16() {
+        long_test::<i16>();
+    }
+
+    #[test]
+    fn long_test_u16() {
+        long_test::<u16>();
+    }
+
+    #[test]
+    fn long_test_u128() {
+        long_test::<u128>();
+    }
+
+    #[cfg(feature = "num-traits")]
+    #[test]
+    fn long_test_num() {
+        long_test::<num::NumFreq<u32>>();
+        long_test::<num::NumFreq<i64>>();
+    }
+
+    fn long_test<F>()
+    where
+        F: Frequency + Debug + Div<Output = F> + 'static,
+        usize: TryInto<F>,
+        <usize as TryInto<F>>::Error: std::fmt::Debug,
+    {
+        for len in 1..=32 {
+            //dbg!("freq_array", len);
+            long_test_impl::<F, freq_array::FreqTable<F>>(len);
+            //dbg!("cumulfreq_array", len);
+            long_test_impl::<F, cumulfreq_array::CumulFreqTable<F>>(len);
+            //dbg!("binary_indexed_tree", len);
+            long_test_impl::<F, binary_indexed_tree::CumulFreqTable<F>>(len);
+            //dbg!("treap", len);
+            long_test_impl::<F, treap::CumulFreqTable<F>>(len);
+        }
+    }
+
+    fn long_test_impl<F, T>(len: usize)
+    where
+        F: Frequency + Debug + Div<Output = F>,
+        usize: TryInto<F>,
+        <usize as TryInto<F>>::Error: std::fmt::Debug,
+        T: CumulFreqTable<F> + Debug + 'static,
+    {
+        let flen: F = (len as u8).into();
+        let f0: F = 0_u8.into();
+        let f1: F = 1_u8.into();
+        let f2: F = 2_u8.into();
+        let f3: F = 3_u8.into();
+        let f42: F = 42_u8.into();
+
+        // Table with all freqs to 1.
+        let mut table: T = T::with_freq(len, f1);
+        //dbg!(&table);
+
+        assert_eq!(table.len(), len);
+        assert_eq!(table.total(), flen);
+        for i in 0..len {
+            assert_eq!(table.freq(i), f1);
+            assert_eq!(table.sum(i), F::from(i as u8) + f1);
+        }
+
+        // All freqs to zero.
+        for i in 0..len {
+            table.dec(i);
+        }
+        assert_eq!(table.total(), f0);
+
+        // Adding zeroes, should not change anything.
+        for i in 0..len {
+            assert_eq!(table.freq(i), f0);
+            assert_eq!(table.sum(i), f0);
+        }
+        assert_eq!(table.total(), f0);
+
+        // All freqs to 1.
+        for i in 0..len {
+            table.inc(i);
+            assert_eq!(table.freq(i), f1);
+            assert_eq!(table.sum(i), F::from(i as u8) + f1);
+        }
+        assert_eq!(table.total(), flen);
+
+        // All freqs to 3.
+        for i in 0..len {
+            table.add(i, 2_u8.into());
+            assert_eq!(table.freq(i), f3);
+            assert_eq!(table.sum(i), (F::from(i as u8) + f1) * f3);
+        }
+        assert_eq!(table.total(), flen * f3);
+
+        // All freqs should still be 3.
+        for i in 0..len {
+            assert_eq!(table.freq(i), f3);
+            assert_eq!(table.sum(i), (F::from(i as u8) + f1) * f3);
+        }
+
+        // Find every element by sum.
+        for i in 0..len {
+            //dbg!(i, (i + 1) * 3, table.sum(i));
+            assert_eq!(table.find_by_sum((F::from(i as u8) + f1) * f3), i);
+        }
+
+        // Divide by two, flooring. So all freqs become 1.
+        table.scale(|x| x / f2);
+        assert_eq!(table.total(), flen);
+        for i in 0..len {
+            assert_eq!(table.freq(i), f3 / f2);
+            assert_eq!(table.sum(i), F::from(i as u8) + f1);
+        }
+
+        // Bring every other positions to 42.
+        for i in (0..len).step_by(2) {
+            table.add(i, f42 - f1);
+        }
+        assert_eq!(
+            table.total(),
+            (F::from(len as u8) - F::from(len as u8) / f2) * f42 + (F::from(len as u8) / f2)
+        );
+        for i in 0..len {
+            if i % 2 == 0 {
+                assert_eq!(table.freq(i), f42);
+            } else {
+                assert_eq!(table.freq(i), f1);
+            }
+            assert_eq!(
+                table.sum(i),
+                ((F::from(i as u8) + f2) / f2 * f42) + ((F::from(i as u8) + f1) / f2)
+            );
+        }
+    }
+
+    #[test]
+    fn scale_test() {
+        type F = usize;
+        for len in 1..=32 {
+            //dbg!("scale_test freq_array", len);
+            scale_test_impl::<F, freq_array::FreqTable<F>>(len);
+            //dbg!("scale_test cumulfreq_array", len);
+            scale_test_impl::<F, cumulfreq_array::CumulFreqTable<F>>(len);
+            //dbg!("scale_test binary_indexed_tree", len);
+            scale_test_impl::<F, binary_indexed_tree::CumulFreqTable<F>>(len);
+            //dbg!("scale_test treap", len);
+            scale_test_impl::<F, treap::CumulFreqTable<F>>(len);
+        }
+    }
+
+    fn scale_test_impl<F, T>(len: usize)
+    where
+        F: Frequency + Debug + Div<Output = F>,
+        usize: TryInto<F>,
+        <usize as TryInto<F>>::Error: std::fmt::Debug,
+        T: CumulFreqTable<F> + Debug + 'static + Clone,
+    {
+        let mut table = T::with_freq(len, 1.into());
+        for i in 0..len {
+            assert_eq!(table.freq(i), 1.into());
+        }
+        table.scale(|x| x * 2.into());
+        for i in 0..len {
+            assert_eq!(table.freq(i), 2.into());
+        }
+        table.scale(|x| (x + 1.into()) / 2.into());
+        for i in 0..len {
+            assert_eq!(table.freq(i), 1.into());
+        }
+        table.scale(|x| x * 42.into());
+        for i in 0..len {
+            assert_eq!(table.freq(i), 42.into());
+        }
+        let mut a = table.clone();
+        let mut b = table.clone();
+        a.scale(|x| x / 5.into());
+        b.scale(|x| (x + 1.into()) / 5.into());
+        for i in 0..len {
+            assert_eq!(a.freq(i), 8.into());
+        }
+        for i in 0..len {
+            assert_eq!(b.freq(i), 8.into());
+        }
+        a.scale(|x| x / 9.into());
+        b.scale(|x| (x + 1.into()) / 9.into());
+        for i in 0..len {
+            assert_eq!(a.freq(i), 0.into());
+        }
+        for i in 0..len {
+            assert_eq!(b.freq(i), 1.into());
+        }
+    }
+
+    #[test]
+    fn halve_test() {
+        for len in 1..=32 {
+            halve_test_impl::<freq_array::FreqTable<u32>>(len);
+            halve_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            halve_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            halve_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+        let mut table = BinaryIndexedTree::<f64>::from_freqs([3.0, 0.5, 0.0, 7.25, 1.0]);
+        table.halve_down();
+        assert_eq!(table.to_freq_vec(), [1.5, 0.25, 0.0, 3.625, 0.5]);
+        assert_eq!(table.total(), 5.875);
+    }
+
+    fn halve_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug + Clone,
+    {
+        let table = T::from_freqs((0..len).map(|pos| (pos * pos % 11) as u32));
+        let mut down = table.clone();
+        down.halve_down();
+        let mut up = table.clone();
+        up.halve_up();
+        for pos in 0..len {
+            let freq = table.freq(pos);
+            assert_eq!(down.freq(pos), freq / 2);
+            assert_eq!(up.freq(pos), freq.div_ceil(2));
+            assert_eq!(up.freq(pos) > 0, freq > 0);
+        }
+        assert_eq!(down.total(), down.sum(len - 1));
+        assert_eq!(up.total(), up.sum(len - 1));
+    }
+
+    #[test]
+    fn map_freqs_test() {
+        for len in 1..=32 {
+            map_freqs_test_impl::<freq_array::FreqTable<u32>>(len);
+            map_freqs_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            map_freqs_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            map_freqs_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+    }
+
+    fn map_freqs_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let mut table = T::with_freq(len, 2);
+        let mut visited = Vec::new();
+        // Boost the first half.
+        table.map_freqs(|pos, freq| {
+            visited.push(pos);
+            if pos < len / 2 {
+                freq * 5
+            } else {
+                freq - 1
+            }
+        });
+        assert_eq!(visited, (0..len).collect::<Vec<_>>());
+        for pos in 0..len {
+            assert_eq!(table.freq(pos), if pos < len / 2 { 10 } else { 1 });
+        }
+        assert_eq!(table.total(), table.sum(len - 1));
+        assert_eq!(table.count_nonzero(), len);
+    }
+
+    #[test]
+    fn retain_test() {
+        for len in 1..=32 {
+            retain_test_impl::<freq_array::FreqTable<u32>>(len);
+            retain_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            retain_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            retain_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+    }
+
+    fn retain_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let freqs = |pos: usize| (pos * 5 % 7) as u32;
+        let mut table = T::from_freqs((0..len).map(freqs));
+        // Prune the rare positions, and every third one.
+        table.retain(|pos, freq| freq >= 3 && pos % 3 != 0);
+        assert_eq!(table.len(), len);
+        for pos in 0..len {
+            let kept = freqs(pos) >= 3 && pos % 3 != 0;
+            assert_eq!(table.freq(pos), if kept { freqs(pos) } else { 0 });
+        }
+        assert_eq!(table.total(), table.sum(len - 1));
+    }
+
+    #[test]
+    fn decay_shift_test() {
+        for len in 1..=32 {
+            decay_shift_test_impl::<freq_array::FreqTable<u16>>(len);
+            decay_shift_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            decay_shift_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            decay_shift_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn decay_shift_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let freqs = |pos: usize| (pos * 37 % 101) as u16;
+        let mut table = T::from_freqs((0..len).map(freqs));
+        table.decay_shift(2);
+        for pos in 0..len {
+            assert_eq!(table.freq(pos), freqs(pos) >> 2);
+        }
+        assert_eq!(table.total(), table.sum(len - 1));
+        table.decay_shift(7);
+        assert_eq!(table.total(), 0);
+        let mut table = T::from_freqs((0..len).map(freqs));
+        table.decay_shift(16);
+        assert_eq!(table.total(), 0);
+        assert_eq!(table.len(), len);
+        table.decay_shift(u32::MAX);
+        assert_eq!(table.total(), 0);
+    }
+
+    #[test]
+    fn scale_ret_test() {
+        for len in 1..=32 {
+            scale_ret_test_impl::<freq_array::FreqTable<u32>>(len);
+            scale_ret_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            scale_ret_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            scale_ret_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+    }
+
+    fn scale_ret_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let mut table = T::from_freqs((0..len).map(|pos| pos as u32 * 3 + 1));
+        let total = table.scale_ret(|f| f / 4);
+        assert_eq!(total, table.total());
+        assert_eq!(
+            total,
+            (0..len).map(|pos| (pos as u32 * 3 + 1) / 4).sum::<u32>()
+        );
+    }
+
+    #[test]
+    fn scale_min1_test() {
+        for len in 1..=32 {
+            scale_min1_test_impl::<freq_array::FreqTable<u32>>(len);
+            scale_min1_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            scale_min1_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            scale_min1_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+        let mut table = FreqTable::<f32>::from_freqs([0.5, 0.0, 8.0]);
+        table.scale_min1(|f| f / 4.0);
+        assert_eq!(table.to_freq_vec(), [1.0, 0.0, 2.0]);
+    }
+
+    fn scale_min1_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug + Clone,
+    {
+        let table = T::from_freqs((0..len).map(|pos| (pos * pos % 13) as u32));
+        let mut scaled = table.clone();
+        scaled.scale_min1(|f| f / 8);
+        for pos in 0..len {
+            let freq = table.freq(pos);
+            let expected = if freq > 0 { (freq / 8).max(1) } else { 0 };
+            assert_eq!(scaled.freq(pos), expected);
+        }
+        assert_eq!(scaled.total(), scaled.sum(len - 1));
+    }
+
+    #[test]
+    fn bit_total_test() {
+        // The total is maintained by every operation.
+        let check =
+            |table: &BinaryIndexedTree<u32>| assert_eq!(table.total(), table.sum(table.len() - 1));
+        let mut table = BinaryIndexedTree::<u32>::with_freq(13, 2);
+        check(&table);
+        table.add(0, 3);
+        table.add(7, 5);
+        table.sub(12, 1);
+        check(&table);
+        table.add_range(2..9, 4);
+        table.sub_range(3..5, 1);
+        check(&table);
+        table.push(6);
+        table.extend_with(4, 1);
+        check(&table);
+        assert_eq!(table.pop(), 1);
+        table.truncate(11);
+        check(&table);
+        table.rotate_left(3);
+        table.reverse();
+        check(&table);
+        table.scale(|freq| freq / 2);
+        check(&table);
+        let other = table.clone();
+        table.merge_add(&other);
+        check(&table);
+        table.fill(3);
+        check(&table);
+        table.clear();
+        assert_eq!(table.total(), 0);
+    }
+
+    #[test]
+    fn bit_debug_test() {
+        let table = BinaryIndexedTree::<u8>::from_freqs([1, 2, 3, 4]);
+        assert_eq!(
+            format!("{table:?}"),
+            "CumulFreqTable { tree: [1, 3, 3, 10] }"
+        );
+        assert_eq!(
+            format!("{table:#?}"),
+            "CumulFreqTable {
+    positions: {
+        0: (1, 1),
+        1: (2, 3),
+        2: (3, 6),
+        3: (4, 10),
+    },
+    levels: [
+        {
+            0..=0: 1,
+            2..=2: 3,
+        },
+        {
+            0..=1: 3,
+        },
+        {
+            0..=3: 10,
+        },
+    ],
+}"
+        );
+        let table = BinaryIndexedTree::<u8>::new(1);
+        assert_eq!(
+            format!("{table:#?}"),
+            "CumulFreqTable {
+    positions: {
+        0: (0, 0),
+    },
+    levels: [
+        {
+            0..=0: 0,
+        },
+    ],
+}"
+        );
+    }
+
+    #[test]
+    fn bit_padded_test() {
+        for len in 1..=32 {
+            let freqs: Vec<u32> = (0..len).map(|pos| (pos as u32 * 7 + 3) % 5).collect();
+            let mut plain = BinaryIndexedTree::from_freqs(freqs.iter().copied());
+            let mut padded = plain.clone().padded();
+            assert!(padded.is_padded());
+            let check = |plain: &BinaryIndexedTree<u32>, padded: &BinaryIndexedTree<u32>| {
+                assert_eq!(padded, plain);
+                assert_eq!(format!("{padded:?}"), format!("{plain:?}"));
+                assert_eq!(padded.len(), plain.len());
+                assert_eq!(padded.total(), plain.total());
+                for pos in 0..plain.len() {
+                    assert_eq!(padded.freq(pos), plain.freq(pos));
+                    assert_eq!(padded.sum(pos), plain.sum(pos));
+                }
+                for sum in 0..=plain.total() + 1 {
+                    assert_eq!(padded.find_by_sum(sum), plain.find_by_sum(sum));
+                    assert_eq!(
+                        padded.find_by_sum_strict(sum),
+                        plain.find_by_sum_strict(sum)
+                    );
+                    assert_eq!(padded.find_with_bounds(sum), plain.find_with_bounds(sum));
+                }
+            };
+            check(&plain, &padded);
+            for table in [&mut plain, &mut padded] {
+                table.push(4);
+                table.extend_with(3, 1);
+                table.add(len / 2, 6);
+            }
+            check(&plain, &padded);
+            assert_eq!(padded.pop(), plain.pop());
+            for table in [&mut plain, &mut padded] {
+                table.truncate(len.div_ceil(2));
+                table.reverse();
+                table.rotate_left(table.len() / 3);
+                table.scale(|freq| freq * 2);
+            }
+            check(&plain, &padded);
+            plain.fill(2);
+            padded.fill(2);
+            check(&plain, &padded);
+            assert_eq!(padded.into_vec(), plain.into_vec());
+        }
+    }
+
+    #[test]
+    fn memory_bytes_test() {
+        for len in 1..=32 {
+            memory_bytes_test_impl::<freq_array::FreqTable<u32>>(len);
+            memory_bytes_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            memory_bytes_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            memory_bytes_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+        let table = FreqTable::<u32>::new(10);
+        assert_eq!(
+            table.memory_bytes(),
+            std::mem::size_of::<FreqTable<u32>>() + 40
+        );
+        let capped = CappedTable::<FreqTable<u32>, u32>::new(10, 3);
+        assert!(capped.memory_bytes() >= table.memory_bytes() + std::mem::size_of::<u32>());
+        let mut window = SlidingWindowTable::<FreqTable<u32>, u32>::new(10, 3);
+        window.observe(2);
+        assert!(window.memory_bytes() >= table.memory_bytes() + 3 * std::mem::size_of::<usize>());
+    }
+
+    fn memory_bytes_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32>,
+    {
+        let table = T::new(len);
+        let bytes = table.memory_bytes();
+        assert!(bytes >= std::mem::size_of::<T>() + len * std::mem::size_of::<u32>());
+        // At most a few words per position, with the spare capacity of a growing Vec.
+        assert!(bytes <= std::mem::size_of::<T>() + len * 128);
+    }
+
+    #[test]
+    fn reserve_shrink_test() {
+        for len in 1..=32 {
+            reserve_shrink_test_impl::<freq_array::FreqTable<u32>>(len);
+            reserve_shrink_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            reserve_shrink_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            reserve_shrink_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+    }
+
+    fn reserve_shrink_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug + PartialEq,
+    {
+        let freqs: Vec<u32> = (0..len as u32).map(|i| i % 5).collect();
+        let mut table = T::from_freqs(freqs.iter().copied());
+        table.shrink_to_fit();
+        let bytes = table.memory_bytes();
+        table.reserve(100);
+        assert!(table.memory_bytes() >= bytes + 100 * std::mem::size_of::<u32>());
+        let reserved = table.memory_bytes();
+        table.extend_with(100, 1);
+        assert_eq!(table.memory_bytes(), reserved);
+
+        table.truncate(len);
+        table.shrink_to_fit();
+        assert_eq!(table.memory_bytes(), bytes);
+        assert_eq!(table, T::from_freqs(freqs));
+    }
+
+    #[test]
+    fn raw_parts_test() {
+        let freqs = vec![3.5, 0.0, 1.25, 2.0];
+        let ptr = freqs.as_ptr();
+        let table = FreqTable::from_raw_parts(freqs);
+        assert_eq!(table.as_freq_slice(), [3.5, 0.0, 1.25, 2.0]);
+        assert_eq!(table.as_freq_slice().as_ptr(), ptr);
+        assert_eq!(table.total(), 6.75);
+        assert_eq!(table.count_nonzero(), 3);
+        let freqs = table.into_raw_parts();
+        assert_eq!(freqs.as_ptr(), ptr);
+        assert_eq!(freqs, [3.5, 0.0, 1.25, 2.0]);
+    }
+
+    #[test]
+    fn from_counts_test() {
+        for len in 1..=32 {
+            let counts: Vec<u64> = (0..len).map(|pos| pos * pos % 7).collect();
+            let table = BinaryIndexedTree::from_counts(&counts);
+            let mut expected = BinaryIndexedTree::new(len as usize);
+            for (pos, &count) in counts.iter().enumerate() {
+                expected.add(pos, count);
+            }
+            assert_eq!(table, expected);
+            assert_eq!(table.total(), counts.iter().sum::<u64>());
+            assert_eq!(table.to_freq_vec(), counts);
+        }
+    }
+
+    #[test]
+    fn dump_test() {
+        for len in 1..=32 {
+            dump_test_impl::<freq_array::FreqTable<u16>>(len);
+            dump_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            dump_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            dump_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+
+        // Any implementation can read back a dump.
+        let mut table = FreqTable::<usize>::new(5);
+        table.add(1, 3);
+        table.add(4, 7);
+        let mut buf = Vec::new();
+        table.write_to(&mut buf).unwrap();
+        assert_eq!(buf.len(), 4 + 2 + 8 + 5 * 8);
+        let other = BinaryIndexedTree::<usize>::read_from(&mut buf.as_slice()).unwrap();
+        for i in 0..5 {
+            assert_eq!(other.freq(i), table.freq(i));
+        }
+
+        // Mismatching element width.
+        let err = FreqTable::<u32>::read_from(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // Truncated payload.
+        let err = FreqTable::<usize>::read_from(&mut &buf[..buf.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    fn dump_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        for i in 0..len {
+            table.add(i, (i * 3) as u16);
+        }
+        let mut buf = Vec::new();
+        table.write_to(&mut buf).unwrap();
+        let read = T::read_from(&mut buf.as_slice()).unwrap();
+        assert_eq!(read.len(), len);
+        for i in 0..len {
+            assert_eq!(read.freq(i), table.freq(i));
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_test() {
+        let mut table = BinaryIndexedTree::<usize>::new(3);
+        table.add(0, 1);
+        table.add(2, 4);
+        let json = table.to_json();
+        assert_eq!(json, r#"{"len":3,"total":5,"freqs":[1,0,4]}"#);
+        let read = FreqTable::<usize>::from_json(&json).unwrap();
+        assert_eq!(read.total(), 5);
+        assert_eq!(read.freq(2), 4);
+
+        assert!(FreqTable::<usize>::from_json(r#"{"len":2,"total":1,"freqs":[1]}"#).is_err());
+        assert!(FreqTable::<usize>::from_json(r#"{"len":0,"total":0,"freqs":[]}"#).is_err());
+        assert!(FreqTable::<u8>::from_json(r#"{"len":1,"total":256,"freqs":[256]}"#).is_err());
+    }
+
+    #[test]
+    fn resize_test() {
+        for len in 1..=32 {
+            resize_test_impl::<freq_array::FreqTable<u16>>(len);
+            resize_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            resize_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            resize_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn resize_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        for i in 0..len {
+            table.add(i, i as u16 + 1);
+        }
+        for new_len in len..=len * 3 {
+            let mut grown = T::new(1);
+            grown.resize(len);
+            for i in 0..len {
+                grown.add(i, i as u16 + 1);
+            }
+            grown.resize(new_len);
+            assert_eq!(grown.len(), new_len);
+            assert_eq!(grown.total(), table.total());
+            for i in 0..new_len {
+                let expected = if i < len { i as u16 + 1 } else { 0 };
+                assert_eq!(grown.freq(i), expected);
+                assert_eq!(grown.sum(i), table.sum(i.min(len - 1)));
+            }
+            // Shrink back, the removed positions are all zero.
+            grown.resize(len);
+            for i in 0..len {
+                assert_eq!(grown.freq(i), table.freq(i));
+                assert_eq!(grown.sum(i), table.sum(i));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "removed frequencies must sum to zero")]
+    fn resize_non_zero_test() {
+        let mut table = BinaryIndexedTree::<usize>::new(4);
+        table.inc(3);
+        table.resize(3);
+    }
+
+    #[test]
+    fn resize_float_test() {
+        let mut table = BinaryIndexedTree::<f64>::from_freqs([0.1, 0.2, 0.3, 0.0]);
+        table.add(0, 0.7);
+        table.add(2, 1e-3);
+        table.sub(1, 0.05);
+        table.resize(3);
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.sum(2), table.sum_range(..));
+    }
+
+    #[test]
+    fn push_pop_test() {
+        for len in 1..=32 {
+            push_pop_test_impl::<freq_array::FreqTable<u16>>(len);
+            push_pop_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            push_pop_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            push_pop_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn push_pop_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(1);
+        table.add(0, 7);
+        for i in 1..len {
+            table.push(i as u16);
+            assert_eq!(table.len(), i + 1);
+            assert_eq!(table.freq(i), i as u16);
+        }
+        let mut expected = T::new(len);
+        expected.add(0, 7);
+        for i in 1..len {
+            expected.add(i, i as u16);
+        }
+        for i in 0..len {
+            assert_eq!(table.freq(i), expected.freq(i));
+            assert_eq!(table.sum(i), expected.sum(i));
+        }
+        assert_eq!(table.find_by_sum(table.total()), len - 1);
+        for i in (1..len).rev() {
+            assert_eq!(table.pop(), i as u16);
+            assert_eq!(table.total(), expected.sum(i - 1));
+        }
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn split_concat_test() {
+        for len in 2..=32 {
+            split_concat_test_impl::<freq_array::FreqTable<u32>>(len);
+            split_concat_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            split_concat_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            split_concat_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+    }
+
+    #[test]
+    fn bit_padded_split_concat_test() {
+        for len in 2..=32 {
+            let table = BinaryIndexedTree::<u32>::from_freqs((0..len).map(|pos| pos % 4));
+            for mid in 1..len as usize {
+                let mut head = table.clone().padded();
+                let tail = head.split_off(mid);
+                assert!(head.is_padded() && tail.is_padded());
+                assert_eq!(
+                    head,
+                    BinaryIndexedTree::from_freqs(table.iter().take(mid).map(|(_, f)| f))
+                );
+                assert_eq!(
+                    tail,
+                    BinaryIndexedTree::from_freqs(table.iter().skip(mid).map(|(_, f)| f))
+                );
+                let whole = head.concat(tail);
+                assert!(whole.is_padded());
+                assert_eq!(whole, table);
+                assert_eq!(
+                    whole.find_by_sum(whole.total()),
+                    table.find_by_sum(table.total())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn treap_split_concat_test() {
+        // The nodes move with their priorities, so the shape of the treaps is kept instead of
+        // rebuilt from the frequencies.
+        for len in 2..=64 {
+            let table = Treap::<u32>::from_freqs((0..len).map(|pos| pos % 3));
+            let prios = table.priorities();
+            for mid in 1..len as usize {
+                let mut head = table.clone();
+                let tail = head.split_off(mid);
+                assert_eq!(head.priorities(), prios[..mid]);
+                assert_eq!(tail.priorities(), prios[mid..]);
+                assert_eq!(
+                    head.count_nonzero() + tail.count_nonzero(),
+                    table.count_nonzero()
+                );
+                let whole = head.concat(tail);
+                assert_eq!(whole.priorities(), prios);
+                assert_eq!(whole, table);
+            }
+        }
+    }
+
+    fn split_concat_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug + Clone,
+    {
+        let freqs = |pos: usize| (pos * 3 % 5) as u32;
+        let table = T::from_freqs((0..len).map(freqs));
+        for mid in 1..len {
+            let mut head = table.clone();
+            let tail = head.split_off(mid);
+            assert_eq!(head.len(), mid);
+            assert_eq!(tail.len(), len - mid);
+            for pos in 0..len {
+                let freq = if pos < mid {
+                    head.freq(pos)
+                } else {
+                    tail.freq(pos - mid)
+                };
+                assert_eq!(freq, freqs(pos));
+            }
+            assert_eq!(head.total() + tail.total(), table.total());
+            assert_eq!(tail.total(), tail.sum(len - mid - 1));
+            assert_eq!(
+                head.count_nonzero() + tail.count_nonzero(),
+                table.count_nonzero()
+            );
+            let whole = head.concat(tail);
+            assert_eq!(whole.len(), len);
+            assert_eq!(whole.total(), table.total());
+            for pos in 0..len {
+                assert_eq!(whole.freq(pos), freqs(pos));
+                assert_eq!(whole.sum(pos), table.sum(pos));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "mid out of bounds")]
+    fn split_off_empty_test() {
+        FreqTable::<u32>::new(3).split_off(3);
+    }
+
+    #[test]
+    fn grow_double_test() {
+        for len in 1..=32 {
+            grow_double_test_impl::<freq_array::FreqTable<u32>>(len);
+            grow_double_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            grow_double_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            grow_double_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+        let mut padded = BinaryIndexedTree::<u32>::from_freqs([1, 2, 3, 4, 5]).padded();
+        padded.grow_double();
+        padded.grow_double();
+        let mut expected = vec![0; 20];
+        expected[..5].copy_from_slice(&[1, 2, 3, 4, 5]);
+        assert_eq!(padded, BinaryIndexedTree::from_freqs(expected));
+    }
+
+    fn grow_double_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let freqs = |pos: usize| (pos * pos % 7) as u32 + 1;
+        let mut table = T::from_freqs((0..len).map(freqs));
+        let total = table.total();
+        table.grow_double();
+        assert_eq!(table.len(), 2 * len);
+        assert_eq!(table.total(), total);
+        for pos in 0..2 * len {
+            let expected = if pos < len { freqs(pos) } else { 0 };
+            assert_eq!(table.freq(pos), expected);
+        }
+        assert_eq!(table.sum(2 * len - 1), total);
+        assert_eq!(table.find_by_sum(total), len - 1);
+        table.add(2 * len - 1, 3);
+        assert_eq!(table.total(), total + 3);
+    }
+
+    #[test]
+    fn truncate_extend_test() {
+        for len in 1..=32 {
+            truncate_extend_test_impl::<freq_array::FreqTable<u16>>(len);
+            truncate_extend_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            truncate_extend_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            truncate_extend_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn truncate_extend_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        for new_len in 1..=len {
+            let mut table = T::with_freq(len, 2);
+            table.truncate(new_len);
+            assert_eq!(table.len(), new_len);
+            assert_eq!(table.total(), new_len as u16 * 2);
+            table.extend_with(len, 3);
+            assert_eq!(table.len(), new_len + len);
+            assert_eq!(table.total(), new_len as u16 * 2 + len as u16 * 3);
+            for i in 0..table.len() {
+                assert_eq!(table.freq(i), if i < new_len { 2 } else { 3 });
+            }
+            let expected = table.total() - 3;
+            assert_eq!(table.sum(table.len() - 2), expected);
+        }
+        let mut table = T::with_freq(len, 1);
+        table.truncate(len + 1);
+        assert_eq!(table.len(), len);
+    }
+
+    #[test]
+    fn insert_remove_test() {
+        let mut table = Treap::<usize>::with_freq(1, 1000);
+        let mut expected = vec![1000];
+        // Deterministic pseudo-random positions.
+        let mut x = 1usize;
+        for i in 1..200 {
+            x = (x * 75 + 74) % 65537;
+            let pos = x % (expected.len() + 1);
+            table.insert_at(pos, i);
+            expected.insert(pos, i);
+            if i % 3 == 0 {
+                let pos = x % expected.len();
+                assert_eq!(table.remove_at(pos), expected.remove(pos));
+            }
+            assert_eq!(table.len(), expected.len());
+        }
+        let mut sum = 0;
+        for (pos, &freq) in expected.iter().enumerate() {
+            sum += freq;
+            assert_eq!(table.freq(pos), freq);
+            assert_eq!(table.sum(pos), sum);
+            assert_eq!(table.find_by_sum(sum), pos);
+        }
+        assert_eq!(table.total(), sum);
+        table.insert_at(expected.len() / 2, 0);
+        assert_eq!(table.count_nonzero(), expected.len());
+        table.sub(0, table.freq(0));
+        assert_eq!(table.count_nonzero(), expected.len() - 1);
+    }
+
+    #[test]
+    fn add_batch_test() {
+        for len in 1..=32 {
+            add_batch_test_impl::<freq_array::FreqTable<u32>>(len);
+            add_batch_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            add_batch_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            add_batch_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+    }
+
+    fn add_batch_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        // A small batch, then a batch larger than the table, with repeated positions.
+        for count in [2, 3 * len] {
+            let updates: Vec<(usize, u32)> = (0..count)
+                .map(|i| ((i * 7 + 3) % len, i as u32 % 4 + 1))
+                .collect();
+            let mut table = T::with_freq(len, 1);
+            let mut expected = T::with_freq(len, 1);
+            table.add_batch(&updates);
+            for &(pos, val) in &updates {
+                expected.add(pos, val);
+            }
+            assert_eq!(table.total(), expected.total());
+            for pos in 0..len {
+                assert_eq!(table.freq(pos), expected.freq(pos));
+                assert_eq!(table.sum(pos), expected.sum(pos));
+            }
+        }
+        let mut table = T::new(len);
+        table.add_batch(&[]);
+        assert_eq!(table.total(), 0);
+    }
+
+    #[test]
+    fn inc_all_test() {
+        for len in 1..=32 {
+            inc_all_test_impl::<freq_array::FreqTable<i16>>(len);
+            inc_all_test_impl::<cumulfreq_array::CumulFreqTable<i16>>(len);
+            inc_all_test_impl::<binary_indexed_tree::CumulFreqTable<i16>>(len);
+            inc_all_test_impl::<treap::CumulFreqTable<i16>>(len);
+        }
+    }
+
+    fn inc_all_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<i16> + Debug,
+    {
+        for count in [1, 2, 5 * len] {
+            let positions = (0..count).map(|i| (i * i + 5) % len);
+            // Negative frequencies become zero, the count of non-zero frequencies follows.
+            let mut table = T::with_freq(len, -1);
+            let mut expected = T::with_freq(len, -1);
+            table.inc_all(positions.clone());
+            for pos in positions {
+                expected.inc(pos);
+            }
+            assert_eq!(table.total(), expected.total());
+            assert_eq!(table.count_nonzero(), expected.count_nonzero());
+            for pos in 0..len {
+                assert_eq!(table.freq(pos), expected.freq(pos));
+                assert_eq!(table.sum(pos), expected.sum(pos));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "pos out of bounds")]
+    fn add_batch_out_of_bounds_test() {
+        BinaryIndexedTree::<u32>::new(4).add_batch(&[(1, 1), (4, 1), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn range_test() {
+        for len in 1..=32 {
+            range_test_impl::<freq_array::FreqTable<u16>>(len);
+            range_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            range_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            range_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn range_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        for first in 0..len {
+            for last in first..len {
+                let mut table = T::with_freq(len, 1);
+                table.add_range(first..=last, 3);
+                for i in 0..len {
+                    let expected = if (first..=last).contains(&i) { 4 } else { 1 };
+                    assert_eq!(table.freq(i), expected);
+                }
+                assert_eq!(table.total(), len as u16 + 3 * (last - first + 1) as u16);
+                table.sub_range(first..last + 1, 3);
+                for i in 0..len {
+                    assert_eq!(table.freq(i), 1);
+                    assert_eq!(table.sum(i), i as u16 + 1);
+                }
+            }
+        }
+        let mut table = T::new(len);
+        table.add_range(.., 2);
+        table.add_range(len..len, 2);
+        assert_eq!(table.total(), len as u16 * 2);
+    }
+
+    #[test]
+    fn sum_range_test() {
+        for len in 1..=32 {
+            sum_range_test_impl::<freq_array::FreqTable<u16>>(len);
+            sum_range_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            sum_range_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            sum_range_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn sum_range_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        for i in 0..len {
+            table.add(i, i as u16 + 1);
+        }
+        for first in 0..len {
+            for last in first..len {
+                let expected: u16 = (first as u16 + 1..=last as u16 + 1).sum();
+                assert_eq!(table.sum_range(first..=last), expected);
+                assert_eq!(table.sum_range(first..last + 1), expected);
+                assert_eq!(table.count_between(first..=last), expected);
+            }
+            assert_eq!(table.sum_range(first..first), 0);
+        }
+        assert_eq!(table.sum_range(..), table.total());
+    }
+
+    #[test]
+    fn sum_exclusive_test() {
+        for len in 1..=32 {
+            sum_exclusive_test_impl::<freq_array::FreqTable<u16>>(len);
+            sum_exclusive_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            sum_exclusive_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            sum_exclusive_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn sum_exclusive_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        for i in 0..len {
+            table.add(i, i as u16 % 3);
+        }
+        for i in 0..len {
+            assert_eq!(table.sum_exclusive(i), table.sum(i) - table.freq(i));
+            assert_eq!(table.rank(i), table.sum_exclusive(i));
+        }
+    }
+
+    #[test]
+    fn select_test() {
+        for len in 1..=32 {
+            select_test_impl::<freq_array::FreqTable<u16>>(len);
+            select_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            select_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            select_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn select_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        // A running median over observations, with removals.
+        let mut table = T::new(len);
+        let mut sorted = Vec::new();
+        for i in 0..len * 3 {
+            let pos = i * 7 % len;
+            table.inc(pos);
+            sorted.push(pos);
+            if i % 4 == 3 {
+                let pos = sorted.remove(i % sorted.len());
+                table.dec(pos);
+            }
+            sorted.sort_unstable();
+            for (k, &expected) in sorted.iter().enumerate() {
+                assert_eq!(table.select(k as u16), expected);
+                assert!(table.rank(expected) <= k as u16);
+            }
+            assert_eq!(
+                table.select(sorted.len() as u16 / 2),
+                sorted[sorted.len() / 2]
+            );
+        }
+    }
+
+    #[test]
+    fn multiset_test() {
+        multiset_test_impl::<FreqTable<u32>>();
+        multiset_test_impl::<cumulfreq_array::CumulFreqTable<u32>>();
+        multiset_test_impl::<BinaryIndexedTree<u32>>();
+        multiset_test_impl::<Treap<u32>>();
+    }
+
+    fn multiset_test_impl<T>()
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let mut set = CountingMultiset::<T, u32>::new(20);
+        let mut sorted = Vec::new();
+        assert!(set.is_empty());
+        assert_eq!(set.kth(0), None);
+        for i in 0..60 {
+            let x = i * 13 % 20;
+            if i % 3 == 2 {
+                let present = sorted.contains(&x);
+                assert_eq!(set.remove(x), present);
+                if let Some(idx) = sorted.iter().position(|&y| y == x) {
+                    sorted.remove(idx);
+                }
+            } else {
+                set.insert(x);
+                sorted.push(x);
+                sorted.sort_unstable();
+            }
+            assert_eq!(set.len(), sorted.len() as u32);
+            for x in 0..20 {
+                let count = sorted.iter().filter(|&&y| y == x).count() as u32;
+                assert_eq!(set.count(x), count);
+                assert_eq!(set.contains(x), count > 0);
+                assert_eq!(
+                    set.rank(x),
+                    sorted.iter().filter(|&&y| y < x).count() as u32
+                );
+            }
+            for (k, &x) in sorted.iter().enumerate() {
+                assert_eq!(set.kth(k as u32), Some(x));
+            }
+            assert_eq!(set.kth(sorted.len() as u32), None);
+        }
+    }
+
+    #[test]
+    fn stats_test() {
+        stats_test_impl::<FreqTable<u32>>();
+        stats_test_impl::<cumulfreq_array::CumulFreqTable<u32>>();
+        stats_test_impl::<BinaryIndexedTree<u32>>();
+        stats_test_impl::<Treap<u32>>();
+    }
+
+    fn stats_test_impl<T>()
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        let table = T::from_freqs([1, 0, 3, 4]);
+        assert_eq!(table.probabilities(), [0.125, 0.0, 0.375, 0.5]);
+        assert_eq!(table.cdf(), [0.125, 0.125, 0.5, 1.0]);
+        assert!(table.percentages().eq(table.cdf()));
+        let table = T::from_freqs([1; 10]);
+        let percentages: Vec<f64> = table.percentages().collect();
+        assert_eq!(percentages[2], 0.3);
+        assert_eq!(percentages[9], 1.0);
+        assert!(T::new(2).percentages().all(f64::is_nan));
+        let table = T::from_freqs([1, 0, 2, 0, 1]);
+        assert!(close(table.mean_pos(), 2.0));
+        // (4 + 0 + 4) / 4.
+        assert!(close(table.variance_pos(), 2.0));
+        assert!(close(table.stddev_pos(), 2.0_f64.sqrt()));
+        let table = T::from_freqs([0, 0, 3]);
+        assert!(close(table.mean_pos(), 2.0));
+        assert!(close(table.variance_pos(), 0.0));
+        assert!(T::new(3).mean_pos().is_nan());
+    }
+
+    #[test]
+    fn inverse_cdf_test() {
+        inverse_cdf_test_impl::<FreqTable<u32>>();
+        inverse_cdf_test_impl::<cumulfreq_array::CumulFreqTable<u32>>();
+        inverse_cdf_test_impl::<BinaryIndexedTree<u32>>();
+        inverse_cdf_test_impl::<Treap<u32>>();
+    }
+
+    fn inverse_cdf_test_impl<T>()
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let table = T::from_freqs([0, 2, 0, 4, 2, 0]);
+        let expected = [
+            (0.0, 1, 1.0),
+            (0.125, 1, 1.5),
+            (0.25, 3, 3.0),
+            (0.5, 3, 3.5),
+            (0.75, 4, 4.0),
+            (0.875, 4, 4.5),
+            (1.0, 5, 6.0),
+        ];
+        for (p, pos, interpolated) in expected {
+            assert_eq!(table.inverse_cdf(p), pos);
+            assert_eq!(table.inverse_cdf_interpolated(p), interpolated);
+        }
+        // The positions are drawn proportionally to their frequencies.
+        let mut counts = [0; 6];
+        for i in 0..800 {
+            counts[table.inverse_cdf(i as f64 / 800.0)] += 1;
+        }
+        assert_eq!(counts, [0, 200, 0, 400, 200, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "p must be within [0, 1]")]
+    fn inverse_cdf_out_of_range_test() {
+        FreqTable::<u32>::from_freqs([1, 2]).inverse_cdf(1.5);
+    }
+
+    #[test]
+    fn entropy_test() {
+        entropy_test_impl::<FreqTable<u32>, BinaryIndexedTree<u32>>();
+        entropy_test_impl::<cumulfreq_array::CumulFreqTable<u32>, Treap<u32>>();
+        entropy_test_impl::<BinaryIndexedTree<u32>, FreqTable<u32>>();
+        entropy_test_impl::<Treap<u32>, cumulfreq_array::CumulFreqTable<u32>>();
+    }
+
+    fn entropy_test_impl<A, B>()
+    where
+        A: CumulFreqTable<u32> + Debug,
+        B: CumulFreqTable<u32> + Debug,
+    {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        let p = A::from_freqs([2, 1, 1, 0]);
+        let q = B::from_freqs([1, 1, 1, 1]);
+        // The entropy of p is 1.5 bits.
+        assert!(close(p.cross_entropy(&A::from_freqs([2, 1, 1, 0])), 1.5));
+        assert!(close(p.kl_divergence(&A::from_freqs([4, 2, 2, 0])), 0.0));
+        assert!(close(p.cross_entropy(&q), 2.0));
+        assert!(close(p.kl_divergence(&q), 0.5));
+        // q has no mass where p has some.
+        let q = B::from_freqs([1, 1, 1, 0]);
+        let p = A::from_freqs([1, 1, 1, 1]);
+        assert_eq!(p.cross_entropy(&q), f64::INFINITY);
+        assert_eq!(p.kl_divergence(&q), f64::INFINITY);
+        assert!(p.cross_entropy(&B::new(4)).is_nan());
+    }
+
+    #[test]
+    fn chi_square_test() {
+        chi_square_test_impl::<FreqTable<u32>, BinaryIndexedTree<u32>>();
+        chi_square_test_impl::<cumulfreq_array::CumulFreqTable<u32>, Treap<u32>>();
+        chi_square_test_impl::<BinaryIndexedTree<u32>, FreqTable<u32>>();
+        chi_square_test_impl::<Treap<u32>, cumulfreq_array::CumulFreqTable<u32>>();
+    }
+
+    fn chi_square_test_impl<A, B>()
+    where
+        A: CumulFreqTable<u32> + Debug,
+        B: CumulFreqTable<u32> + Debug,
+    {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        let observed = A::from_freqs([10, 20, 30, 40]);
+        assert!(close(
+            observed.chi_square(&B::from_freqs([1, 2, 3, 4])),
+            0.0
+        ));
+        // Expected 25 everywhere: (225 + 25 + 25 + 225) / 25.
+        assert!(close(observed.chi_square(&B::with_freq(4, 7)), 20.0));
+        assert!(close(observed.chi_square_uniform(), 20.0));
+        let observed = A::from_freqs([5, 0, 5]);
+        assert!(close(observed.chi_square(&B::from_freqs([1, 0, 1])), 0.0));
+        assert_eq!(
+            observed.chi_square(&B::from_freqs([1, 1, 0])),
+            f64::INFINITY
+        );
+        // Nothing observed or nothing expected.
+        assert!(A::new(3).chi_square_uniform().is_nan());
+        assert_eq!(A::new(3).chi_square(&B::from_freqs([1, 2, 3])), 0.0);
+        assert!(observed.chi_square(&B::new(3)).is_nan());
+        assert!(A::new(3).chi_square(&B::new(3)).is_nan());
+    }
+
+    #[test]
+    fn gini_test() {
+        gini_test_impl::<FreqTable<u32>>();
+        gini_test_impl::<cumulfreq_array::CumulFreqTable<u32>>();
+        gini_test_impl::<BinaryIndexedTree<u32>>();
+        gini_test_impl::<Treap<u32>>();
+    }
+
+    fn gini_test_impl<T>()
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        assert!(close(T::with_freq(5, 3).gini(), 0.0));
+        assert!(close(T::from_freqs([0, 0, 8, 0]).gini(), 0.75));
+        assert!(close(T::from_freqs([9]).gini(), 0.0));
+        // Mean absolute difference 8 / 9 over twice the mean 2.
+        assert!(close(T::from_freqs([3, 1, 2]).gini(), 2.0 / 9.0));
+        assert!(T::new(3).gini().is_nan());
+    }
+
+    #[test]
+    fn max_min_test() {
+        for len in 1..=32 {
+            max_min_test_impl::<freq_array::FreqTable<u16>>(len);
+            max_min_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            max_min_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            max_min_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn max_min_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        assert_eq!(table.max_freq_pos(), (0, 0));
+        assert_eq!(table.min_freq_pos(), (0, 0));
+        for i in 0..len {
+            table.add(i, [3, 1, 4, 1, 5, 9, 2, 6][i % 8]);
+        }
+        let freqs: Vec<u16> = (0..len).map(|i| table.freq(i)).collect();
+        let max = *freqs.iter().max().unwrap();
+        let min = *freqs.iter().min().unwrap();
+        let first = |val| freqs.iter().position(|&f| f == val).unwrap();
+        assert_eq!(table.max_freq_pos(), (first(max), max));
+        assert_eq!(table.min_freq_pos(), (first(min), min));
+    }
+
+    #[test]
+    fn top_k_test() {
+        for len in 1..=32 {
+            top_k_test_impl::<freq_array::FreqTable<u16>>(len);
+            top_k_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            top_k_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            top_k_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn top_k_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        for i in 0..len {
+            table.add(i, [3, 1, 4, 1, 5, 9, 2, 6][i % 8]);
+        }
+        let mut expected: Vec<(usize, u16)> = (0..len).map(|i| (i, table.freq(i))).collect();
+        expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for k in 0..=len + 1 {
+            assert_eq!(table.top_k(k), expected[..k.min(len)]);
+        }
+    }
+
+    #[test]
+    fn huffman_test() {
+        for len in 1..=32 {
+            huffman_test_impl::<freq_array::FreqTable<u64>>(len);
+            huffman_test_impl::<cumulfreq_array::CumulFreqTable<u64>>(len);
+            huffman_test_impl::<binary_indexed_tree::CumulFreqTable<u64>>(len);
+            huffman_test_impl::<treap::CumulFreqTable<u64>>(len);
+        }
+        // Fibonacci frequencies give the longest codes.
+        let fib = [1.0, 1.0, 2.0, 3.0, 5.0, 8.0, 13.0];
+        assert_eq!(
+            FreqTable::<f64>::from_freqs(fib).huffman_lengths(),
+            [6, 6, 5, 4, 3, 2, 1]
+        );
+        assert_eq!(
+            huffman::canonical_codes(&[2, 1, 3, 0, 3]),
+            [0b10, 0b0, 0b110, 0, 0b111]
+        );
+    }
+
+    fn huffman_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u64> + Debug,
+    {
+        let mut table = T::new(len);
+        assert_eq!(table.huffman_lengths(), vec![0; len]);
+        table.add(len / 2, 7);
+        let mut expected = vec![0; len];
+        expected[len / 2] = 1;
+        assert_eq!(table.huffman_lengths(), expected);
+
+        for i in 0..len {
+            table.set(i, [3, 0, 4, 1, 5, 9, 2, 6][i % 8]);
+        }
+        let lengths = table.huffman_lengths();
+        let codes = huffman::canonical_codes(&lengths);
+        let coded: Vec<usize> = (0..len).filter(|&i| table.freq(i) > 0).collect();
+        for (i, &length) in lengths.iter().enumerate() {
+            assert_eq!(length == 0, table.freq(i) == 0);
+        }
+        if coded.len() > 1 {
+            // Kraft equality: a complete prefix code.
+            let kraft: f64 = coded.iter().map(|&i| 0.5f64.powi(lengths[i] as i32)).sum();
+            assert_eq!(kraft, 1.0);
+        }
+        // No code is a prefix of another.
+        for &a in &coded {
+            for &b in &coded {
+                if a != b && lengths[a] <= lengths[b] {
+                    assert_ne!(codes[b] >> (lengths[b] - lengths[a]), codes[a]);
+                }
+            }
+        }
+        // Optimal: never longer than the Shannon code, within 1 bit of the entropy per symbol.
+        let total = table.total() as f64;
+        let cost: f64 = coded
+            .iter()
+            .map(|&i| table.freq(i) as f64 * lengths[i] as f64)
+            .sum();
+        let entropy: f64 = coded
+            .iter()
+            .map(|&i| -(table.freq(i) as f64) * (table.freq(i) as f64 / total).log2())
+            .sum();
+        assert!(
+            cost >= entropy - 1e-9 && cost <= entropy + total,
+            "{cost} {entropy}"
+        );
+    }
+
+    #[test]
+    fn histogram_test() {
+        let expected = "0 |########  | 4  40.0%\n\
+                        1 |          | 0  40.0%\n\
+                        2 |##########| 5  90.0%\n\
+                        3 |##        | 1 100.0%\n";
+        histogram_test_impl::<freq_array::FreqTable<u8>>(expected);
+        histogram_test_impl::<cumulfreq_array::CumulFreqTable<u8>>(expected);
+        histogram_test_impl::<binary_indexed_tree::CumulFreqTable<u8>>(expected);
+        histogram_test_impl::<treap::CumulFreqTable<u8>>(expected);
+
+        let table = FreqTable::<i32>::from_freqs([-2, 0, 12]);
+        assert_eq!(
+            table.histogram(3),
+            "0 |   | -2 -20.0%\n\
+             1 |   |  0 -20.0%\n\
+             2 |###| 12 100.0%\n"
+        );
+        let table = FreqTable::<u8>::from_freqs([1; 11]);
+        assert!(table.histogram(1).starts_with(" 0 |#| 1   9.1%\n"));
+        assert!(table.histogram(1).ends_with("10 |#| 1 100.0%\n"));
+        assert_eq!(
+            FreqTable::<f64>::new(1).to_string(),
+            format!("0 |{:40}| 0   0.0%\n", "")
+        );
+    }
+
+    fn histogram_test_impl<T>(expected: &str)
+    where
+        T: CumulFreqTable<u8> + std::fmt::Display,
+    {
+        let table = T::from_freqs([4, 0, 5, 1]);
+        assert_eq!(table.histogram(10), expected);
+        assert_eq!(format!("{table:10}"), expected);
+    }
+
+    #[test]
+    fn count_nonzero_test() {
+        for len in 1..=32 {
+            count_nonzero_test_impl::<freq_array::FreqTable<i16>>(len);
+            count_nonzero_test_impl::<cumulfreq_array::CumulFreqTable<i16>>(len);
+            count_nonzero_test_impl::<binary_indexed_tree::CumulFreqTable<i16>>(len);
+            count_nonzero_test_impl::<treap::CumulFreqTable<i16>>(len);
+        }
+        let mut padded = BinaryIndexedTree::<i16>::from_freqs([1, 0, 2]).padded();
+        padded.push(0);
+        padded.push(3);
+        padded.pop();
+        assert_eq!(padded.count_nonzero(), 2);
+        let tail = padded.split_off(2);
+        assert_eq!((padded.count_nonzero(), tail.count_nonzero()), (1, 1));
+        assert_eq!(padded.concat(tail).count_nonzero(), 2);
+    }
+
+    fn count_nonzero_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<i16> + Debug,
+    {
+        let expected = |table: &T| (0..table.len()).filter(|&i| table.freq(i) != 0).count();
+        let mut table = T::new(len);
+        assert_eq!(table.count_nonzero(), 0);
+        for i in 0..len * 4 {
+            let pos = i * 5 % len;
+            match i % 5 {
+                0 | 1 => table.inc(pos),
+                2 => table.dec(pos),
+                3 => table.set(pos, 0),
+                _ => table.add_range(pos.., 1),
+            }
+            assert_eq!(table.count_nonzero(), expected(&table));
+        }
+        table.push(0);
+        table.push(3);
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.pop();
+        table.truncate(len.div_ceil(2));
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.scale(|f| f / 2);
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.merge_add(&T::with_freq(table.len(), 1));
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.sub_range(..1, 1);
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.add_batch(&[(0, 2), (table.len() - 1, -1)]);
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.rotate_left(1);
+        table.reverse();
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.resize(table.len() + 2);
+        table.resize(table.len() - 1);
+        assert_eq!(table.count_nonzero(), expected(&table));
+        table.set(0, 0);
+        table.swap(0, table.len() - 1);
+        assert_eq!(table.count_nonzero(), expected(&table));
+        if table.len() > 1 {
+            let tail = table.split_off(table.len() / 2);
+            assert_eq!(tail.count_nonzero(), expected(&tail));
+            assert_eq!(table.count_nonzero(), expected(&table));
+            table = table.concat(tail);
+            assert_eq!(table.count_nonzero(), expected(&table));
+        }
+        table.clear();
+        assert_eq!(table.count_nonzero(), 0);
+        table.fill(0);
+        assert_eq!(table.count_nonzero(), 0);
+        table.fill(2);
+        assert_eq!(table.count_nonzero(), table.len());
+        assert_eq!(T::from_freqs([0, 1, -1, 0]).count_nonzero(), 2);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn sample_test() {
+        sample_test_impl::<FreqTable<u32>>();
+        sample_test_impl::<cumulfreq_array::CumulFreqTable<u32>>();
+        sample_test_impl::<BinaryIndexedTree<u32>>();
+        sample_test_impl::<Treap<u32>>();
+        let table = FreqTable::<f64>::from_freqs([0.0, 0.5, 0.0, 0.25]);
+        let mut rng = rand::rngs::mock::StepRng::new(0, 1 << 60);
+        for _ in 0..100 {
+            let pos = rand::distributions::Distribution::sample(&table, &mut rng);
+            assert!(pos == 1 || pos == 3);
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn sample_and_inc_test() {
+        let expected = sample_and_inc_test_impl::<FreqTable<u32>>();
+        assert!(expected.iter().all(|&pos| [0, 2, 5].contains(&pos)));
+        assert_eq!(
+            sample_and_inc_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(),
+            expected
+        );
+        assert_eq!(
+            sample_and_inc_test_impl::<BinaryIndexedTree<u32>>(),
+            expected
+        );
+        assert_eq!(sample_and_inc_test_impl::<Treap<u32>>(), expected);
+    }
+
+    // The positions drawn from a seeded rng, which must be the same for every implementation.
+    #[cfg(feature = "rand")]
+    fn sample_and_inc_test_impl<T>() -> Vec<usize>
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        use rand::SeedableRng;
+        let mut table = T::from_freqs([1, 0, 2, 0, 0, 1, 0]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let draws: Vec<usize> = (0..500).map(|_| table.sample_and_inc(&mut rng)).collect();
+        assert_eq!(table.total(), 504);
+        assert_eq!(table.count_nonzero(), 3);
+        for pos in 0..table.len() {
+            let drawn = draws.iter().filter(|&&p| p == pos).count() as u32;
+            assert_eq!(table.freq(pos), [1, 0, 2, 0, 0, 1, 0][pos] + drawn);
+        }
+        draws
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn sample_and_dec_test() {
+        let expected = sample_and_dec_test_impl::<FreqTable<u32>>();
+        assert_eq!(
+            sample_and_dec_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(),
+            expected
+        );
+        assert_eq!(
+            sample_and_dec_test_impl::<BinaryIndexedTree<u32>>(),
+            expected
+        );
+        assert_eq!(sample_and_dec_test_impl::<Treap<u32>>(), expected);
+    }
+
+    #[cfg(feature = "rand")]
+    fn sample_and_dec_test_impl<T>() -> Vec<usize>
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        use rand::SeedableRng;
+        let freqs = [3, 0, 1, 4, 0, 2];
+        let mut table = T::from_freqs(freqs);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let first = table.sample_and_dec(&mut rng);
+        assert_eq!(table.freq(first), freqs[first] - 1);
+        let mut draws = vec![first];
+        draws.extend(table.drain_sampled(&mut rng));
+        assert_eq!(table.total(), 0);
+        assert_eq!(draws.len(), 10);
+        for (pos, &freq) in freqs.iter().enumerate() {
+            assert_eq!(draws.iter().filter(|&&p| p == pos).count() as u32, freq);
+        }
+        assert_eq!(table.drain_sampled(&mut rng).next(), None);
+        draws
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn freeze_sampler_test() {
+        freeze_sampler_test_impl::<FreqTable<u32>>();
+        freeze_sampler_test_impl::<cumulfreq_array::CumulFreqTable<u32>>();
+        freeze_sampler_test_impl::<BinaryIndexedTree<u32>>();
+        freeze_sampler_test_impl::<Treap<u32>>();
+        // A single position is always drawn.
+        let sampler = AliasSampler::from_freqs([0.25f64]);
+        let mut rng = rand::rngs::mock::StepRng::new(0, 1 << 60);
+        assert!((0..10).all(|_| rand::distributions::Distribution::sample(&sampler, &mut rng) == 0));
+    }
+
+    #[cfg(feature = "rand")]
+    fn freeze_sampler_test_impl<T>()
+    where
+        T: CumulFreqTable<u32> + Debug + PartialEq,
+    {
+        use rand::distributions::Distribution;
+        use rand::SeedableRng;
+        let freqs = [5, 0, 1, 10, 0, 4, 0];
+        let table = T::from_freqs(freqs);
+        let sampler = table.freeze_sampler();
+        assert_eq!(sampler.len(), freqs.len());
+        assert_eq!(sampler.freqs(), freqs);
+        assert_eq!(sampler.to_table::<T>(), table);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut counts = [0u32; 7];
+        for _ in 0..20_000 {
+            counts[sampler.sample(&mut rng)] += 1;
+        }
+        for (pos, &freq) in freqs.iter().enumerate() {
+            // 1000 draws per unit of frequency.
+            assert!(
+                counts[pos].abs_diff(freq * 1000) <= 300,
+                "{pos}: {counts:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    fn sample_test_impl<T>()
+    where
+        T: CumulFreqTable<u32> + rand::distributions::Distribution<usize> + Debug,
+    {
+        use rand::SeedableRng;
+        let table = T::from_freqs([0, 1, 0, 0, 2, 0, 1, 0]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let mut counts = [0; 8];
+        for pos in table.sample_iter(&mut rng).take(4000) {
+            counts[pos] += 1;
+        }
+        assert_eq!(counts.iter().sum::<u32>(), 4000);
+        assert_eq!(counts[0] + counts[2] + counts[3] + counts[5] + counts[7], 0);
+        assert!((900..1100).contains(&counts[1]));
+        assert!((1900..2100).contains(&counts[4]));
+        assert!((900..1100).contains(&counts[6]));
+    }
+
+    #[test]
+    fn bounds_test() {
+        for len in 1..=32 {
+            bounds_test_impl::<freq_array::FreqTable<u16>>(len);
+            bounds_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            bounds_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            bounds_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn bounds_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
         for i in 0..len {
-            assert_eq!(table.freq(i), 1.into());
+            table.add(i, i as u16 % 4);
+        }
+        for i in 0..len {
+            assert_eq!(table.bounds(i), (table.sum_exclusive(i), table.sum(i)));
+        }
+    }
+
+    #[test]
+    fn find_test() {
+        for len in 1..=32 {
+            find_test_impl::<freq_array::FreqTable<u16>>(len);
+            find_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            find_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            find_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn find_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        for i in 0..len {
+            // Some zero frequencies, and some runs of zeros.
+            table.add(i, [2, 0, 3, 0, 0, 1][i % 6]);
+        }
+        for sum in 0..=table.total() + 2 {
+            let expected = (0..len).find(|&i| table.sum(i) >= sum).unwrap_or(len - 1);
+            assert_eq!(table.find_by_sum(sum), expected, "sum {}", sum);
+            let (low, high) = table.bounds(expected);
+            assert_eq!(table.find_with_bounds(sum), (expected, low, high));
+            assert_eq!(
+                table.find_by_sum_rem(sum),
+                (expected, sum.wrapping_sub(low))
+            );
+            let expected = (0..len).find(|&i| table.sum(i) > sum).unwrap_or(len - 1);
+            assert_eq!(table.find_by_sum_strict(sum), expected, "sum {}", sum);
+        }
+    }
+
+    #[test]
+    fn find_nonzero_test() {
+        for len in 1..=32 {
+            find_nonzero_test_impl::<freq_array::FreqTable<u16>>(len);
+            find_nonzero_test_impl::<cumulfreq_array::CumulFreqTable<u16>>(len);
+            find_nonzero_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>(len);
+            find_nonzero_test_impl::<treap::CumulFreqTable<u16>>(len);
+        }
+    }
+
+    fn find_nonzero_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = T::new(len);
+        // Zeros at both ends and in runs.
+        table.add(len / 2, 1);
+        for i in 1..len.saturating_sub(1) {
+            table.add(i, [0, 2, 0, 0, 1][i % 5]);
+        }
+        let nonzero: Vec<usize> = (0..len).filter(|&i| table.freq(i) > 0).collect();
+        for sum in 0..=table.total() + 2 {
+            let pos = table.find_by_sum_nonzero(sum);
+            let expected = nonzero
+                .iter()
+                .copied()
+                .find(|&i| table.sum(i) >= sum)
+                .unwrap_or(*nonzero.last().unwrap());
+            assert_eq!(pos, expected, "sum {}", sum);
+        }
+    }
+
+    #[test]
+    fn find_strict_test() {
+        find_strict_test_impl::<freq_array::FreqTable<f64>>();
+        find_strict_test_impl::<cumulfreq_array::CumulFreqTable<f64>>();
+        find_strict_test_impl::<binary_indexed_tree::CumulFreqTable<f64>>();
+        find_strict_test_impl::<treap::CumulFreqTable<f64>>();
+    }
+
+    fn find_strict_test_impl<T>()
+    where
+        T: CumulFreqTable<f64> + Debug,
+    {
+        let table = T::from_freqs([0.5, 0.0, 0.25, 0.25]);
+        assert_eq!(table.find_by_sum_strict(0.0), 0);
+        assert_eq!(table.find_by_sum_strict(0.5), 2);
+        assert_eq!(table.find_by_sum_strict(0.6), 2);
+        assert_eq!(table.find_by_sum_strict(0.75), 3);
+        assert_eq!(table.find_by_sum_strict(1.0), 3);
+        assert_eq!(table.find_by_sum(0.5), 0);
+    }
+
+    #[test]
+    fn quantize_test() {
+        let check = |freqs: &[u32], bits: u32| {
+            let mut table = FreqTable::<u32>::new(freqs.len());
+            for (pos, &freq) in freqs.iter().enumerate() {
+                table.add(pos, freq);
+            }
+            let quantized = table.quantize_to(bits);
+            assert_eq!(quantized.iter().sum::<u32>(), 1 << bits);
+            for (&freq, &q) in freqs.iter().zip(quantized.iter()) {
+                assert_eq!(freq == 0, q == 0);
+            }
+            quantized
+        };
+        assert_eq!(check(&[1, 1, 2], 2), vec![1, 1, 2]);
+        assert_eq!(check(&[1, 1, 2], 4), vec![4, 4, 8]);
+        assert_eq!(check(&[1, 0, 1, 1], 4), vec![6, 0, 5, 5]);
+        assert_eq!(check(&[1000, 1, 1, 0], 3), vec![6, 1, 1, 0]);
+        assert_eq!(check(&[7], 10), vec![1024]);
+        check(&[3, 5, 7, 11, 13, 17, 0, 19], 12);
+        check(&(1..=255).collect::<Vec<_>>(), 8);
+    }
+
+    #[test]
+    fn ac_test() {
+        // Skewed deterministic pseudo-random symbols.
+        let mut x = 7u32;
+        let message: Vec<usize> = (0..5000)
+            .map(|_| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let r = (x >> 16) % 100;
+                if r < 70 {
+                    0
+                } else {
+                    (r % 31) as usize
+                }
+            })
+            .collect();
+        ac_test_impl::<freq_array::FreqTable<u32>>(&message);
+        ac_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(&message);
+        ac_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(&message);
+        ac_test_impl::<treap::CumulFreqTable<u32>>(&message);
+        ac_test_impl::<FreqTable<u32>>(&[]);
+        ac_test_impl::<FreqTable<u32>>(&[5]);
+    }
+
+    fn ac_test_impl<T>(message: &[usize])
+    where
+        T: CumulFreqTable<u32> + Debug + PartialEq,
+    {
+        let mut encoder = ac::Encoder::new(T::with_freq(32, 1), Vec::new());
+        for &symbol in message {
+            encoder.encode(symbol).unwrap();
+        }
+        let (model, bytes) = encoder.finish().unwrap();
+        // Adaptive compression beats 5 bits per symbol.
+        assert!(bytes.len() * 8 <= message.len() * 5 + 16);
+
+        let mut decoder = ac::Decoder::new(T::with_freq(32, 1), bytes.as_slice()).unwrap();
+        for &symbol in message {
+            assert_eq!(decoder.decode().unwrap(), symbol);
+        }
+        assert_eq!(decoder.model(), &model);
+    }
+
+    #[cfg(feature = "rans")]
+    #[test]
+    fn rans_test() {
+        let mut table = BinaryIndexedTree::<usize>::new(6);
+        for (pos, freq) in [(0, 100), (1, 1), (3, 30), (5, 7)] {
+            table.add(pos, freq);
+        }
+        let model = rans::Model::new(&table, 8);
+        assert_eq!(model.len(), 6);
+        assert_eq!(model.freq(2), 0);
+        assert!(model.freq(1) >= 1);
+        let total: u32 = (0..6).map(|pos| model.freq(pos)).sum();
+        assert_eq!(total, 256);
+        for pos in 0..6 {
+            let (start, freq) = model.get(pos);
+            assert_eq!(start, model.start(pos));
+            for slot in start..start + freq {
+                assert_eq!(model.find_by_sum(slot), pos);
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_test() {
+        let mut model = AdaptiveModel::new(BinaryIndexedTree::<u32>::new(4), 16);
+        for _ in 0..12 {
+            model.observe(0);
+        }
+        for _ in 0..4 {
+            model.observe(3);
+        }
+        assert_eq!(model.table().total(), 16);
+        // Crossing the ceiling halves rounding up.
+        model.observe(1);
+        assert_eq!(model.table().freq(0), 6);
+        assert_eq!(model.table().freq(1), 1);
+        assert_eq!(model.table().freq(2), 0);
+        assert_eq!(model.table().freq(3), 2);
+        assert_eq!(model.into_inner().total(), 9);
+
+        let table = BinaryIndexedTree::<u32>::from_freqs([5, 1, 0, 4]);
+        let mut model =
+            AdaptiveModel::new(table, 0).on_total_exceeds(12, adaptive::Rescale::HalveDown);
+        assert_eq!(model.rescale_strategy(), adaptive::Rescale::HalveDown);
+        model.add(2, 2);
+        assert_eq!(model.table().total(), 12);
+        // Rescaled before the add: [2, 0, 1, 2] + 3.
+        model.add(2, 3);
+        assert_eq!(model.table().freq(1), 0);
+        assert_eq!(model.table().freq(2), 4);
+        assert_eq!(model.table().total(), 8);
+        // Rescaling cannot make room.
+        model.add(0, 20);
+        assert_eq!(model.table().total(), 20);
+    }
+
+    #[test]
+    fn escape_test() {
+        use escape::{EscapeMethod, Interval, Prob};
+        let interval = |low, high, total| Interval { low, high, total };
+        for method in [
+            EscapeMethod::A,
+            EscapeMethod::B,
+            EscapeMethod::C,
+            EscapeMethod::D,
+        ] {
+            let model = EscapeModel::<BinaryIndexedTree<u32>, u32>::new(4, method);
+            assert_eq!(model.encode_prob(2), Prob::Escape(interval(0, 1, 1)));
+        }
+
+        // Counts: [3, 0, 1, 2], n = 6, q = 3.
+        let mut counts = BinaryIndexedTree::<u32>::new(4);
+        counts.add(0, 3);
+        counts.add(2, 1);
+        counts.add(3, 2);
+        let model = |method| EscapeModel::from_counts(counts.clone(), method);
+
+        let a = model(EscapeMethod::A);
+        assert_eq!(a.distinct(), 3);
+        assert_eq!(a.encode_prob(0), Prob::Symbol(interval(0, 3, 7)));
+        assert_eq!(a.encode_prob(1), Prob::Escape(interval(6, 7, 7)));
+        assert_eq!(a.encode_prob(3), Prob::Symbol(interval(4, 6, 7)));
+
+        let b = model(EscapeMethod::B);
+        assert_eq!(b.encode_prob(0), Prob::Symbol(interval(0, 2, 6)));
+        assert_eq!(b.encode_prob(2), Prob::Escape(interval(3, 6, 6)));
+        assert_eq!(b.encode_prob(3), Prob::Symbol(interval(2, 3, 6)));
+
+        let c = model(EscapeMethod::C);
+        assert_eq!(c.encode_prob(2), Prob::Symbol(interval(3, 4, 9)));
+        assert_eq!(c.encode_prob(1), Prob::Escape(interval(6, 9, 9)));
+
+        let mut d = model(EscapeMethod::D);
+        assert_eq!(d.encode_prob(0), Prob::Symbol(interval(0, 5, 12)));
+        assert_eq!(d.encode_prob(2), Prob::Symbol(interval(5, 6, 12)));
+        assert_eq!(d.encode_prob(3), Prob::Symbol(interval(6, 9, 12)));
+        assert_eq!(d.encode_prob(1), Prob::Escape(interval(9, 12, 12)));
+
+        d.observe(1);
+        assert_eq!(d.distinct(), 4);
+        assert_eq!(d.encode_prob(1), Prob::Symbol(interval(5, 6, 14)));
+    }
+
+    #[test]
+    fn window_test() {
+        let mut table = SlidingWindowTable::<FreqTable<u32>, u32>::new(4, 3);
+        for pos in [0, 1, 1, 3, 3, 3] {
+            table.observe(pos);
+        }
+        assert_eq!(table.table().total(), 3);
+        assert_eq!(table.table().freq(1), 0);
+        assert_eq!(table.table().freq(3), 3);
+        table.observe(2);
+        assert_eq!(table.observations().collect::<Vec<_>>(), vec![3, 3, 2]);
+        assert_eq!(table.table().freq(3), 2);
+        assert_eq!(table.into_inner().sum(2), 1);
+    }
+
+    #[test]
+    fn decay_test() {
+        let mut table = DecayingTable::<BinaryIndexedTree<u32>, u32>::new(3, 4, 1);
+        for pos in [0, 0, 0, 2] {
+            table.observe(pos);
+        }
+        assert_eq!(table.table().freq(0), 1);
+        assert_eq!(table.table().freq(2), 0);
+        for pos in [2, 2, 2, 2] {
+            table.observe(pos);
+        }
+        assert_eq!(table.table().freq(0), 0);
+        assert_eq!(table.table().freq(2), 2);
+        table.observe(1);
+        assert_eq!(table.into_inner().total(), 3);
+    }
+
+    #[test]
+    fn ema_test() {
+        ema_test_impl::<freq_array::FreqTable<f64>>();
+        ema_test_impl::<cumulfreq_array::CumulFreqTable<f64>>();
+        ema_test_impl::<binary_indexed_tree::CumulFreqTable<f64>>();
+        ema_test_impl::<treap::CumulFreqTable<f64>>();
+    }
+
+    fn ema_test_impl<T>()
+    where
+        T: CumulFreqTable<f64> + Debug,
+    {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        let mut table = EmaTable::<T>::new(3);
+        table.observe_ema(0, 0.5);
+        table.observe_ema(2, 0.5);
+        assert!(close(table.freq(0), 0.25));
+        assert!(close(table.freq(2), 0.5));
+        assert!(close(table.sum(1), 0.25));
+        assert_eq!(table.find_by_sum(0.5), 2);
+        // Long enough to renormalize many times.
+        for i in 0..10_000 {
+            table.observe_ema(i % 3, 0.1);
+        }
+        assert!(close(table.total(), 1.0));
+        let sum = table.sum(1);
+        let raw = table.into_inner();
+        assert!(close(raw.sum(1), sum));
+
+        let mut table = EmaTable::<T>::new(2);
+        table.observe_ema(0, 0.5);
+        table.observe_ema(1, 1.0);
+        assert_eq!(table.freq(0), 0.0);
+        assert_eq!(table.freq(1), 1.0);
+    }
+
+    #[test]
+    fn smoothed_test() {
+        for len in 1..=32 {
+            smoothed_test_impl::<freq_array::FreqTable>(len);
+            smoothed_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            smoothed_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            smoothed_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn smoothed_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + Clone + PartialEq,
+    {
+        let mut table = T::new(len);
+        for pos in (0..len).step_by(3) {
+            table.add(pos, pos);
+        }
+        let raw = table.clone();
+        let view = SmoothedView::new(&table, 2);
+        let mut sum = 0;
+        for pos in 0..len {
+            assert_eq!(view.freq(pos), table.freq(pos) + 2);
+            sum += view.freq(pos);
+            assert_eq!(view.sum(pos), sum);
+        }
+        assert_eq!(view.total(), sum);
+        for target in 0..=sum + 1 {
+            let pos = view.find_by_sum(target);
+            assert!(view.sum(pos) >= target || pos == len - 1);
+            assert!(pos == 0 || view.sum(pos - 1) < target);
+        }
+        assert_eq!(table, raw);
+    }
+
+    #[test]
+    fn view_test() {
+        for len in 1..=16 {
+            view_test_impl::<freq_array::FreqTable>(len);
+            view_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            view_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            view_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn view_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug,
+    {
+        let table = T::from_freqs((0..len).map(|pos| pos % 3));
+        for first in 0..len {
+            for last in first..len {
+                let view = table.view(first..=last);
+                assert_eq!(view.len(), last - first + 1);
+                assert_eq!(view.first(), first);
+                let mut sum = 0;
+                for (pos, freq) in view.iter() {
+                    assert_eq!(freq, table.freq(first + pos));
+                    assert_eq!(view.freq(pos), freq);
+                    sum += freq;
+                    assert_eq!(view.sum(pos), sum);
+                }
+                assert_eq!(view.total(), sum);
+                for target in 0..=sum + 1 {
+                    let pos = view.find_by_sum(target);
+                    assert!(pos < view.len());
+                    assert!(view.sum(pos) >= target || pos == view.len() - 1);
+                    assert!(pos == 0 || view.sum(pos - 1) < target);
+                }
+            }
+        }
+        assert_eq!(table.view(..).total(), table.total());
+    }
+
+    #[test]
+    fn view_mut_test() {
+        for len in 1..=16 {
+            view_mut_test_impl::<freq_array::FreqTable>(len);
+            view_mut_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            view_mut_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            view_mut_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn view_mut_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug,
+    {
+        for first in 0..len {
+            for last in first..len {
+                let mut table = T::with_freq(len, 1);
+                let mut view = table.view_mut(first..=last);
+                for pos in 0..view.len() {
+                    view.add(pos, pos + 2);
+                }
+                view.dec(0);
+                view.inc(view.len() - 1);
+                let view_len = view.len();
+                let view_total = view.total();
+                assert_eq!(view.sum(view_len - 1), view_total);
+                assert_eq!(view.find_by_sum(view_total), view_len - 1);
+                assert_eq!(view.find_by_sum(0), 0);
+                let mut expected = vec![1; len];
+                for (pos, freq) in expected[first..=last].iter_mut().enumerate() {
+                    *freq += pos + 2;
+                }
+                expected[first] -= 1;
+                expected[last] += 1;
+                assert_eq!(table.to_freq_vec(), expected);
+                assert_eq!(table.total(), len - view_len + view_total);
+                assert_eq!(table.total(), table.sum(len - 1));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "pos out of bounds")]
+    fn view_mut_out_of_bounds_test() {
+        let mut table = FreqTable::<u32>::new(4);
+        table.view_mut(1..3).inc(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "range must be non-empty")]
+    fn view_empty_test() {
+        FreqTable::<u32>::new(3).view(2..2);
+    }
+
+    #[test]
+    fn mixed_test() {
+        for len in 1..=32 {
+            mixed_test_impl::<freq_array::FreqTable, binary_indexed_tree::CumulFreqTable>(len);
+            mixed_test_impl::<cumulfreq_array::CumulFreqTable, treap::CumulFreqTable>(len);
+            mixed_test_impl::<binary_indexed_tree::CumulFreqTable, freq_array::FreqTable>(len);
+        }
+    }
+
+    fn mixed_test_impl<A, B>(len: usize)
+    where
+        A: CumulFreqTable + Debug,
+        B: CumulFreqTable + Debug,
+    {
+        let mut a = A::new(len);
+        let mut b = B::new(len);
+        for pos in 0..len {
+            a.add(pos, pos % 3);
+            b.add(pos, pos % 2);
+        }
+        let view = MixedView::new(&a, 3, &b, 2);
+        let mut sum = 0;
+        for pos in 0..len {
+            assert_eq!(view.freq(pos), 3 * (pos % 3) + 2 * (pos % 2));
+            sum += view.freq(pos);
+            assert_eq!(view.sum(pos), sum);
+        }
+        assert_eq!(view.total(), sum);
+        for target in 0..=sum + 1 {
+            let pos = view.find_by_sum(target);
+            assert!(view.sum(pos) >= target || pos == len - 1);
+            assert!(pos == 0 || view.sum(pos - 1) < target);
+        }
+    }
+
+    #[test]
+    fn merge_test() {
+        for len in 1..=32 {
+            merge_test_impl::<freq_array::FreqTable, binary_indexed_tree::CumulFreqTable>(len);
+            merge_test_impl::<cumulfreq_array::CumulFreqTable, treap::CumulFreqTable>(len);
+            merge_test_impl::<binary_indexed_tree::CumulFreqTable, freq_array::FreqTable>(len);
+            merge_test_impl::<treap::CumulFreqTable, cumulfreq_array::CumulFreqTable>(len);
+        }
+    }
+
+    fn merge_test_impl<A, B>(len: usize)
+    where
+        A: CumulFreqTable + Debug + Clone + PartialEq,
+        B: CumulFreqTable + Debug,
+    {
+        let mut a = A::new(len);
+        let mut b = B::new(len);
+        for pos in 0..len {
+            a.add(pos, pos % 3);
+            b.add(pos, pos % 5 + 1);
+        }
+        let orig = a.clone();
+        a.merge_add(&b);
+        let mut sum = 0;
+        for pos in 0..len {
+            assert_eq!(a.freq(pos), pos % 3 + pos % 5 + 1);
+            sum += a.freq(pos);
+            assert_eq!(a.sum(pos), sum);
+        }
+        assert_eq!(a.total(), sum);
+        a.merge_sub(&b);
+        assert_eq!(a, orig);
+    }
+
+    #[test]
+    fn blend_test() {
+        for len in 1..=32 {
+            blend_test_impl::<freq_array::FreqTable, binary_indexed_tree::CumulFreqTable>(len);
+            blend_test_impl::<cumulfreq_array::CumulFreqTable, treap::CumulFreqTable>(len);
+            blend_test_impl::<binary_indexed_tree::CumulFreqTable, freq_array::FreqTable>(len);
+            blend_test_impl::<treap::CumulFreqTable, cumulfreq_array::CumulFreqTable>(len);
+        }
+        let prior = FreqTable::<f64>::from_freqs([10.0; 3]);
+        let learned = FreqTable::<f64>::from_freqs([0.0, 20.0, 40.0]);
+        let blended = Treap::blend(&prior, &learned, 0.25, 1.0);
+        assert_eq!(blended.to_freq_vec(), [2.5, 17.5, 32.5]);
+    }
+
+    fn blend_test_impl<A, B>(len: usize)
+    where
+        A: CumulFreqTable + Debug,
+        B: CumulFreqTable + Debug,
+    {
+        let a = A::from_freqs((0..len).map(|pos| pos % 3 * 10));
+        let b = B::from_freqs((0..len).map(|pos| pos % 5 * 10));
+        for w in 0..=4 {
+            let blended = A::blend(&a, &b, w, 4);
+            assert_eq!(blended.len(), len);
+            for pos in 0..len {
+                assert_eq!(
+                    blended.freq(pos),
+                    (pos % 3 * 10 * w + pos % 5 * 10 * (4 - w)) / 4
+                );
+            }
+            assert_eq!(blended.total(), blended.sum(len - 1));
+        }
+        assert_eq!(B::blend(&a, &b, 1, 1).to_freq_vec(), a.to_freq_vec());
+        assert_eq!(B::blend(&a, &b, 0, 1).to_freq_vec(), b.to_freq_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "w_num must be <= w_den")]
+    fn blend_weight_test() {
+        let table = FreqTable::<u32>::new(2);
+        FreqTable::blend(&table, &table, 3, 2);
+    }
+
+    #[test]
+    fn combine_test() {
+        for len in 1..=32 {
+            combine_test_impl::<freq_array::FreqTable, binary_indexed_tree::CumulFreqTable>(len);
+            combine_test_impl::<cumulfreq_array::CumulFreqTable, treap::CumulFreqTable>(len);
+            combine_test_impl::<binary_indexed_tree::CumulFreqTable, freq_array::FreqTable>(len);
+            combine_test_impl::<treap::CumulFreqTable, cumulfreq_array::CumulFreqTable>(len);
+        }
+    }
+
+    fn combine_test_impl<A, B>(len: usize)
+    where
+        A: CumulFreqTable + Debug + Clone,
+        B: CumulFreqTable + Debug,
+    {
+        let mut a = A::new(len);
+        let mut b = B::new(len);
+        for pos in 0..len {
+            a.add(pos, pos % 3);
+            b.add(pos, pos % 5);
+        }
+        let check = |table: &A, expected: &dyn Fn(usize) -> usize| {
+            let mut sum = 0;
+            for pos in 0..len {
+                assert_eq!(table.freq(pos), expected(pos));
+                sum += expected(pos);
+                assert_eq!(table.sum(pos), sum);
+            }
+            assert_eq!(table.total(), sum);
+        };
+        let mut max = a.clone();
+        max.combine_with(&b, |a, b| a.max(b));
+        check(&max, &|pos| (pos % 3).max(pos % 5));
+        let mut min = a.clone();
+        min.combine_with(&b, |a, b| a.min(b));
+        check(&min, &|pos| (pos % 3).min(pos % 5));
+        a.combine_with(&b, |a, b| a * 3 + b);
+        check(&a, &|pos| pos % 3 * 3 + pos % 5);
+    }
+
+    #[test]
+    fn diff_test() {
+        for len in 1..=32 {
+            diff_test_impl::<freq_array::FreqTable, binary_indexed_tree::CumulFreqTable>(len);
+            diff_test_impl::<cumulfreq_array::CumulFreqTable, treap::CumulFreqTable>(len);
+            diff_test_impl::<binary_indexed_tree::CumulFreqTable, freq_array::FreqTable>(len);
+        }
+    }
+
+    fn diff_test_impl<A, B>(len: usize)
+    where
+        A: CumulFreqTable + Debug,
+        B: CumulFreqTable + Debug,
+    {
+        let mut a = A::new(len);
+        let mut b = B::new(len);
+        assert_eq!(a.max_abs_diff(&b), 0);
+        assert_eq!(a.disagreements(&b, 0).count(), 0);
+        for pos in 0..len {
+            a.add(pos, pos % 3);
+            b.add(pos, pos % 5);
+        }
+        let expected = (0..len).map(|pos| (pos % 3).abs_diff(pos % 5));
+        assert_eq!(a.max_abs_diff(&b), expected.clone().max().unwrap());
+        assert_eq!(b.max_abs_diff(&a), a.max_abs_diff(&b));
+        for threshold in 0..4 {
+            let positions: Vec<usize> = a.disagreements(&b, threshold).collect();
+            let expected: Vec<usize> = expected
+                .clone()
+                .enumerate()
+                .filter(|&(_, diff)| diff > threshold)
+                .map(|(pos, _)| pos)
+                .collect();
+            assert_eq!(positions, expected);
+        }
+    }
+
+    #[test]
+    fn convert_test() {
+        for len in 1..=32 {
+            let mut table = FreqTable::new(len);
+            for pos in 0..len {
+                table.add(pos, pos % 7);
+            }
+            let bit = BinaryIndexedTree::from(&table);
+            let treap = Treap::from(&bit);
+            let cumul = cumulfreq_array::CumulFreqTable::from(&treap);
+            let back = FreqTable::from(&cumul);
+            assert_eq!(back, table);
+            assert_eq!(FreqTable::from(&bit), table);
+            assert_eq!(FreqTable::from(&treap), table);
+            assert_eq!(BinaryIndexedTree::from(&cumul), bit);
+            assert_eq!(BinaryIndexedTree::from(&treap), bit);
+            assert_eq!(Treap::from(&table), treap);
+            assert_eq!(Treap::from(&cumul), treap);
+            assert_eq!(cumulfreq_array::CumulFreqTable::from(&table), cumul);
+            assert_eq!(cumulfreq_array::CumulFreqTable::from(&bit), cumul);
+            for pos in 0..len {
+                assert_eq!(bit.sum(pos), table.sum(pos));
+                assert_eq!(treap.sum(pos), table.sum(pos));
+                assert_eq!(cumul.sum(pos), table.sum(pos));
+            }
+        }
+    }
+
+    #[test]
+    fn cross_eq_test() {
+        for len in 1..=32 {
+            let freqs: Vec<u32> = (0..len as u32).map(|i| i % 7).collect();
+            let table = FreqTable::from_freqs(freqs.iter().copied());
+            let cumul = cumulfreq_array::CumulFreqTable::from_freqs(freqs.iter().copied());
+            let bit = BinaryIndexedTree::from_freqs(freqs.iter().copied());
+            let mut treap = Treap::from_freqs(freqs.iter().copied());
+            assert_eq!(table, cumul);
+            assert_eq!(table, bit);
+            assert_eq!(table, treap);
+            assert_eq!(cumul, table);
+            assert_eq!(cumul, bit);
+            assert_eq!(cumul, treap);
+            assert_eq!(bit, table);
+            assert_eq!(bit, cumul);
+            assert_eq!(bit, treap);
+            assert_eq!(treap, table);
+            assert_eq!(treap, cumul);
+            assert_eq!(treap, bit);
+
+            treap.inc(len - 1);
+            assert_ne!(table, treap);
+            assert_ne!(treap, bit);
+            treap.dec(len - 1);
+            treap.push(0);
+            assert_ne!(cumul, treap);
+            assert_ne!(treap, table);
+        }
+    }
+
+    #[test]
+    fn freq_array_eq_test() {
+        let built = FreqTable::from_freqs([1.0, 1e-16]);
+        let mut added = FreqTable::<f64>::new(2);
+        added.add(0, 1.0);
+        added.add(1, 1e-16);
+        assert_eq!(built, added);
+        added.sub(1, 1e-16);
+        assert_ne!(built, added);
+    }
+
+    #[test]
+    fn hash_test() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        fn hash<T: Hash>(val: &T) -> u64 {
+            let mut state = DefaultHasher::new();
+            val.hash(&mut state);
+            state.finish()
         }
-        table.scale(|x| x * 2.into());
-        for i in 0..len {
-            assert_eq!(table.freq(i), 2.into());
+        for len in 1..=32 {
+            let freqs: Vec<u32> = (0..len as u32).map(|i| i * 7 % 5).collect();
+            let expected = hash(&freqs);
+            assert_eq!(
+                hash(&FreqTable::from_freqs(freqs.iter().copied())),
+                expected
+            );
+            assert_eq!(
+                hash(&cumulfreq_array::CumulFreqTable::from_freqs(
+                    freqs.iter().copied()
+                )),
+                expected
+            );
+            assert_eq!(
+                hash(&BinaryIndexedTree::from_freqs(freqs.iter().copied())),
+                expected
+            );
+            let mut treap = Treap::from_freqs(freqs.iter().copied());
+            assert_eq!(hash(&treap), expected);
+            // The shape of the treap doesn't matter.
+            treap.insert_at(len / 2, 3);
+            treap.remove_at(len / 2);
+            assert_eq!(hash(&treap), expected);
+            treap.inc(0);
+            assert_ne!(hash(&treap), expected);
         }
-        table.scale(|x| (x + 1.into()) / 2.into());
-        for i in 0..len {
-            assert_eq!(table.freq(i), 1.into());
+    }
+
+    #[test]
+    fn default_test() {
+        default_test_impl::<freq_array::FreqTable<u32>>();
+        default_test_impl::<cumulfreq_array::CumulFreqTable<u32>>();
+        default_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>();
+        default_test_impl::<treap::CumulFreqTable<u32>>();
+    }
+
+    fn default_test_impl<T>()
+    where
+        T: CumulFreqTable<u32> + Default + Debug + PartialEq,
+    {
+        let mut table = T::default();
+        assert_eq!(table, T::new(1));
+        assert_eq!(table.len(), 1);
+        assert!(!table.is_empty());
+        assert_eq!(table.total(), 0);
+        table.push(3);
+        assert_eq!(table.sum(1), 3);
+    }
+
+    #[test]
+    fn from_freqs_test() {
+        for len in 1..=32 {
+            from_freqs_test_impl::<freq_array::FreqTable>(len);
+            from_freqs_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            from_freqs_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            from_freqs_test_impl::<treap::CumulFreqTable>(len);
         }
-        table.scale(|x| x * 42.into());
-        for i in 0..len {
-            assert_eq!(table.freq(i), 42.into());
+    }
+
+    fn from_freqs_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let freqs: Vec<usize> = (0..len).map(|pos| pos * 7 % 5).collect();
+        let table = T::from_freqs(freqs.iter().copied());
+        let mut expected = T::new(len);
+        for (pos, &freq) in freqs.iter().enumerate() {
+            expected.add(pos, freq);
         }
-        let mut a = table.clone();
-        let mut b = table.clone();
-        a.scale(|x| x / 5.into());
-        b.scale(|x| (x + 1.into()) / 5.into());
-        for i in 0..len {
-            assert_eq!(a.freq(i), 8.into());
+        assert_eq!(table, expected);
+        let mut sum = 0;
+        for (pos, &freq) in freqs.iter().enumerate() {
+            sum += freq;
+            assert_eq!(table.freq(pos), freq);
+            assert_eq!(table.sum(pos), sum);
         }
-        for i in 0..len {
-            assert_eq!(b.freq(i), 8.into());
+        assert_eq!(table.total(), sum);
+    }
+
+    #[test]
+    fn count_test() {
+        count_test_impl::<freq_array::FreqTable>();
+        count_test_impl::<cumulfreq_array::CumulFreqTable>();
+        count_test_impl::<binary_indexed_tree::CumulFreqTable>();
+        count_test_impl::<treap::CumulFreqTable>();
+    }
+
+    fn count_test_impl<T>()
+    where
+        T: CumulFreqTable + FromIterator<usize> + Debug + PartialEq,
+    {
+        let observations = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        let table: T = observations.iter().copied().collect();
+        assert_eq!(table.len(), 10);
+        assert_eq!(table.total(), observations.len());
+        for pos in 0..10 {
+            let count = observations.iter().filter(|&&o| o == pos).count();
+            assert_eq!(table.freq(pos), count);
         }
-        a.scale(|x| x / 9.into());
-        b.scale(|x| (x + 1.into()) / 9.into());
+        let counted = T::count_from(observations, 12);
+        assert_eq!(counted.len(), 12);
+        assert_eq!(counted.freq(11), 0);
+        let mut resized = table;
+        resized.resize(12);
+        assert_eq!(counted, resized);
+    }
+
+    #[test]
+    fn extend_test() {
+        extend_test_impl::<freq_array::FreqTable>();
+        extend_test_impl::<cumulfreq_array::CumulFreqTable>();
+        extend_test_impl::<binary_indexed_tree::CumulFreqTable>();
+        extend_test_impl::<treap::CumulFreqTable>();
+    }
+
+    fn extend_test_impl<T>()
+    where
+        T: CumulFreqTable + Extend<usize> + Extend<(usize, usize)> + Debug + PartialEq,
+    {
+        let observations = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        let mut table = T::new(10);
+        table.extend(observations);
+        assert_eq!(table, T::count_from(observations, 10));
+        table.extend([(0, 3), (9, 2)]);
+        assert_eq!(table.freq(0), 3);
+        assert_eq!(table.freq(9), 3);
+        assert_eq!(table.total(), observations.len() + 5);
+    }
+
+    #[test]
+    fn iter_test() {
+        for len in 1..=32 {
+            iter_test_impl::<freq_array::FreqTable>(len);
+            iter_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            iter_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            iter_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn iter_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug,
+    {
+        let freqs: Vec<usize> = (0..len).map(|pos| pos * 7 % 5).collect();
+        let table = T::from_freqs(freqs.iter().copied());
+        let pairs: Vec<(usize, usize)> = table.iter().collect();
+        assert_eq!(pairs, freqs.into_iter().enumerate().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn to_freq_sum_vec_test() {
+        for len in 1..=32 {
+            to_freq_sum_vec_test_impl::<freq_array::FreqTable<u32>>(len);
+            to_freq_sum_vec_test_impl::<cumulfreq_array::CumulFreqTable<u32>>(len);
+            to_freq_sum_vec_test_impl::<binary_indexed_tree::CumulFreqTable<u32>>(len);
+            to_freq_sum_vec_test_impl::<treap::CumulFreqTable<u32>>(len);
+        }
+    }
+
+    fn to_freq_sum_vec_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u32> + Debug,
+    {
+        let mut table = T::new(len);
         for i in 0..len {
-            assert_eq!(a.freq(i), 0.into());
+            table.add(i, (i as u32 * 7) % 5);
+        }
+        let freqs: Vec<u32> = (0..len).map(|pos| table.freq(pos)).collect();
+        let sums: Vec<u32> = (0..len).map(|pos| table.sum(pos)).collect();
+        assert_eq!(table.to_freq_vec(), freqs);
+        assert_eq!(table.to_sum_vec(), sums);
+    }
+
+    #[test]
+    fn into_vec_test() {
+        for len in 1..=32 {
+            into_vec_test_impl::<freq_array::FreqTable>(len);
+            into_vec_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            into_vec_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            into_vec_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn into_vec_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + IntoIterator<Item = usize> + Into<Vec<usize>> + Clone + Debug,
+    {
+        let freqs: Vec<usize> = (0..len).map(|pos| pos * 7 % 5).collect();
+        let table = T::from_freqs(freqs.iter().copied());
+        assert_eq!(table.clone().into_iter().collect::<Vec<_>>(), freqs);
+        let vec: Vec<usize> = table.into();
+        assert_eq!(vec, freqs);
+    }
+
+    #[test]
+    fn index_test() {
+        for len in 1..=32 {
+            index_test_impl::<freq_array::FreqTable>(len);
+            index_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn index_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + std::ops::Index<usize, Output = usize> + Debug,
+    {
+        let table = T::from_freqs((0..len).map(|pos| pos * 7 % 5));
+        for pos in 0..len {
+            assert_eq!(table[pos], pos * 7 % 5);
+        }
+    }
+
+    #[test]
+    fn set_test() {
+        for len in 1..=32 {
+            set_test_impl::<freq_array::FreqTable>(len);
+            set_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            set_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            set_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn set_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let mut table = T::with_freq(len, 3);
+        for pos in 0..len {
+            table.set(pos, pos % 5);
+        }
+        assert_eq!(table, T::from_freqs((0..len).map(|pos| pos % 5)));
+        assert_eq!(table.total(), (0..len).map(|pos| pos % 5).sum::<usize>());
+    }
+
+    #[test]
+    fn swap_test() {
+        for len in 1..=32 {
+            swap_test_impl::<freq_array::FreqTable>(len);
+            swap_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            swap_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            swap_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn swap_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let mut freqs: Vec<usize> = (0..len).map(|pos| pos * 7 % 5).collect();
+        let mut table = T::from_freqs(freqs.iter().copied());
+        for a in 0..len {
+            let b = (a * 3 + 1) % len;
+            table.swap(a, b);
+            freqs.swap(a, b);
+            assert_eq!(table, T::from_freqs(freqs.iter().copied()));
+        }
+    }
+
+    #[test]
+    fn bit_swap_test() {
+        for len in 1..=32 {
+            let freqs: Vec<u32> = (0..len).map(|pos| pos * 7 % 5).collect();
+            for padded in [false, true] {
+                let mut table = BinaryIndexedTree::from_freqs(freqs.iter().copied());
+                if padded {
+                    table = table.padded();
+                }
+                for a in 0..len as usize {
+                    for b in 0..len as usize {
+                        // Like the default, with frequency queries and point updates.
+                        let mut expected = table.clone();
+                        let (fa, fb) = (expected.freq(a), expected.freq(b));
+                        expected.sub(a, fa);
+                        expected.add(a, fb);
+                        expected.sub(b, fb);
+                        expected.add(b, fa);
+                        table.swap(a, b);
+                        assert_eq!(table, expected);
+                        assert_eq!(table.total(), expected.total());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_test() {
+        for len in 1..=32 {
+            reverse_test_impl::<freq_array::FreqTable>(len);
+            reverse_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            reverse_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            reverse_test_impl::<treap::CumulFreqTable>(len);
         }
+    }
+
+    fn reverse_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let freqs: Vec<usize> = (0..len).map(|pos| pos * 7 % 5).collect();
+        let mut table = T::from_freqs(freqs.iter().copied());
+        table.reverse();
+        assert_eq!(table, T::from_freqs(freqs.iter().rev().copied()));
+        assert_eq!(table.total(), freqs.iter().sum::<usize>());
+        table.reverse();
+        assert_eq!(table, T::from_freqs(freqs.iter().copied()));
+    }
+
+    #[test]
+    fn rotate_test() {
+        for len in 1..=32 {
+            rotate_test_impl::<freq_array::FreqTable>(len);
+            rotate_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            rotate_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            rotate_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn rotate_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let freqs: Vec<usize> = (0..len).map(|pos| pos * 7 % 5).collect();
+        for k in 0..=len {
+            let mut table = T::from_freqs(freqs.iter().copied());
+            let mut expected = freqs.clone();
+            table.rotate_left(k);
+            expected.rotate_left(k);
+            assert_eq!(table, T::from_freqs(expected.iter().copied()));
+            table.rotate_right(k);
+            assert_eq!(table, T::from_freqs(freqs.iter().copied()));
+        }
+    }
+
+    #[test]
+    fn clear_test() {
+        for len in 1..=32 {
+            clear_test_impl::<freq_array::FreqTable>(len);
+            clear_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            clear_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            clear_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn clear_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let mut table = T::from_freqs((0..len).map(|pos| pos * 7 % 5 + 1));
+        table.clear();
+        assert_eq!(table, T::new(len));
+        assert_eq!(table.total(), 0);
+        table.inc(len - 1);
+        assert_eq!(table.sum(len - 1), 1);
+    }
+
+    #[test]
+    fn fill_test() {
+        for len in 1..=32 {
+            fill_test_impl::<freq_array::FreqTable>(len);
+            fill_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            fill_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            fill_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn fill_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let mut table = T::from_freqs((0..len).map(|pos| pos * 7 % 5));
+        table.fill(3);
+        assert_eq!(table, T::with_freq(len, 3));
+        assert_eq!(table.total(), 3 * len);
+        for pos in 0..len {
+            assert_eq!(table.sum(pos), 3 * (pos + 1));
+        }
+    }
+
+    #[test]
+    fn checked_test() {
+        for len in 1..=32 {
+            checked_test_impl::<freq_array::FreqTable<u8>>(len);
+            checked_test_impl::<cumulfreq_array::CumulFreqTable<u8>>(len);
+            checked_test_impl::<binary_indexed_tree::CumulFreqTable<u8>>(len);
+            checked_test_impl::<treap::CumulFreqTable<u8>>(len);
+        }
+    }
+
+    fn checked_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable<u8> + Debug + Clone + PartialEq,
+    {
+        let mut table = T::new(len);
+        let last = len - 1;
+        assert_eq!(table.try_sub(last, 1), Err(OverflowError));
+        assert_eq!(table, T::new(len));
+        table.try_add(last, 200).unwrap();
+        let before = table.clone();
+        // The total would overflow, even if the frequency doesn't.
+        assert_eq!(table.try_add(0, 100), Err(OverflowError));
+        assert_eq!(table, before);
+        table.try_add(0, 55).unwrap();
+        assert_eq!(table.total(), 255);
+        let full = table.clone();
+        assert_eq!(table.try_add(last / 2, 1), Err(OverflowError));
+        assert_eq!(table, full);
+        if len > 1 {
+            assert_eq!(table.try_sub(0, 56), Err(OverflowError));
+            assert_eq!(table, full);
+        }
+        table.try_sub(0, 55).unwrap();
+        assert_eq!(table, before);
+    }
+
+    #[test]
+    fn checked_signed_test() {
+        checked_signed_test_impl::<freq_array::FreqTable<i16>>();
+        checked_signed_test_impl::<cumulfreq_array::CumulFreqTable<i16>>();
+        checked_signed_test_impl::<binary_indexed_tree::CumulFreqTable<i16>>();
+        checked_signed_test_impl::<treap::CumulFreqTable<i16>>();
+    }
+
+    fn checked_signed_test_impl<T>()
+    where
+        T: CumulFreqTable<i16> + Debug + Clone + PartialEq,
+    {
+        // The cumulative frequencies stay in range, but not the frequency of the last position.
+        let mut table = T::from_freqs([-30000, 30000]);
+        let before = table.clone();
+        assert_eq!(table.try_add(1, 5000), Err(OverflowError));
+        assert_eq!(table, before);
+        assert_eq!(table.try_sub(0, 5000), Err(OverflowError));
+        assert_eq!(table, before);
+        table.try_add(1, 2000).unwrap();
+        assert_eq!(table.freq(1), 32000);
+        assert_eq!(table.total(), 2000);
+    }
+
+    #[test]
+    fn try_new_test() {
+        try_new_test_impl::<freq_array::FreqTable<u8>>();
+        try_new_test_impl::<cumulfreq_array::CumulFreqTable<u8>>();
+        try_new_test_impl::<binary_indexed_tree::CumulFreqTable<u8>>();
+        try_new_test_impl::<treap::CumulFreqTable<u8>>();
+    }
+
+    fn try_new_test_impl<T>()
+    where
+        T: CumulFreqTable<u8> + Debug + PartialEq,
+    {
+        assert_eq!(T::try_new(0), Err(TableError::Empty));
+        assert_eq!(T::try_new(300), Ok(T::new(300)));
+        assert_eq!(T::try_with_freq(0, 1), Err(TableError::Empty));
+        assert_eq!(T::try_with_freq(51, 5), Ok(T::with_freq(51, 5)));
+        assert_eq!(T::try_with_freq(52, 5), Err(TableError::Overflow));
+        // The length itself doesn't fit.
+        assert_eq!(T::try_with_freq(300, 0), Err(TableError::Overflow));
+    }
+
+    #[test]
+    fn get_test() {
+        for len in 1..=32 {
+            get_test_impl::<freq_array::FreqTable>(len);
+            get_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            get_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            get_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn get_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug,
+    {
+        let table = T::from_freqs((0..len).map(|pos| pos * 7 % 5));
+        for pos in 0..len {
+            assert_eq!(table.get_freq(pos), Some(table.freq(pos)));
+            assert_eq!(table.get_sum(pos), Some(table.sum(pos)));
+        }
+        assert_eq!(table.get_freq(len), None);
+        assert_eq!(table.get_sum(len), None);
+        assert_eq!(table.get_freq(usize::MAX), None);
+    }
+
+    #[test]
+    fn unchecked_test() {
+        for len in 1..=32 {
+            unchecked_test_impl::<freq_array::FreqTable>(len);
+            unchecked_test_impl::<cumulfreq_array::CumulFreqTable>(len);
+            unchecked_test_impl::<binary_indexed_tree::CumulFreqTable>(len);
+            unchecked_test_impl::<treap::CumulFreqTable>(len);
+        }
+    }
+
+    fn unchecked_test_impl<T>(len: usize)
+    where
+        T: CumulFreqTable + Debug + PartialEq,
+    {
+        let mut table = T::new(len);
+        for pos in 0..len {
+            // SAFETY: pos < len.
+            unsafe { table.add_unchecked(pos, pos * 7 % 5) };
+        }
+        assert_eq!(table, T::from_freqs((0..len).map(|pos| pos * 7 % 5)));
+        for pos in 0..len {
+            // SAFETY: pos < len.
+            unsafe {
+                assert_eq!(table.freq_unchecked(pos), table.freq(pos));
+                assert_eq!(table.sum_unchecked(pos), table.sum(pos));
+            }
+        }
+    }
+
+    #[test]
+    fn capped_test() {
+        capped_test_impl::<freq_array::FreqTable<u16>>();
+        capped_test_impl::<binary_indexed_tree::CumulFreqTable<u16>>();
+        capped_test_impl::<treap::CumulFreqTable<u16>>();
+    }
+
+    fn capped_test_impl<T>()
+    where
+        T: CumulFreqTable<u16> + Debug,
+    {
+        let mut table = CappedTable::<T, u16>::new(4, 255);
+        assert_eq!(table.add(1, 200), 200);
+        assert_eq!(table.add(1, 100), 55);
+        assert!(!table.inc(1));
+        assert!(table.inc(2));
+        assert_eq!(table.max_freq(), 255);
+        assert_eq!(table.table().freq(1), 255);
+        assert_eq!(table.table().total(), 256);
+        let table = table.into_inner();
+        assert_eq!(table.sum(1), 255);
+    }
+
+    #[test]
+    fn signed_test() {
+        for len in 1..=32 {
+            signed_test_impl::<freq_array::FreqTable<i32>>(len, true);
+            signed_test_impl::<cumulfreq_array::CumulFreqTable<i32>>(len, true);
+            signed_test_impl::<binary_indexed_tree::CumulFreqTable<i32>>(len, false);
+            signed_test_impl::<treap::CumulFreqTable<i32>>(len, false);
+        }
+    }
+
+    fn signed_test_impl<T>(len: usize, first: bool)
+    where
+        T: CumulFreqTable<i32> + Debug + PartialEq,
+    {
+        // A difference table, with negative partial sums.
+        let freqs: Vec<i32> = (0..len as i32).map(|pos| pos * 7 % 11 - 5).collect();
+        let mut table = T::new(len);
+        for (pos, &freq) in freqs.iter().enumerate() {
+            if freq < 0 {
+                table.sub(pos, -freq);
+            } else {
+                table.add(pos, freq);
+            }
+        }
+        assert_eq!(table, T::from_freqs(freqs.iter().copied()));
+        let sums: Vec<i32> = freqs
+            .iter()
+            .scan(0, |sum, &freq| {
+                *sum += freq;
+                Some(*sum)
+            })
+            .collect();
+        for (pos, &sum) in sums.iter().enumerate() {
+            assert_eq!(table.freq(pos), freqs[pos]);
+            assert_eq!(table.sum(pos), sum);
+        }
+        let max = *sums.iter().max().unwrap();
+        for target in -40..40 {
+            let pos = table.find_by_sum(target);
+            assert_eq!(
+                table.find_with_bounds(target),
+                (pos, table.bounds(pos).0, sums[pos])
+            );
+            if target > max || (!first && pos == len - 1 && sums[pos] < target) {
+                assert_eq!(pos, len - 1);
+                continue;
+            }
+            // A crossing.
+            assert!(target <= sums[pos]);
+            assert!(pos == 0 || sums[pos - 1] < target);
+            if first {
+                assert!(sums[..pos].iter().all(|&sum| sum < target));
+            }
+        }
+    }
+
+    #[test]
+    fn kahan_test() {
+        let len = 1_000_000;
+        let table = FreqTable::<f64>::from_freqs(std::iter::repeat_n(0.1, len));
+        assert!((table.total() - 100_000.0).abs() < 1e-9);
+        assert!((table.sum(len - 1) - 100_000.0).abs() < 1e-9);
+        assert!((table.sum_range(1..) - 99_999.9).abs() < 1e-9);
+        assert_eq!(table.find_by_sum(50_000.0), 499_999);
+
+        let mut table = FreqTable::<f64>::new(10);
         for i in 0..len {
-            assert_eq!(b.freq(i), 1.into());
+            table.add(i % 10, 0.1);
+        }
+        assert!((table.total() - 100_000.0).abs() < 1e-9);
+        table.scale(|f| f / 4.0);
+        assert!((table.total() - 25_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn u128_test() {
+        u128_test_impl::<freq_array::FreqTable<u128>>();
+        u128_test_impl::<cumulfreq_array::CumulFreqTable<u128>>();
+        u128_test_impl::<binary_indexed_tree::CumulFreqTable<u128>>();
+        u128_test_impl::<treap::CumulFreqTable<u128>>();
+    }
+
+    fn u128_test_impl<T>()
+    where
+        T: CumulFreqTable<u128> + Debug + PartialEq,
+    {
+        let big = u64::MAX as u128;
+        let mut table = T::with_freq(5, big);
+        table.add(2, big);
+        assert_eq!(table.total(), 6 * big);
+        assert_eq!(table.sum(2), 4 * big);
+        assert_eq!(table.find_by_sum(3 * big + 1), 2);
+        assert_eq!(table.try_add(0, u128::MAX), Err(OverflowError));
+
+        let mut buf = Vec::new();
+        table.write_to(&mut buf).unwrap();
+        assert_eq!(T::read_from(&mut buf.as_slice()).unwrap(), table);
+        #[cfg(feature = "json")]
+        assert_eq!(T::from_json(&table.to_json()).unwrap(), table);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn bigint_test() {
+        use num_bigint::BigUint;
+        for len in 1..=32 {
+            let mut big = BigTable::new(len);
+            let mut small = BinaryIndexedTree::<u128>::new(len);
+            for i in 0..100 {
+                let pos = i * 7 % len;
+                let val = (i * 37 % 1000) as u64;
+                big.add(pos, BigUint::from(val));
+                small.add(pos, val as u128);
+            }
+            for pos in 0..len {
+                assert_eq!(big.freq(pos), BigUint::from(small.freq(pos)));
+                assert_eq!(big.sum(pos), BigUint::from(small.sum(pos)));
+            }
+            for sum in 0..=small.total() + 1 {
+                assert_eq!(big.find_by_sum(BigUint::from(sum)), small.find_by_sum(sum));
+                assert_eq!(
+                    big.find_by_sum_strict(BigUint::from(sum)),
+                    small.find_by_sum_strict(sum)
+                );
+            }
+            big.reverse();
+            small.reverse();
+            big.scale(|f| f / 2u8);
+            small.scale(|f| f / 2);
+            assert_eq!(
+                big.scale_ret(|f| f * 3u8),
+                BigUint::from(small.scale_ret(|f| f * 3))
+            );
+            let freqs: Vec<BigUint> = (0..len).map(|pos| big.freq(pos)).collect();
+            let expected: Vec<BigUint> = (0..len).map(|pos| small.freq(pos).into()).collect();
+            assert_eq!(freqs, expected);
+            assert_eq!(BigTable::from_freqs(freqs), big);
         }
+
+        // Beyond any primitive type.
+        let max = BigUint::from(u128::MAX);
+        let mut table = BigTable::with_freq(3, max.clone());
+        table.add(1, max.clone());
+        assert_eq!(table.total(), &max * 4u8);
+        assert_eq!(table.find_by_sum(&max * 2u8 + 1u8), 1);
+        assert_eq!(table.pop(), max);
+        assert_eq!(table.try_sub(0, &max + 1u8), Err(OverflowError));
+        assert_eq!(table.try_add(0, max.clone()), Ok(()));
+        assert_eq!(table.freq(0), &max * 2u8);
     }
 }
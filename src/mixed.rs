@@ -0,0 +1,95 @@
+use crate::CumulFreqTable;
+use std::ops::{Add, Mul};
+
+/// A read-only view of the weighted sum of two tables of the same length, as in context mixing.
+/// Every query combines the queries of both tables: `weight_a * a + weight_b * b`. With integer
+/// weights, the results are exact.
+#[derive(Debug)]
+pub struct MixedView<'a, A, B, F = usize> {
+    a: &'a A,
+    b: &'a B,
+    weight_a: F,
+    weight_b: F,
+}
+
+impl<A, B, F: Copy> Clone for MixedView<'_, A, B, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, B, F: Copy> Copy for MixedView<'_, A, B, F> {}
+
+impl<'a, A, B, F> MixedView<'a, A, B, F>
+where
+    A: CumulFreqTable<F>,
+    B: CumulFreqTable<F>,
+    F: From<u8> + Copy + Add<Output = F> + Mul<Output = F> + PartialOrd,
+{
+    /// Mix the two tables with the given weights.
+    /// Panics if the tables have different lengths.
+    pub fn new(a: &'a A, weight_a: F, b: &'a B, weight_b: F) -> Self {
+        assert_eq!(a.len(), b.len(), "tables must have the same length");
+        Self {
+            a,
+            b,
+            weight_a,
+            weight_b,
+        }
+    }
+
+    /// The weights of the two tables.
+    pub fn weights(&self) -> (F, F) {
+        (self.weight_a, self.weight_b)
+    }
+
+    /// The two underlying tables.
+    pub fn tables(&self) -> (&'a A, &'a B) {
+        (self.a, self.b)
+    }
+
+    /// Get the length of the tables.
+    pub fn len(&self) -> usize {
+        self.a.len()
+    }
+
+    /// Always false, tables are non-empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the mixed frequency of the given position.
+    pub fn freq(&self, pos: usize) -> F {
+        self.mix(self.a.freq(pos), self.b.freq(pos))
+    }
+
+    /// Get the mixed cumulative frequency of the given position.
+    pub fn sum(&self, pos: usize) -> F {
+        self.mix(self.a.sum(pos), self.b.sum(pos))
+    }
+
+    /// The mixed total cumulative frequency.
+    pub fn total(&self) -> F {
+        self.mix(self.a.total(), self.b.total())
+    }
+
+    /// Find the first position with an equal or greater mixed cumulative frequency.
+    /// Returns the last position if sum is greater than the total.
+    /// O(㏒₂ len) cumulative frequency queries.
+    pub fn find_by_sum(&self, sum: F) -> usize {
+        let (mut lo, mut hi) = (0, self.len() - 1);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.sum(mid) >= sum {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+
+    fn mix(&self, a: F, b: F) -> F {
+        self.weight_a * a + self.weight_b * b
+    }
+}
@@ -0,0 +1,116 @@
+use crate::CumulFreqTable;
+
+/// A multiset of the integers in `[0, universe)`, counting every element in a table.
+/// Insertion, removal, counting, ranking and selection are all O(㏒₂ universe) with a tree based
+/// table.
+///
+/// # Example
+///
+/// ```rust
+/// use cumulfreqtable::{BinaryIndexedTree, CountingMultiset};
+///
+/// let mut set = CountingMultiset::<BinaryIndexedTree>::new(100);
+/// for x in [42, 7, 42, 99, 3] {
+///     set.insert(x);
+/// }
+/// assert_eq!(set.count(42), 2);
+/// assert_eq!(set.rank(42), 2);
+/// assert_eq!(set.kth(2), Some(42));
+/// assert_eq!(set.kth(set.len() / 2), Some(42));
+/// assert!(set.remove(42));
+/// assert_eq!(set.len(), 4);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountingMultiset<T, F = usize> {
+    table: T,
+    _freq: std::marker::PhantomData<F>,
+}
+
+impl<T, F> CountingMultiset<T, F>
+where
+    T: CumulFreqTable<F>,
+    F: From<u8> + PartialOrd,
+{
+    /// Create an empty multiset of the integers in `[0, universe)`.
+    /// Panics if universe < 1.
+    pub fn new(universe: usize) -> Self {
+        Self {
+            table: T::new(universe),
+            _freq: std::marker::PhantomData,
+        }
+    }
+
+    /// The number of distinct integers that can be stored.
+    pub fn universe(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Add an occurrence of x.
+    /// Panics if x is out of the universe.
+    pub fn insert(&mut self, x: usize) {
+        self.table.inc(x);
+    }
+
+    /// Remove an occurrence of x. Returns false if x is absent.
+    /// Panics if x is out of the universe.
+    pub fn remove(&mut self, x: usize) -> bool {
+        if self.table.freq(x) > 0.into() {
+            self.table.dec(x);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The number of occurrences of x.
+    /// Panics if x is out of the universe.
+    pub fn count(&self, x: usize) -> F {
+        self.table.freq(x)
+    }
+
+    /// Whether x occurs at least once.
+    /// Panics if x is out of the universe.
+    pub fn contains(&self, x: usize) -> bool {
+        self.count(x) > 0.into()
+    }
+
+    /// The number of elements strictly smaller than x.
+    /// Panics if x is out of the universe.
+    pub fn rank(&self, x: usize) -> F {
+        self.table.rank(x)
+    }
+
+    /// The k-th smallest element, counting from 0. None if k is not below the length.
+    pub fn kth(&self, k: F) -> Option<usize> {
+        if k < self.table.total() {
+            Some(self.table.select(k))
+        } else {
+            None
+        }
+    }
+
+    /// The number of elements, counting every occurrence.
+    pub fn len(&self) -> F {
+        self.table.total()
+    }
+
+    /// Whether there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0.into()
+    }
+
+    /// The memory used in bytes, inline and on the heap, see [CumulFreqTable::memory_bytes].
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<T>() + self.table.memory_bytes()
+    }
+
+    /// The underlying table.
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Return the underlying table.
+    pub fn into_inner(self) -> T {
+        self.table
+    }
+}
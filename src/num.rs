@@ -0,0 +1,109 @@
+//! Frequencies from any [num_traits] numeric type, enabled by the `num-traits` feature.
+//!
+//! The implementations require [crate::Frequency], built from the operators of `std`. [NumFreq]
+//! wraps a type implementing [num_traits::Num] and provides all of them, so any type of the
+//! num-traits ecosystem can be used with any implementation.
+//!
+//! The trait keeps its `From<u8>` bound, primitive types are used directly.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cumulfreqtable::{num::NumFreq, BinaryIndexedTree, CumulFreqTable};
+//!
+//! let mut table = BinaryIndexedTree::<NumFreq<u32>>::new(4);
+//! table.add(1, NumFreq(3));
+//! table.inc(2);
+//! assert_eq!(table.total(), NumFreq(4));
+//! assert_eq!(table.find_by_sum(NumFreq(4)), 2);
+//! ```
+
+use crate::{Checked, OverflowError, ToF64};
+use num_traits::{CheckedAdd, CheckedMul, CheckedSub, FromPrimitive, Num, ToPrimitive};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Rem, Sub, SubAssign};
+
+/// A frequency of type T, implementing [crate::Frequency].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct NumFreq<T>(pub T);
+
+impl<T> NumFreq<T> {
+    /// The wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: FromPrimitive> From<u8> for NumFreq<T> {
+    /// Panics if T cannot represent val.
+    fn from(val: u8) -> Self {
+        Self(T::from_u8(val).expect("T cannot represent a u8"))
+    }
+}
+
+impl<T: FromPrimitive> TryFrom<usize> for NumFreq<T> {
+    type Error = OverflowError;
+
+    fn try_from(val: usize) -> Result<Self, OverflowError> {
+        T::from_usize(val).map(Self).ok_or(OverflowError)
+    }
+}
+
+macro_rules! impl_op {
+    ($($op:ident, $method:ident;)*) => {$(
+        impl<T: Num> $op for NumFreq<T> {
+            type Output = Self;
+
+            fn $method(self, rhs: Self) -> Self {
+                Self(self.0.$method(rhs.0))
+            }
+        }
+    )*};
+}
+
+impl_op! {
+    Add, add;
+    Sub, sub;
+    Mul, mul;
+    Div, div;
+    Rem, rem;
+}
+
+impl<T: Num + Copy> AddAssign for NumFreq<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 = self.0 + rhs.0;
+    }
+}
+
+impl<T: Num + Copy> SubAssign for NumFreq<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 = self.0 - rhs.0;
+    }
+}
+
+impl<T: Num> Sum for NumFreq<T> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Self(iter.fold(T::zero(), |acc, val| acc + val.0))
+    }
+}
+
+impl<T: CheckedAdd + CheckedSub + CheckedMul> Checked for NumFreq<T> {
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(&rhs.0).map(Self)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(&rhs.0).map(Self)
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        self.0.checked_mul(&rhs.0).map(Self)
+    }
+}
+
+impl<T: ToPrimitive> ToF64 for NumFreq<T> {
+    /// NaN if T has no f64 representation.
+    fn to_f64(self) -> f64 {
+        ToPrimitive::to_f64(&self.0).unwrap_or(f64::NAN)
+    }
+}
@@ -0,0 +1,62 @@
+//! Quantization of frequencies to a power-of-two total.
+
+/// Rescale freqs so they sum to exactly 2^total_bits, keeping every non-zero frequency at least 1.
+/// Every frequency is first scaled down. The remainder goes to the positions with the largest
+/// fractional parts. The excess, from the frequencies raised to 1, is taken from the largest
+/// frequencies.
+/// Panics if the total is zero or if there are more non-zero frequencies than 2^total_bits.
+/// Panics if total_bits > 63.
+pub(crate) fn quantize(freqs: &[u64], total_bits: u32) -> Vec<u64> {
+    assert!(total_bits < 64, "total_bits must be < 64");
+    let target = 1_u64 << total_bits;
+    let total: u128 = freqs.iter().map(|&f| f as u128).sum();
+    assert!(total > 0, "total must be non-zero");
+    let nonzero = freqs.iter().filter(|&&f| f > 0).count();
+    assert!(
+        nonzero as u128 <= target as u128,
+        "more non-zero frequencies than the target total"
+    );
+
+    let mut remainders = Vec::with_capacity(nonzero);
+    let mut scaled: Vec<u64> = freqs
+        .iter()
+        .enumerate()
+        .map(|(pos, &f)| {
+            if f == 0 {
+                return 0;
+            }
+            let exact = f as u128 * target as u128;
+            remainders.push((exact % total, pos));
+            // exact / total <= target.
+            ((exact / total) as u64).max(1)
+        })
+        .collect();
+    let mut sum: u64 = scaled.iter().sum();
+
+    if sum < target {
+        // Less than one missing per non-zero position. Ties go to the lowest position.
+        remainders.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for &(_, pos) in &remainders[..(target - sum) as usize] {
+            scaled[pos] += 1;
+        }
+        sum = target;
+    }
+
+    if sum > target {
+        let mut by_freq: Vec<usize> = (0..scaled.len()).filter(|&pos| scaled[pos] > 1).collect();
+        by_freq.sort_by(|&a, &b| scaled[b].cmp(&scaled[a]));
+        // Terminates because the target is at least the number of non-zero positions.
+        while sum > target {
+            for &pos in &by_freq {
+                if sum == target {
+                    break;
+                }
+                if scaled[pos] > 1 {
+                    scaled[pos] -= 1;
+                    sum -= 1;
+                }
+            }
+        }
+    }
+    scaled
+}
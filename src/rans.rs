@@ -0,0 +1,150 @@
+//! Static rANS model, enabled by the `rans` feature.
+//!
+//! A [Model] is a frozen snapshot of a table, normalized to a total of `2^scale_bits`, with every
+//! non-zero frequency at least 1. The encoder needs the `(start, freq)` of every symbol, and the
+//! decoder needs to find the symbol from a slot in `[0, 2^scale_bits)`. The latter is a single
+//! lookup in a table of `2^scale_bits` entries.
+//!
+//! # Example
+//!
+//! A minimal 32 bits rANS coder with byte-wise renormalization.
+//!
+//! ```rust
+//! use cumulfreqtable::{rans, CumulFreqTable, FreqTable};
+//!
+//! let message = [0, 1, 0, 2, 0, 0, 1, 3];
+//! let mut table = FreqTable::<u32>::new(4);
+//! for &symbol in &message {
+//!     table.inc(symbol);
+//! }
+//! let model = rans::Model::new(&table, 12);
+//! const LOW: u32 = 1 << 23;
+//!
+//! // rANS encodes in reverse.
+//! let mut state = LOW;
+//! let mut bytes = Vec::new();
+//! for &symbol in message.iter().rev() {
+//!     let (start, freq) = model.get(symbol);
+//!     let max = ((LOW >> model.scale_bits()) << 8) * freq;
+//!     while state >= max {
+//!         bytes.push(state as u8);
+//!         state >>= 8;
+//!     }
+//!     state = ((state / freq) << model.scale_bits()) + (state % freq) + start;
+//! }
+//!
+//! let mask = (1 << model.scale_bits()) - 1;
+//! for &symbol in &message {
+//!     let slot = state & mask;
+//!     let pos = model.find_by_sum(slot);
+//!     assert_eq!(pos, symbol);
+//!     let (start, freq) = model.get(pos);
+//!     state = freq * (state >> model.scale_bits()) + slot - start;
+//!     while state < LOW {
+//!         state = (state << 8) | bytes.pop().unwrap() as u32;
+//!     }
+//! }
+//! ```
+
+use crate::CumulFreqTable;
+
+/// The largest supported scale_bits.
+pub const MAX_SCALE_BITS: u32 = 24;
+
+/// A frozen table normalized to a power-of-two total.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Model {
+    scale_bits: u32,
+    /// The start of every symbol, followed by the total.
+    starts: Vec<u32>,
+    /// The symbol of every slot.
+    symbols: Vec<u32>,
+}
+
+impl Model {
+    /// Freeze and normalize the table to a total of `2^scale_bits`.
+    /// Panics if the total is zero or if there are more non-zero positions than `2^scale_bits`.
+    /// Panics if scale_bits > [MAX_SCALE_BITS] or if a frequency doesn't fit in u64.
+    /// Panics if the table has more than 2^32 positions.
+    /// O(len + 2^scale_bits).
+    pub fn new<F, T>(table: &T, scale_bits: u32) -> Self
+    where
+        T: CumulFreqTable<F>,
+        F: From<u8> + TryInto<u64>,
+    {
+        assert!(
+            scale_bits <= MAX_SCALE_BITS,
+            "scale_bits must be <= MAX_SCALE_BITS"
+        );
+        assert!(table.len() as u64 <= 1 << 32, "too many positions");
+        let freqs: Vec<u64> = (0..table.len())
+            .map(|pos| match table.freq(pos).try_into() {
+                Ok(freq) => freq,
+                Err(_) => panic!("frequency doesn't fit in u64"),
+            })
+            .collect();
+        let freqs = crate::quantize::quantize(&freqs, scale_bits);
+
+        let mut starts = Vec::with_capacity(freqs.len() + 1);
+        let mut symbols = Vec::with_capacity(1 << scale_bits);
+        let mut start = 0;
+        for (pos, &freq) in freqs.iter().enumerate() {
+            starts.push(start);
+            // freq <= 2^scale_bits.
+            start += freq as u32;
+            symbols.resize(start as usize, pos as u32);
+        }
+        starts.push(start);
+        Self {
+            scale_bits,
+            starts,
+            symbols,
+        }
+    }
+
+    /// The total is `2^scale_bits`.
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    /// The number of symbols.
+    pub fn len(&self) -> usize {
+        self.starts.len() - 1
+    }
+
+    /// Always false, tables are non-empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The cumulative frequency strictly below the symbol.
+    /// Panics if pos is out of bounds.
+    /// O(1).
+    pub fn start(&self, pos: usize) -> u32 {
+        assert!(pos < self.len(), "pos out of bounds");
+        self.starts[pos]
+    }
+
+    /// The normalized frequency of the symbol.
+    /// Panics if pos is out of bounds.
+    /// O(1).
+    pub fn freq(&self, pos: usize) -> u32 {
+        assert!(pos < self.len(), "pos out of bounds");
+        self.starts[pos + 1] - self.starts[pos]
+    }
+
+    /// The `(start, freq)` of the symbol.
+    /// Panics if pos is out of bounds.
+    /// O(1).
+    pub fn get(&self, pos: usize) -> (u32, u32) {
+        assert!(pos < self.len(), "pos out of bounds");
+        (self.starts[pos], self.starts[pos + 1] - self.starts[pos])
+    }
+
+    /// Find the symbol covering the slot: `start(pos) <= slot < start(pos) + freq(pos)`.
+    /// Panics if slot >= 2^scale_bits.
+    /// O(1).
+    pub fn find_by_sum(&self, slot: u32) -> usize {
+        self.symbols[slot as usize] as usize
+    }
+}
@@ -0,0 +1,174 @@
+//! Weighted sampling of the positions, enabled by the `rand` feature.
+//!
+//! Every table is a [Distribution] of the positions, each drawn with a probability proportional
+//! to its frequency. Unlike [rand::distributions::WeightedIndex], the weights can be updated in
+//! O(㏒₂ len) with a tree based table.
+//!
+//! When the frequencies won't change for a while, [CumulFreqTable::freeze_sampler] takes an
+//! [AliasSampler] snapshot, drawing in O(1).
+//!
+//! # Example
+//!
+//! ```rust
+//! use cumulfreqtable::{BinaryIndexedTree, CumulFreqTable};
+//! use rand::distributions::Distribution;
+//!
+//! let mut table = BinaryIndexedTree::<u32>::new(4);
+//! table.add(1, 3);
+//! table.add(3, 1);
+//! let mut rng = rand::thread_rng();
+//! let pos = table.sample(&mut rng);
+//! assert!(pos == 1 || pos == 3);
+//! ```
+
+use crate::{
+    binary_indexed_tree, cumulfreq_array, freq_array, treap, CumulFreqTable, Frequency, ToF64,
+};
+use rand::distributions::uniform::SampleUniform;
+use rand::distributions::Distribution;
+use rand::Rng;
+
+/// Draw a cumulative frequency uniformly in `[0, total)`. The position to sample is the first
+/// with a cumulative frequency greater than the draw: it has a non-zero frequency, and
+/// `[sum_exclusive(pos), sum(pos))` has a length of freq(pos). This also holds for floats.
+/// Panics if the total is zero.
+pub(crate) fn draw_sum<F, R>(total: F, rng: &mut R) -> F
+where
+    F: Frequency + SampleUniform,
+    R: Rng + ?Sized,
+{
+    assert!(total > 0.into(), "total must be non-zero");
+    rng.gen_range(0.into()..total)
+}
+
+/// Draw a position with a probability proportional to its frequency.
+/// Panics if the total is zero. Assumes non-negative frequencies.
+pub(crate) fn draw<F, T, R>(table: &T, rng: &mut R) -> usize
+where
+    F: Frequency + SampleUniform,
+    T: CumulFreqTable<F> + ?Sized,
+    R: Rng + ?Sized,
+{
+    table.find_by_sum_strict(draw_sum(table.total(), rng))
+}
+
+macro_rules! impl_distribution {
+    ($($table:ty),+) => {
+        $(
+            impl<F> Distribution<usize> for $table
+            where
+                F: Frequency + SampleUniform,
+            {
+                /// Draw a position with a probability proportional to its frequency.
+                /// Panics if the total is zero.
+                fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+                    draw(self, rng)
+                }
+            }
+        )+
+    };
+}
+
+impl_distribution!(
+    freq_array::FreqTable<F>,
+    cumulfreq_array::CumulFreqTable<F>,
+    binary_indexed_tree::CumulFreqTable<F>,
+    treap::CumulFreqTable<F>
+);
+
+/// An immutable snapshot of the frequencies, drawing a position in O(1) with the alias method of
+/// Walker and Vose. Created in O(len) by [CumulFreqTable::freeze_sampler] or
+/// [AliasSampler::from_freqs], and turned back into a live table with [AliasSampler::to_table].
+///
+/// # Example
+///
+/// ```rust
+/// use cumulfreqtable::{BinaryIndexedTree, CumulFreqTable};
+/// use rand::distributions::Distribution;
+///
+/// let table = BinaryIndexedTree::<u32>::from_freqs([0, 3, 0, 1]);
+/// let sampler = table.freeze_sampler();
+/// let mut rng = rand::thread_rng();
+/// let pos = sampler.sample(&mut rng);
+/// assert!(pos == 1 || pos == 3);
+/// let table: BinaryIndexedTree<u32> = sampler.to_table();
+/// assert_eq!(table.total(), 4);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasSampler<F> {
+    freqs: Vec<F>,
+    // The probability to keep the column drawn, scaled so that a uniform column is 1.
+    prob: Vec<f64>,
+    // The position drawn when the column is not kept.
+    alias: Vec<usize>,
+}
+
+impl<F: Frequency + ToF64> AliasSampler<F> {
+    /// Create a sampler for the given frequencies.
+    /// Panics if there are no frequencies or the total is zero. Assumes non-negative frequencies.
+    pub fn from_freqs<I: IntoIterator<Item = F>>(freqs: I) -> Self {
+        let freqs: Vec<F> = freqs.into_iter().collect();
+        assert!(!freqs.is_empty(), "table must be non-empty");
+        let total: f64 = freqs.iter().map(|freq| freq.to_f64()).sum();
+        assert!(total > 0.0, "total must be non-zero");
+
+        let len = freqs.len();
+        let scale = len as f64 / total;
+        let mut prob: Vec<f64> = freqs.iter().map(|freq| freq.to_f64() * scale).collect();
+        // Columns left over by rounding errors alias to a position with the largest frequency,
+        // so a zero frequency is never drawn.
+        let max_pos = (0..len).fold(0, |max, pos| if prob[pos] > prob[max] { pos } else { max });
+        let mut alias = vec![max_pos; len];
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..len).partition(|&pos| prob[pos] < 1.0);
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            alias[s] = l;
+            prob[l] -= 1.0 - prob[s];
+            if prob[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+        for pos in large {
+            prob[pos] = 1.0;
+        }
+        Self { freqs, prob, alias }
+    }
+
+    /// The number of positions.
+    pub fn len(&self) -> usize {
+        self.freqs.len()
+    }
+
+    /// Always false, a sampler has at least one position.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The frequencies of the snapshot, in position order.
+    pub fn freqs(&self) -> &[F] {
+        &self.freqs
+    }
+
+    /// Create a live table with the frequencies of the snapshot.
+    /// O(len) or O(len ㏒₂ len) depending on the implementation of
+    /// [CumulFreqTable::from_freqs].
+    pub fn to_table<T: CumulFreqTable<F>>(&self) -> T {
+        T::from_freqs(self.freqs.iter().copied())
+    }
+}
+
+impl<F> Distribution<usize> for AliasSampler<F> {
+    /// Draw a position with a probability proportional to its frequency.
+    /// O(1).
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let pos = rng.gen_range(0..self.prob.len());
+        if rng.gen::<f64>() < self.prob[pos] {
+            pos
+        } else {
+            self.alias[pos]
+        }
+    }
+}
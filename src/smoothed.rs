@@ -0,0 +1,97 @@
+use crate::CumulFreqTable;
+use std::ops::Add;
+
+/// A read-only view of a table with a constant alpha added to every position, as with additive
+/// (Laplace) smoothing. The underlying table is untouched.
+#[derive(Debug)]
+pub struct SmoothedView<'a, T, F = usize> {
+    table: &'a T,
+    alpha: F,
+}
+
+impl<T, F: Copy> Clone for SmoothedView<'_, T, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, F: Copy> Copy for SmoothedView<'_, T, F> {}
+
+impl<'a, T, F> SmoothedView<'a, T, F>
+where
+    T: CumulFreqTable<F>,
+    F: From<u8> + Copy + Add<Output = F> + PartialOrd,
+{
+    /// Smooth the table by alpha.
+    pub fn new(table: &'a T, alpha: F) -> Self {
+        Self { table, alpha }
+    }
+
+    /// The constant added to every position.
+    pub fn alpha(&self) -> F {
+        self.alpha
+    }
+
+    /// The underlying table.
+    pub fn table(&self) -> &'a T {
+        self.table
+    }
+
+    /// Get the length of the table.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Always false, tables are non-empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the smoothed frequency of the given position.
+    pub fn freq(&self, pos: usize) -> F {
+        self.table.freq(pos) + self.alpha
+    }
+
+    /// Get the smoothed cumulative frequency of the given position.
+    pub fn sum(&self, pos: usize) -> F {
+        self.table.sum(pos) + times(self.alpha, pos + 1)
+    }
+
+    /// The smoothed total cumulative frequency.
+    pub fn total(&self) -> F {
+        self.table.total() + times(self.alpha, self.len())
+    }
+
+    /// Find the first position with an equal or greater smoothed cumulative frequency.
+    /// Returns the last position if sum is greater than the total.
+    /// O(㏒₂ len) cumulative frequency queries.
+    pub fn find_by_sum(&self, sum: F) -> usize {
+        // The smoothed cumulative frequencies are non-decreasing, search the first position
+        // reaching sum.
+        let (mut lo, mut hi) = (0, self.len() - 1);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.sum(mid) >= sum {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+}
+
+/// `val * n` with additions only, by doubling. O(㏒₂ n).
+fn times<F: From<u8> + Copy + Add<Output = F>>(mut val: F, mut n: usize) -> F {
+    let mut acc = F::from(0);
+    while n > 0 {
+        if n & 1 == 1 {
+            acc = acc + val;
+        }
+        n >>= 1;
+        if n > 0 {
+            val = val + val;
+        }
+    }
+    acc
+}
@@ -0,0 +1,19 @@
+//! Statistics of the distribution held by a table, computed in f64.
+
+/// A frequency type convertible to f64, possibly with a loss of precision.
+pub trait ToF64 {
+    /// The nearest f64.
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_to_f64 {
+    ($($t:ty),*) => {$(
+        impl ToF64 for $t {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )*};
+}
+
+impl_to_f64!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
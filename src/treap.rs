@@ -0,0 +1,957 @@
+use crate::{Checked, Frequency, OverflowError};
+use std::ops::{Index, IndexMut};
+
+/// The index of a node. u32 halves the size of the links, so the table is limited to
+/// `u32::MAX - 1` positions.
+type Idx = u32;
+
+const NIL: Idx = Idx::MAX;
+
+/// Store the frequencies in an implicit treap: a randomized balanced binary tree ordered by
+/// position, where every node also maintains the size, the total frequency and the number of
+/// non-zero frequencies of its subtree.
+///
+/// Unlike the other implementations, positions can be inserted and removed anywhere in the table
+/// with [CumulFreqTable::insert_at] and [CumulFreqTable::remove_at], shifting the following
+/// positions. All operations are O(㏒₂ len) expected, but with a larger constant factor than
+/// [crate::BinaryIndexedTree].
+///
+/// The nodes link to each other with u32 indices, so the table holds at most `u32::MAX - 1`
+/// positions. Creating or growing a larger table panics.
+#[derive(Debug, Clone)]
+pub struct CumulFreqTable<F = usize> {
+    nodes: Nodes<F>,
+    free: Vec<Idx>,
+    root: Idx,
+    rng: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Node<F> {
+    freq: F,
+    sum: F,
+    size: Idx,
+    nonzero: Idx,
+    prio: u64,
+    left: Idx,
+    right: Idx,
+}
+
+/// The arena of nodes, indexed by [Idx].
+#[derive(Debug, Clone)]
+struct Nodes<F>(Vec<Node<F>>);
+
+impl<F> Index<Idx> for Nodes<F> {
+    type Output = Node<F>;
+
+    fn index(&self, node: Idx) -> &Node<F> {
+        &self.0[node as usize]
+    }
+}
+
+impl<F> IndexMut<Idx> for Nodes<F> {
+    fn index_mut(&mut self, node: Idx) -> &mut Node<F> {
+        &mut self.0[node as usize]
+    }
+}
+
+impl<F> CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    fn empty() -> Self {
+        Self {
+            nodes: Nodes(Vec::new()),
+            free: Vec::new(),
+            root: NIL,
+            rng: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    fn repeat(len: usize, init: F) -> Self {
+        assert!(len > 0, "table must be non-empty");
+        let mut table = Self::empty();
+        table.nodes.0.reserve(len);
+        table.root = table.build(std::iter::repeat_n(init, len));
+        table
+    }
+
+    /// Build the treap from the frequencies. O(len).
+    pub(crate) fn from_vec(freqs: Vec<F>) -> Self {
+        assert!(!freqs.is_empty(), "table must be non-empty");
+        let mut table = Self::empty();
+        table.nodes.0.reserve(freqs.len());
+        table.root = table.build(freqs.into_iter());
+        table
+    }
+
+    /// Copy the frequencies in position order. O(len).
+    pub(crate) fn to_vec(&self) -> Vec<F> {
+        self.in_order()
+            .into_iter()
+            .map(|node| self.nodes[node].freq)
+            .collect()
+    }
+
+    /// Move the frequencies out in position order. O(len).
+    pub(crate) fn into_vec(self) -> Vec<F> {
+        self.to_vec()
+    }
+
+    /// The priorities of the nodes in position order, which determine the shape of the treap.
+    #[cfg(test)]
+    pub(crate) fn priorities(&self) -> Vec<u64> {
+        self.in_order()
+            .into_iter()
+            .map(|node| self.nodes[node].prio)
+            .collect()
+    }
+
+    /// Insert a position with the given frequency before pos. The following positions are
+    /// shifted by one.
+    /// Panics if pos > len.
+    /// O(㏒₂ len) expected.
+    pub fn insert_at(&mut self, pos: usize, freq: F) {
+        assert!(pos <= self.size(self.root), "pos out of bounds");
+        let node = self.alloc(freq);
+        let (left, right) = self.split(self.root, pos);
+        let left = self.merge(left, node);
+        self.root = self.merge(left, right);
+    }
+
+    /// Remove the given position and return its frequency. The following positions are shifted
+    /// by one.
+    /// Panics if pos is out of bounds.
+    /// Panics if the table has a single position.
+    /// O(㏒₂ len) expected.
+    pub fn remove_at(&mut self, pos: usize) -> F {
+        let len = self.size(self.root);
+        assert!(pos < len, "pos out of bounds");
+        assert!(len > 1, "table must be non-empty");
+        let (left, right) = self.split(self.root, pos);
+        let (node, right) = self.split(right, 1);
+        self.root = self.merge(left, right);
+        self.free.push(node);
+        self.nodes[node].freq
+    }
+
+    fn size(&self, node: Idx) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].size as usize
+        }
+    }
+
+    fn node_sum(&self, node: Idx) -> F {
+        if node == NIL {
+            0.into()
+        } else {
+            self.nodes[node].sum
+        }
+    }
+
+    fn node_nonzero(&self, node: Idx) -> Idx {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].nonzero
+        }
+    }
+
+    fn update(&mut self, node: Idx) {
+        let Node {
+            left, right, freq, ..
+        } = self.nodes[node];
+        let size = 1 + self.size(left) + self.size(right);
+        let mut sum = self.node_sum(left);
+        sum += freq;
+        sum += self.node_sum(right);
+        let nonzero =
+            (freq != 0.into()) as Idx + self.node_nonzero(left) + self.node_nonzero(right);
+        // Bounded by the number of nodes.
+        self.nodes[node].size = size as Idx;
+        self.nodes[node].sum = sum;
+        self.nodes[node].nonzero = nonzero;
+    }
+
+    /// Account for the frequency of pos changing from old to new, on the path from the root.
+    /// O(㏒₂ len) expected, only when the frequency becomes or stops being zero.
+    fn track_nonzero(&mut self, mut pos: usize, old: F, new: F) {
+        let (was, is) = (old != 0.into(), new != 0.into());
+        if was == is {
+            return;
+        }
+        let mut node = self.root;
+        loop {
+            if is {
+                self.nodes[node].nonzero += 1;
+            } else {
+                self.nodes[node].nonzero -= 1;
+            }
+            let left = self.nodes[node].left;
+            let left_size = self.size(left);
+            if pos < left_size {
+                node = left;
+            } else if pos == left_size {
+                return;
+            } else {
+                pos -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+    }
+
+    /// Panics if there are already `u32::MAX - 1` nodes.
+    fn alloc(&mut self, freq: F) -> Idx {
+        // xorshift64*.
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let node = Node {
+            freq,
+            sum: freq,
+            size: 1,
+            nonzero: (freq != 0.into()) as Idx,
+            prio: self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d),
+            left: NIL,
+            right: NIL,
+        };
+        self.place(node)
+    }
+
+    /// Store the node, in a free slot if any.
+    /// Panics if there are already `u32::MAX - 1` nodes.
+    fn place(&mut self, node: Node<F>) -> Idx {
+        if let Some(idx) = self.free.pop() {
+            self.nodes[idx] = node;
+            idx
+        } else {
+            assert!(self.nodes.0.len() < NIL as usize, "too many positions");
+            self.nodes.0.push(node);
+            (self.nodes.0.len() - 1) as Idx
+        }
+    }
+
+    /// Split the subtree in two: the first pos positions, and the rest.
+    fn split(&mut self, node: Idx, pos: usize) -> (Idx, Idx) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+        let Node { left, right, .. } = self.nodes[node];
+        let left_size = self.size(left);
+        if pos <= left_size {
+            let (a, b) = self.split(left, pos);
+            self.nodes[node].left = b;
+            self.update(node);
+            (a, node)
+        } else {
+            let (a, b) = self.split(right, pos - left_size - 1);
+            self.nodes[node].right = a;
+            self.update(node);
+            (node, b)
+        }
+    }
+
+    /// Concatenate two subtrees.
+    fn merge(&mut self, a: Idx, b: Idx) -> Idx {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+        if self.nodes[a].prio > self.nodes[b].prio {
+            let right = self.merge(self.nodes[a].right, b);
+            self.nodes[a].right = right;
+            self.update(a);
+            a
+        } else {
+            let left = self.merge(a, self.nodes[b].left);
+            self.nodes[b].left = left;
+            self.update(b);
+            b
+        }
+    }
+
+    /// Build a subtree from the frequencies in position order.
+    /// O(n) with the stack based cartesian tree construction.
+    fn build<I: Iterator<Item = F>>(&mut self, freqs: I) -> Idx {
+        // The right spine of the tree being built. Every node is the right child of the previous.
+        let mut spine: Vec<Idx> = Vec::new();
+        for freq in freqs {
+            let node = self.alloc(freq);
+            let mut left = NIL;
+            while let Some(&top) = spine.last() {
+                if self.nodes[top].prio > self.nodes[node].prio {
+                    break;
+                }
+                // The subtree of top is complete.
+                self.update(top);
+                left = top;
+                spine.pop();
+            }
+            self.nodes[node].left = left;
+            if let Some(&top) = spine.last() {
+                self.nodes[top].right = node;
+            }
+            spine.push(node);
+        }
+        for &node in spine.iter().rev() {
+            self.update(node);
+        }
+        spine.first().copied().unwrap_or(NIL)
+    }
+
+    /// Copy the subtree of another arena into this one, with the same shape and priorities.
+    /// O(n).
+    fn copy_subtree(&mut self, nodes: &Nodes<F>, node: Idx) -> Idx {
+        if node == NIL {
+            return NIL;
+        }
+        let left = self.copy_subtree(nodes, nodes[node].left);
+        let right = self.copy_subtree(nodes, nodes[node].right);
+        self.place(Node {
+            left,
+            right,
+            ..nodes[node].clone()
+        })
+    }
+
+    /// Move the subtree into a table of its own. O(n).
+    fn take_subtree(&mut self, node: Idx) -> Self {
+        let mut table = Self::empty();
+        table.nodes.0.reserve(self.size(node));
+        table.root = table.copy_subtree(&self.nodes, node);
+        self.free_subtree(node);
+        table
+    }
+
+    fn free_subtree(&mut self, node: Idx) {
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if node != NIL {
+                stack.push(self.nodes[node].left);
+                stack.push(self.nodes[node].right);
+                self.free.push(node);
+            }
+        }
+    }
+
+    /// The nodes in position order.
+    fn in_order(&self) -> Vec<Idx> {
+        let mut order = Vec::with_capacity(self.size(self.root));
+        let mut stack = Vec::new();
+        let mut node = self.root;
+        while node != NIL || !stack.is_empty() {
+            while node != NIL {
+                stack.push(node);
+                node = self.nodes[node].left;
+            }
+            // SAFETY: the loop condition guarantees the stack is non-empty here.
+            node = unsafe { stack.pop().unwrap_unchecked() };
+            order.push(node);
+            node = self.nodes[node].right;
+        }
+        order
+    }
+
+    /// Recompute the size and sum of every subtree bottom-up, after the frequencies changed.
+    /// O(len).
+    fn update_all(&mut self) {
+        // A post-order traversal.
+        let mut stack = vec![(self.root, false)];
+        while let Some((node, visited)) = stack.pop() {
+            if node == NIL {
+                continue;
+            }
+            if visited {
+                self.update(node);
+            } else {
+                stack.push((node, true));
+                stack.push((self.nodes[node].left, false));
+                stack.push((self.nodes[node].right, false));
+            }
+        }
+    }
+
+    /// Whether check succeeds on the sum of every node from the root to pos, and on its frequency.
+    fn check_path(&self, mut pos: usize, check: impl Fn(F) -> Option<F>) -> bool {
+        let mut node = self.root;
+        loop {
+            if check(self.nodes[node].sum).is_none() {
+                return false;
+            }
+            let left = self.nodes[node].left;
+            let left_size = self.size(left);
+            if pos < left_size {
+                node = left;
+            } else if pos == left_size {
+                return check(self.nodes[node].freq).is_some();
+            } else {
+                pos -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+    }
+
+    /// Find the position like [CumulFreqTable::find_by_sum_strict], applying op with val to the
+    /// sums on the path and to the frequency found. A search and an update in a single descent.
+    #[cfg(feature = "rand")]
+    fn find_strict_and_update(&mut self, mut sum: F, val: F, op: fn(&mut F, F)) -> usize {
+        let mut pos = 0;
+        let mut node = self.root;
+        loop {
+            let Node {
+                left, right, freq, ..
+            } = self.nodes[node];
+            op(&mut self.nodes[node].sum, val);
+            if left != NIL && self.nodes[left].sum > sum {
+                node = left;
+                continue;
+            }
+            sum -= self.node_sum(left);
+            pos += self.size(left);
+            if freq > sum || right == NIL {
+                op(&mut self.nodes[node].freq, val);
+                self.track_nonzero(pos, freq, self.nodes[node].freq);
+                return pos;
+            }
+            sum -= freq;
+            pos += 1;
+            node = right;
+        }
+    }
+
+    /// Find the node at the given position.
+    fn find(&self, mut pos: usize) -> Idx {
+        let mut node = self.root;
+        loop {
+            let left = self.nodes[node].left;
+            let left_size = self.size(left);
+            if pos < left_size {
+                node = left;
+            } else if pos == left_size {
+                return node;
+            } else {
+                pos -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+    }
+}
+
+impl<F> super::CumulFreqTable<F> for CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    /// Panics if len < 1.
+    /// O(len).
+    fn new(len: usize) -> Self {
+        Self::repeat(len, 0.into())
+    }
+
+    /// Panics if len < 1.
+    /// O(len).
+    fn with_freq(len: usize, init: F) -> Self {
+        Self::repeat(len, init)
+    }
+
+    /// Panics if freqs is empty.
+    /// O(len).
+    fn from_freqs<I: IntoIterator<Item = F>>(freqs: I) -> Self {
+        Self::from_vec(freqs.into_iter().collect())
+    }
+
+    /// O(1).
+    fn len(&self) -> usize {
+        self.size(self.root)
+    }
+
+    /// Includes the free nodes, kept for reuse.
+    /// O(1).
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.nodes.0.capacity() * std::mem::size_of::<Node<F>>()
+            + self.free.capacity() * std::mem::size_of::<Idx>()
+    }
+
+    /// Panics if new_len < 1.
+    /// Panics if the removed frequencies don't sum to zero.
+    /// O(|new_len - len| + ㏒₂ len) expected.
+    fn resize(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        let len = self.len();
+        if new_len < len {
+            assert!(
+                self.sum(new_len - 1) == self.total(),
+                "removed frequencies must sum to zero"
+            );
+            self.truncate(new_len);
+        } else {
+            self.extend_with(new_len - len, 0.into());
+        }
+    }
+
+    /// Panics if new_len < 1.
+    /// O(len - new_len + ㏒₂ len) expected.
+    fn truncate(&mut self, new_len: usize) {
+        assert!(new_len > 0, "table must be non-empty");
+        if new_len < self.len() {
+            let (left, right) = self.split(self.root, new_len);
+            self.root = left;
+            self.free_subtree(right);
+        }
+    }
+
+    /// Panics if mid < 1 or mid >= len.
+    /// O(min(mid, len - mid) + ㏒₂ len) expected: the treap is split in O(㏒₂ len), and the
+    /// nodes of the smaller side move to an arena of their own, keeping their shape.
+    fn split_off(&mut self, mid: usize) -> Self {
+        let len = self.len();
+        assert!(mid > 0 && mid < len, "mid out of bounds");
+        let (left, right) = self.split(self.root, mid);
+        if len - mid <= mid {
+            self.root = left;
+            self.take_subtree(right)
+        } else {
+            // The tail keeps the arena.
+            let head = self.take_subtree(left);
+            self.root = right;
+            std::mem::replace(self, head)
+        }
+    }
+
+    /// O(min(len, other len) + ㏒₂ len) expected: the nodes of the smaller table move to the arena
+    /// of the larger one, keeping their shape, and the treaps are merged in O(㏒₂ len).
+    fn concat(mut self, mut other: Self) -> Self {
+        if self.len() >= other.len() {
+            let right = self.copy_subtree(&other.nodes, other.root);
+            self.root = self.merge(self.root, right);
+            self
+        } else {
+            let left = other.copy_subtree(&self.nodes, self.root);
+            other.root = other.merge(left, other.root);
+            other
+        }
+    }
+
+    /// O(extra + ㏒₂ len) expected.
+    fn extend_with(&mut self, extra: usize, init: F) {
+        let right = self.build(std::iter::repeat_n(init, extra));
+        self.root = self.merge(self.root, right);
+    }
+
+    /// O(㏒₂ len) expected.
+    fn push(&mut self, freq: F) {
+        self.insert_at(self.len(), freq);
+    }
+
+    /// Panics if len < 2.
+    /// O(㏒₂ len) expected.
+    fn pop(&mut self) -> F {
+        self.remove_at(self.len() - 1)
+    }
+
+    /// Freed nodes are reused first.
+    /// O(len).
+    fn reserve(&mut self, additional: usize) {
+        self.nodes
+            .0
+            .reserve(additional.saturating_sub(self.free.len()));
+    }
+
+    /// Rebuilds the treap to release the freed nodes, if any.
+    /// O(len).
+    fn shrink_to_fit(&mut self) {
+        if !self.free.is_empty() {
+            *self = Self::from_vec(self.to_vec());
+        }
+        self.nodes.0.shrink_to_fit();
+        self.free.shrink_to_fit();
+    }
+
+    /// Panics if pos is out of bounds.
+    /// Panics on overflow in debug.
+    /// O(㏒₂ len) expected.
+    fn add(&mut self, pos: usize, val: F) {
+        assert!(pos < self.len(), "pos out of bounds");
+        let (mut rem, mut node) = (pos, self.root);
+        loop {
+            self.nodes[node].sum += val;
+            let left = self.nodes[node].left;
+            let left_size = self.size(left);
+            if rem < left_size {
+                node = left;
+            } else if rem == left_size {
+                let old = self.nodes[node].freq;
+                self.nodes[node].freq += val;
+                self.track_nonzero(pos, old, self.nodes[node].freq);
+                return;
+            } else {
+                rem -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+    }
+
+    /// Panics if pos is out of bounds.
+    /// Panics on underflow in debug.
+    /// O(㏒₂ len) expected.
+    fn sub(&mut self, pos: usize, val: F) {
+        assert!(pos < self.len(), "pos out of bounds");
+        let (mut rem, mut node) = (pos, self.root);
+        loop {
+            self.nodes[node].sum -= val;
+            let left = self.nodes[node].left;
+            let left_size = self.size(left);
+            if rem < left_size {
+                node = left;
+            } else if rem == left_size {
+                let old = self.nodes[node].freq;
+                self.nodes[node].freq -= val;
+                self.track_nonzero(pos, old, self.nodes[node].freq);
+                return;
+            } else {
+                rem -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len) expected.
+    fn try_add(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.len(), "pos out of bounds");
+        if !self.check_path(pos, |f| f.checked_add(val)) {
+            return Err(OverflowError);
+        }
+        self.add(pos, val);
+        Ok(())
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len) expected.
+    fn try_sub(&mut self, pos: usize, val: F) -> Result<(), OverflowError>
+    where
+        F: Checked,
+    {
+        assert!(pos < self.len(), "pos out of bounds");
+        if !self.check_path(pos, |f| f.checked_sub(val)) {
+            return Err(OverflowError);
+        }
+        self.sub(pos, val);
+        Ok(())
+    }
+
+    /// O(len).
+    fn clear(&mut self) {
+        // Every subtree sums to zero, including the free nodes which are harmless.
+        for node in self.nodes.0.iter_mut() {
+            node.freq = 0.into();
+            node.sum = 0.into();
+            node.nonzero = 0;
+        }
+    }
+
+    /// O(len).
+    fn fill(&mut self, init: F) {
+        for node in self.in_order() {
+            self.nodes[node].freq = init;
+        }
+        self.update_all();
+    }
+
+    /// O(len).
+    fn reverse(&mut self) {
+        // The shape of the tree doesn't depend on the frequencies. Reverse them in place.
+        let order = self.in_order();
+        for (&a, &b) in order.iter().zip(order.iter().rev()).take(order.len() / 2) {
+            let freq = self.nodes[a].freq;
+            self.nodes[a].freq = self.nodes[b].freq;
+            self.nodes[b].freq = freq;
+        }
+        self.update_all();
+    }
+
+    /// Panics if k > len.
+    /// O(㏒₂ len) expected.
+    fn rotate_left(&mut self, k: usize) {
+        assert!(k <= self.size(self.root), "k out of bounds");
+        let (left, right) = self.split(self.root, k);
+        self.root = self.merge(right, left);
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len) expected.
+    fn sum(&self, mut pos: usize) -> F {
+        assert!(pos < self.len(), "pos out of bounds");
+        let mut sum: F = 0.into();
+        let mut node = self.root;
+        loop {
+            let left = self.nodes[node].left;
+            let left_size = self.size(left);
+            if pos < left_size {
+                node = left;
+            } else {
+                sum += self.node_sum(left);
+                sum += self.nodes[node].freq;
+                if pos == left_size {
+                    return sum;
+                }
+                pos -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len) expected.
+    fn bounds(&self, mut pos: usize) -> (F, F) {
+        assert!(pos < self.len(), "pos out of bounds");
+        let mut low: F = 0.into();
+        let mut node = self.root;
+        loop {
+            let left = self.nodes[node].left;
+            let left_size = self.size(left);
+            if pos < left_size {
+                node = left;
+            } else {
+                low += self.node_sum(left);
+                if pos == left_size {
+                    let mut high = low;
+                    high += self.nodes[node].freq;
+                    return (low, high);
+                }
+                low += self.nodes[node].freq;
+                pos -= left_size + 1;
+                node = self.nodes[node].right;
+            }
+        }
+    }
+
+    /// O(1).
+    fn total(&self) -> F {
+        self.node_sum(self.root)
+    }
+
+    /// O(1).
+    fn count_nonzero(&self) -> usize {
+        self.node_nonzero(self.root) as usize
+    }
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len) expected.
+    fn freq(&self, pos: usize) -> F {
+        assert!(pos < self.len(), "pos out of bounds");
+        self.nodes[self.find(pos)].freq
+    }
+
+    /// O(len).
+    fn to_freq_vec(&self) -> Vec<F> {
+        self.to_vec()
+    }
+
+    /// O(len).
+    fn iter(&self) -> impl Iterator<Item = (usize, F)> + '_ {
+        self.in_order()
+            .into_iter()
+            .map(|node| self.nodes[node].freq)
+            .enumerate()
+    }
+
+    /// O(㏒₂ len) expected.
+    fn find_by_sum(&self, mut sum: F) -> usize {
+        let mut pos = 0;
+        let mut node = self.root;
+        loop {
+            let Node {
+                left, right, freq, ..
+            } = self.nodes[node];
+            if left != NIL && self.nodes[left].sum >= sum {
+                node = left;
+                continue;
+            }
+            sum -= self.node_sum(left);
+            pos += self.size(left);
+            if freq >= sum || right == NIL {
+                return pos;
+            }
+            sum -= freq;
+            pos += 1;
+            node = right;
+        }
+    }
+
+    /// Panics if the total is zero.
+    /// O(㏒₂ len) expected, in a single descent.
+    #[cfg(feature = "rand")]
+    fn sample_and_inc<R: rand::Rng + ?Sized>(&mut self, rng: &mut R) -> usize
+    where
+        F: rand::distributions::uniform::SampleUniform,
+    {
+        let sum = crate::sample::draw_sum(self.total(), rng);
+        self.find_strict_and_update(sum, 1.into(), F::add_assign)
+    }
+
+    /// Panics if the total is zero.
+    /// O(㏒₂ len) expected, in a single descent.
+    #[cfg(feature = "rand")]
+    fn sample_and_dec<R: rand::Rng + ?Sized>(&mut self, rng: &mut R) -> usize
+    where
+        F: rand::distributions::uniform::SampleUniform,
+    {
+        let sum = crate::sample::draw_sum(self.total(), rng);
+        self.find_strict_and_update(sum, 1.into(), F::sub_assign)
+    }
+
+    /// O(㏒₂ len) expected.
+    fn find_by_sum_strict(&self, mut sum: F) -> usize {
+        let mut pos = 0;
+        let mut node = self.root;
+        loop {
+            let Node {
+                left, right, freq, ..
+            } = self.nodes[node];
+            if left != NIL && self.nodes[left].sum > sum {
+                node = left;
+                continue;
+            }
+            sum -= self.node_sum(left);
+            pos += self.size(left);
+            if freq > sum || right == NIL {
+                return pos;
+            }
+            sum -= freq;
+            pos += 1;
+            node = right;
+        }
+    }
+
+    /// O(㏒₂ len) expected.
+    fn find_with_bounds(&self, mut sum: F) -> (usize, F, F) {
+        let mut pos = 0;
+        let mut low: F = 0.into();
+        let mut node = self.root;
+        loop {
+            let Node {
+                left, right, freq, ..
+            } = self.nodes[node];
+            if left != NIL && self.nodes[left].sum >= sum {
+                node = left;
+                continue;
+            }
+            sum -= self.node_sum(left);
+            low += self.node_sum(left);
+            pos += self.size(left);
+            if freq >= sum || right == NIL {
+                let mut high = low;
+                high += freq;
+                return (pos, low, high);
+            }
+            sum -= freq;
+            low += freq;
+            pos += 1;
+            node = right;
+        }
+    }
+
+    /// O(len).
+    /// scale_freq is called O(len) times (once per position).
+    fn scale<C: Fn(F) -> F>(&mut self, scale_freq: C) {
+        for node in self.in_order() {
+            self.nodes[node].freq = scale_freq(self.nodes[node].freq);
+        }
+        self.update_all();
+    }
+
+    /// O(len).
+    fn map_freqs<M: FnMut(usize, F) -> F>(&mut self, mut map: M) {
+        for (pos, node) in self.in_order().into_iter().enumerate() {
+            self.nodes[node].freq = map(pos, self.nodes[node].freq);
+        }
+        self.update_all();
+    }
+
+    /// Panics if the tables have different lengths.
+    /// Panics on overflow in debug.
+    /// O(len).
+    fn merge_add<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        for (pos, node) in self.in_order().into_iter().enumerate() {
+            self.nodes[node].freq += other.freq(pos);
+        }
+        self.update_all();
+    }
+
+    /// Panics if the tables have different lengths.
+    /// Panics on underflow in debug.
+    /// O(len).
+    fn merge_sub<T: super::CumulFreqTable<F>>(&mut self, other: &T) {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        for (pos, node) in self.in_order().into_iter().enumerate() {
+            self.nodes[node].freq -= other.freq(pos);
+        }
+        self.update_all();
+    }
+
+    /// Panics if the tables have different lengths.
+    /// O(len).
+    /// combine is called O(len) times (once per position).
+    fn combine_with<T, C>(&mut self, other: &T, combine: C)
+    where
+        T: super::CumulFreqTable<F>,
+        C: Fn(F, F) -> F,
+    {
+        assert_eq!(self.len(), other.len(), "tables must have the same length");
+        for (pos, node) in self.in_order().into_iter().enumerate() {
+            self.nodes[node].freq = combine(self.nodes[node].freq, other.freq(pos));
+        }
+        self.update_all();
+    }
+}
+
+impl<F> Default for CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    /// A single position with a zero frequency, tables are never empty.
+    fn default() -> Self {
+        <Self as super::CumulFreqTable<F>>::new(1)
+    }
+}
+
+impl<F: PartialEq> PartialEq for CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    /// Compare the frequencies position by position, regardless of the shape of the trees.
+    fn eq(&self, other: &Self) -> bool {
+        let a = self.in_order();
+        let b = other.in_order();
+        a.len() == b.len()
+            && a.iter()
+                .zip(b.iter())
+                .all(|(&a, &b)| self.nodes[a].freq == other.nodes[b].freq)
+    }
+}
+
+impl<F: Eq + Frequency> Eq for CumulFreqTable<F> {}
+
+/// Read the frequency of a position, like [crate::CumulFreqTable::freq].
+impl<F> Index<usize> for CumulFreqTable<F>
+where
+    F: Frequency,
+{
+    type Output = F;
+
+    /// Panics if pos is out of bounds.
+    /// O(㏒₂ len) expected.
+    fn index(&self, pos: usize) -> &F {
+        assert!(pos < self.size(self.root), "pos out of bounds");
+        &self.nodes[self.find(pos)].freq
+    }
+}
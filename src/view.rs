@@ -0,0 +1,232 @@
+use crate::{CumulFreqTable, Frequency};
+use std::ops::RangeBounds;
+
+/// A read-only view of a contiguous range of positions of a table, re-based so the first
+/// position of the range is position 0, and its cumulative frequencies start from zero: a
+/// conditional distribution over a class of symbols, without copying the frequencies.
+///
+/// # Example
+///
+/// ```rust
+/// use cumulfreqtable::{BinaryIndexedTree, CumulFreqTable};
+///
+/// let table = BinaryIndexedTree::<u32>::from_freqs([5, 1, 2, 3, 4]);
+/// let view = table.view(1..4);
+/// assert_eq!(view.len(), 3);
+/// assert_eq!(view.freq(0), 1);
+/// assert_eq!(view.sum(2), 6);
+/// assert_eq!(view.total(), 6);
+/// assert_eq!(view.find_by_sum(4), 2);
+/// ```
+#[derive(Debug)]
+pub struct TableView<'a, T, F = usize> {
+    table: &'a T,
+    first: usize,
+    len: usize,
+    // The cumulative frequency before the first position.
+    base: F,
+}
+
+impl<T, F: Copy> Clone for TableView<'_, T, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, F: Copy> Copy for TableView<'_, T, F> {}
+
+impl<'a, T, F> TableView<'a, T, F>
+where
+    T: CumulFreqTable<F>,
+    F: Frequency,
+{
+    /// View the positions of the table in range.
+    /// Panics if the range is empty or out of bounds.
+    /// O(㏒₂ len).
+    pub fn new<R: RangeBounds<usize>>(table: &'a T, range: R) -> Self {
+        let (first, last) =
+            crate::inclusive_range(range, table.len()).expect("range must be non-empty");
+        Self {
+            table,
+            first,
+            len: last - first + 1,
+            base: table.sum_exclusive(first),
+        }
+    }
+
+    /// The position of the table where the view starts.
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    /// The underlying table.
+    pub fn table(&self) -> &'a T {
+        self.table
+    }
+
+    /// Get the number of positions of the view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always false, views are non-empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the frequency of the given position of the view.
+    /// Panics if pos is out of the view.
+    pub fn freq(&self, pos: usize) -> F {
+        assert!(pos < self.len, "pos out of bounds");
+        self.table.freq(self.first + pos)
+    }
+
+    /// Get the cumulative frequency of the given position of the view, from the start of the view.
+    /// Panics if pos is out of the view.
+    pub fn sum(&self, pos: usize) -> F {
+        assert!(pos < self.len, "pos out of bounds");
+        self.table.sum(self.first + pos) - self.base
+    }
+
+    /// The total frequency of the view.
+    pub fn total(&self) -> F {
+        self.sum(self.len - 1)
+    }
+
+    /// Find the first position of the view with an equal or greater cumulative frequency.
+    /// Returns the last position if sum is greater than the total.
+    /// O(㏒₂ len) with a tree based table.
+    pub fn find_by_sum(&self, sum: F) -> usize {
+        // The positions before the view all have a cumulative frequency at or below the base,
+        // the ones after at or above the total.
+        let pos = self.table.find_by_sum(sum + self.base);
+        pos.clamp(self.first, self.first + self.len - 1) - self.first
+    }
+
+    /// Iterate over the `(position, frequency)` pairs of the view, in position order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, F)> + 'a {
+        let (table, first) = (self.table, self.first);
+        (0..self.len).map(move |pos| (pos, table.freq(first + pos)))
+    }
+}
+
+/// A view of a contiguous range of positions of a table, like [TableView], that can also update
+/// the frequencies of the range. The updates go through the table, so its total stays
+/// consistent, and the positions outside of the range cannot be changed: a worker can be handed
+/// a slice of the alphabet.
+///
+/// # Example
+///
+/// ```rust
+/// use cumulfreqtable::{CumulFreqTable, FreqTable};
+///
+/// let mut table = FreqTable::<u32>::from_freqs([5, 1, 2, 3, 4]);
+/// let mut view = table.view_mut(1..4);
+/// view.inc(0);
+/// view.add(2, 10);
+/// assert_eq!(view.total(), 17);
+/// assert_eq!(table.total(), 26);
+/// assert_eq!(table.freq(3), 13);
+/// ```
+#[derive(Debug)]
+pub struct TableViewMut<'a, T, F = usize> {
+    table: &'a mut T,
+    first: usize,
+    len: usize,
+    // The cumulative frequency before the first position, unchanged by the updates of the range.
+    base: F,
+}
+
+impl<'a, T, F> TableViewMut<'a, T, F>
+where
+    T: CumulFreqTable<F>,
+    F: Frequency,
+{
+    /// View the positions of the table in range.
+    /// Panics if the range is empty or out of bounds.
+    /// O(㏒₂ len).
+    pub fn new<R: RangeBounds<usize>>(table: &'a mut T, range: R) -> Self {
+        let view = TableView::new(&*table, range);
+        let (first, len, base) = (view.first, view.len, view.base);
+        Self {
+            table,
+            first,
+            len,
+            base,
+        }
+    }
+
+    /// A read-only view of the same range.
+    pub fn view(&self) -> TableView<'_, T, F> {
+        TableView {
+            table: &*self.table,
+            first: self.first,
+            len: self.len,
+            base: self.base,
+        }
+    }
+
+    /// The position of the table where the view starts.
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    /// Get the number of positions of the view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always false, views are non-empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the frequency of the given position of the view.
+    /// Panics if pos is out of the view.
+    pub fn freq(&self, pos: usize) -> F {
+        self.view().freq(pos)
+    }
+
+    /// Get the cumulative frequency of the given position of the view, from the start of the view.
+    /// Panics if pos is out of the view.
+    pub fn sum(&self, pos: usize) -> F {
+        self.view().sum(pos)
+    }
+
+    /// The total frequency of the view.
+    pub fn total(&self) -> F {
+        self.view().total()
+    }
+
+    /// Find the first position of the view with an equal or greater cumulative frequency.
+    /// Returns the last position if sum is greater than the total.
+    pub fn find_by_sum(&self, sum: F) -> usize {
+        self.view().find_by_sum(sum)
+    }
+
+    /// Add to the frequency of the given position of the view.
+    /// Panics if pos is out of the view.
+    pub fn add(&mut self, pos: usize, val: F) {
+        assert!(pos < self.len, "pos out of bounds");
+        self.table.add(self.first + pos, val);
+    }
+
+    /// Substract to the frequency of the given position of the view.
+    /// Panics if pos is out of the view.
+    pub fn sub(&mut self, pos: usize, val: F) {
+        assert!(pos < self.len, "pos out of bounds");
+        self.table.sub(self.first + pos, val);
+    }
+
+    /// Add one to the frequency of the given position of the view.
+    /// Panics if pos is out of the view.
+    pub fn inc(&mut self, pos: usize) {
+        self.add(pos, 1.into());
+    }
+
+    /// Substract one to the frequency of the given position of the view.
+    /// Panics if pos is out of the view.
+    pub fn dec(&mut self, pos: usize) {
+        self.sub(pos, 1.into());
+    }
+}
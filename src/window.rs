@@ -0,0 +1,72 @@
+use crate::CumulFreqTable;
+use std::collections::VecDeque;
+
+/// A table reflecting exactly the last N observations.
+/// A ring buffer keeps the observed positions. When the window is full, the oldest observation is
+/// forgotten: its frequency is decremented as the new one is incremented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlidingWindowTable<T, F = usize> {
+    table: T,
+    window: VecDeque<usize>,
+    window_len: usize,
+    _freq: std::marker::PhantomData<F>,
+}
+
+impl<T, F> SlidingWindowTable<T, F>
+where
+    T: CumulFreqTable<F>,
+    F: From<u8>,
+{
+    /// Create a table of len positions remembering the last window_len observations.
+    /// Panics if len < 1 or window_len < 1.
+    pub fn new(len: usize, window_len: usize) -> Self {
+        assert!(window_len > 0, "window must be non-empty");
+        Self {
+            table: T::new(len),
+            window: VecDeque::with_capacity(window_len),
+            window_len,
+            _freq: std::marker::PhantomData,
+        }
+    }
+
+    /// Increment the frequency of the position, and decrement the frequency of the expired
+    /// observation if the window is full.
+    /// Panics if pos is out of bounds.
+    pub fn observe(&mut self, pos: usize) {
+        self.table.inc(pos);
+        if self.window.len() == self.window_len {
+            // SAFETY: the window is full, and non-empty.
+            let expired = unsafe { self.window.pop_front().unwrap_unchecked() };
+            self.table.dec(expired);
+        }
+        self.window.push_back(pos);
+    }
+
+    /// The maximum number of observations remembered.
+    pub fn window_len(&self) -> usize {
+        self.window_len
+    }
+
+    /// The observations in the window, from the oldest to the newest.
+    pub fn observations(&self) -> impl Iterator<Item = usize> + '_ {
+        self.window.iter().copied()
+    }
+
+    /// The memory used in bytes, inline and on the heap, see [CumulFreqTable::memory_bytes].
+    /// Includes the remembered observations.
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<T>()
+            + self.window.capacity() * std::mem::size_of::<usize>()
+            + self.table.memory_bytes()
+    }
+
+    /// The underlying table.
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Return the underlying table.
+    pub fn into_inner(self) -> T {
+        self.table
+    }
+}